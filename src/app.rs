@@ -5,6 +5,8 @@ use std::cmp::min;
 use std::num::NonZeroU32;
 
 use crate::Change;
+use crate::Clock;
+use crate::Frame;
 use crate::Instant;
 use crate::Tick;
 use crate::game::Game;
@@ -12,6 +14,8 @@ use crate::keys;
 use crate::keys::Key;
 use crate::keys::KeyRepeat;
 use crate::keys::Keys as KeysT;
+use crate::replay::Action;
+use crate::replay::Tape;
 
 type Keys<K> = KeysT<K, Instant>;
 
@@ -22,16 +26,24 @@ pub(crate) struct App<O> {
   game: Game,
   keys: Keys<Key>,
   was_paused: bool,
+  /// The recording or playback of inputs.
+  tape: Tape,
+  /// The clock mapping wall clock time onto frames of the game.
+  clock: Clock,
 }
 
 impl<O> App<O> {
-  pub fn new(ops: O, game: Game, keys: Keys<Key>) -> Self {
+  pub fn new(ops: O, game: Game, keys: Keys<Key>, tape: Tape) -> Self {
     let was_paused = game.is_paused();
+    // A resumed game continues where it left off.
+    let clock = Clock::with_frame(Instant::now(), game.frame());
     Self {
       ops,
       game,
       keys,
       was_paused,
+      tape,
+      clock,
     }
   }
 
@@ -48,7 +60,7 @@ impl<O> App<O> {
       if !self.was_paused {
         // The game was not paused when we lost focus. That means
         // we ended up pausing it. Unpause it again.
-        let () = self.game.pause(false);
+        let _change = Self::dispatch(Action::Resume, &mut self.game, &mut self.tape);
       }
     } else {
       self.was_paused = self.game.is_paused();
@@ -57,7 +69,7 @@ impl<O> App<O> {
         // focus. Pause it, as the user will no longer have a
         // chance to control it and it's not great to have it
         // actively running in the background.
-        let () = self.game.pause(true);
+        let _change = Self::dispatch(Action::Pause, &mut self.game, &mut self.tape);
       }
 
       // We may not get informed about key releases once unfocused.
@@ -71,50 +83,134 @@ impl<O> App<O> {
     let () = self.game.update_view(Some(phys_w), Some(phys_h));
   }
 
-  fn handle_key(key: &Key, repeat: &mut KeyRepeat, game: &mut Game) -> Change {
+  /// Perform an action on the game.
+  fn perform(action: Action, game: &mut Game) -> Change {
+    match action {
+      Action::RotateLeft => game.on_rotate_left(),
+      Action::RotateRight => game.on_rotate_right(),
+      Action::MoveLeft => game.on_move_left(),
+      Action::MoveDown => game.on_move_down(),
+      Action::MoveRight => game.on_move_right(),
+      Action::Drop => game.on_drop(),
+      Action::Hold => game.on_hold(),
+      Action::Restart => game.restart(),
+      Action::ToggleAutoPlay => {
+        if let Err(err) = game.auto_play(!game.is_auto_playing()) {
+          eprintln!("warning: failed to start auto-play: {err:#}");
+        }
+        Change::Unchanged
+      },
+      Action::TogglePause => {
+        let () = game.pause(!game.is_paused());
+        Change::Changed
+      },
+      Action::Pause => {
+        let () = game.pause(true);
+        Change::Changed
+      },
+      Action::Resume => {
+        let () = game.pause(false);
+        Change::Changed
+      },
+      Action::ToggleColorMode => {
+        let () = game.toggle_color_mode();
+        Change::Changed
+      },
+      Action::ToggleHint => {
+        let () = game.show_hint(!game.is_showing_hint());
+        Change::Changed
+      },
+    }
+  }
+
+  /// Perform an action triggered by the user, recording it if
+  /// requested.
+  ///
+  /// While a replay is being played back, only actions not affecting
+  /// the course of the game are performed.
+  fn dispatch(action: Action, game: &mut Game, tape: &mut Tape) -> Change {
+    match tape {
+      Tape::Off => Self::perform(action, game),
+      Tape::Record(replay) => {
+        let () = replay.push_action(game.frame(), action);
+        Self::perform(action, game)
+      },
+      Tape::Play { .. } => match action {
+        Action::ToggleColorMode | Action::ToggleHint => Self::perform(action, game),
+        _ => Change::Unchanged,
+      },
+    }
+  }
+
+  /// Handle a key press while the player is asked for a name to
+  /// record a new high score under.
+  fn handle_name_key(key: &Key, repeat: &mut KeyRepeat, game: &mut Game) -> Change {
+    *repeat = KeyRepeat::Disabled;
+
     match key {
+      k if *k == keys::KEY_CONFIRM => game.on_name_confirm(),
+      k if *k == keys::KEY_ERASE => game.on_name_erase(),
+      k => match keys::key_char(k) {
+        Some(c) => game.on_name_char(c),
+        None => Change::Unchanged,
+      },
+    }
+  }
+
+  fn handle_key(key: &Key, repeat: &mut KeyRepeat, game: &mut Game, tape: &mut Tape) -> Change {
+    if game.is_prompting() {
+      return Self::handle_name_key(key, repeat, game)
+    }
+
+    let action = match key {
       k if *k == keys::KEY_ROTATE_LEFT => {
         *repeat = KeyRepeat::Disabled;
-        game.on_rotate_left()
+        Action::RotateLeft
       },
       k if *k == keys::KEY_ROTATE_RIGHT => {
         *repeat = KeyRepeat::Disabled;
-        game.on_rotate_right()
+        Action::RotateRight
       },
-      k if *k == keys::KEY_MOVE_LEFT => game.on_move_left(),
-      k if *k == keys::KEY_MOVE_DOWN => game.on_move_down(),
-      k if *k == keys::KEY_MOVE_RIGHT => game.on_move_right(),
-      k if *k == keys::KEY_QUIT => Change::Quit,
+      k if *k == keys::KEY_MOVE_LEFT => Action::MoveLeft,
+      k if *k == keys::KEY_MOVE_DOWN => Action::MoveDown,
+      k if *k == keys::KEY_MOVE_RIGHT => Action::MoveRight,
+      k if *k == keys::KEY_QUIT => return Change::Quit,
       k if *k == keys::KEY_RESTART => {
         *repeat = KeyRepeat::Disabled;
-        game.restart()
+        Action::Restart
       },
       k if *k == keys::KEY_DROP => {
         *repeat = KeyRepeat::Disabled;
-        game.on_drop()
+        Action::Drop
+      },
+      k if *k == keys::KEY_HOLD => {
+        *repeat = KeyRepeat::Disabled;
+        Action::Hold
       },
       k if *k == keys::KEY_AUTO_PLAY => {
-        let () = game.auto_play(!game.is_auto_playing());
         *repeat = KeyRepeat::Disabled;
-        Change::Unchanged
+        Action::ToggleAutoPlay
       },
       k if *k == keys::KEY_PAUSE => {
-        let () = game.pause(!game.is_paused());
         *repeat = KeyRepeat::Disabled;
-        Change::Changed
+        Action::TogglePause
       },
       k if *k == keys::KEY_MODE => {
-        let () = game.toggle_color_mode();
         *repeat = KeyRepeat::Disabled;
-        Change::Changed
+        Action::ToggleColorMode
+      },
+      k if *k == keys::KEY_HINT => {
+        *repeat = KeyRepeat::Disabled;
+        Action::ToggleHint
       },
       #[cfg(feature = "debug")]
       k if *k == keys::KEY_DEBUG => {
         let () = game.dump_state();
-        Change::Unchanged
+        return Change::Unchanged
       },
-      _ => Change::Unchanged,
-    }
+      _ => return Change::Unchanged,
+    };
+    Self::dispatch(action, game, tape)
   }
 
   #[cfg(not(target_arch = "wasm32"))]
@@ -123,12 +219,70 @@ impl<O> App<O> {
   }
 
   pub fn tick_at(&mut self, now: Instant) -> (Change, Tick) {
+    let frame = self.clock.frame(now);
+
+    if let Tape::Play { .. } = self.tape {
+      return self.play_at(now, frame)
+    }
+
+    if let Tape::Record(replay) = &mut self.tape {
+      let () = replay.advance(frame);
+    }
+
+    // Fast-forward the game before handling input, so that any actions
+    // are performed on the state as of `frame`.
+    let (tick_change, _tick) = self.game.tick(frame);
     let (keys_change, keys_wait) = self.keys.tick(now, |key, repeat| {
-      Self::handle_key(key, repeat, &mut self.game)
+      Self::handle_key(key, repeat, &mut self.game, &mut self.tape)
+    });
+    // Actions may have changed when the game needs to be ticked next.
+    let (game_change, game_wait) = self.game.tick(frame);
+
+    let change = tick_change | keys_change | game_change;
+    let game_wait = game_wait.map(|frame| self.clock.instant(frame));
+    let tick = min(game_wait, Tick::from(keys_wait));
+    (change, tick)
+  }
+
+  /// Play back all recorded inputs up to `frame`.
+  fn play_at(&mut self, now: Instant, frame: Frame) -> (Change, Tick) {
+    let (mut change, keys_wait) = self.keys.tick(now, |key, repeat| {
+      Self::handle_key(key, repeat, &mut self.game, &mut self.tape)
     });
-    let (game_change, game_wait) = self.game.tick(now);
 
-    let change = keys_change | game_change;
+    let Tape::Play { replay, next } = &mut self.tape else {
+      unreachable!()
+    };
+
+    let frame = frame.min(replay.length());
+    while let Some(input) = replay.inputs().get(*next) {
+      if input.frame > frame {
+        break
+      }
+
+      change |= self.game.tick(input.frame).0;
+      if self.game.frame() < input.frame {
+        // The game is held back by the AI; the input can only be
+        // replayed once it reached the recorded frame.
+        break
+      }
+
+      for action in &input.actions {
+        change |= Self::perform(*action, &mut self.game);
+      }
+      *next += 1;
+    }
+
+    let (game_change, game_wait) = self.game.tick(frame);
+    change |= game_change;
+
+    // The game stops once the end of the replay has been reached.
+    let game_wait = if self.game.frame() < replay.length() {
+      let input_wait = Tick::from(replay.inputs().get(*next).map(|input| input.frame));
+      min(game_wait, input_wait).map(|frame| self.clock.instant(frame))
+    } else {
+      Tick::None
+    };
     let tick = min(game_wait, Tick::from(keys_wait));
     (change, tick)
   }
@@ -137,9 +291,16 @@ impl<O> App<O> {
     let () = self.game.render();
   }
 
+  /// Retrieve the game being played.
+  #[cfg(not(target_arch = "wasm32"))]
+  #[inline]
+  pub fn game(&self) -> &Game {
+    &self.game
+  }
+
   #[cfg(not(target_arch = "wasm32"))]
-  pub fn into_parts(self) -> (Game, Keys<Key>) {
-    (self.game, self.keys)
+  pub fn into_parts(self) -> (Game, Keys<Key>, Tape) {
+    (self.game, self.keys, self.tape)
   }
 
   #[cfg(not(target_arch = "wasm32"))]
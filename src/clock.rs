@@ -0,0 +1,133 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+
+use crate::Instant;
+
+
+/// The number of frames the game simulates per second.
+pub(crate) const FRAMES_PER_SEC: u64 = 60;
+/// The number of nanoseconds per second.
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+
+/// A frame of the game simulation, counted from the start of the game.
+pub type Frame = u64;
+
+
+/// Convert a duration into the number of frames it spans, rounding up.
+#[inline]
+pub(crate) fn duration_to_frames(duration: Duration) -> Frame {
+  (duration.as_nanos() * u128::from(FRAMES_PER_SEC)).div_ceil(NANOS_PER_SEC) as Frame
+}
+
+/// Convert a number of frames into the duration they span, rounding
+/// down.
+#[inline]
+pub(crate) fn frames_to_duration(frames: Frame) -> Duration {
+  let nanos = u128::from(frames) * NANOS_PER_SEC / u128::from(FRAMES_PER_SEC);
+  Duration::from_nanos(nanos as u64)
+}
+
+
+/// A clock mapping wall clock time onto frames of the game simulation.
+///
+/// The simulation itself is entirely frame based and independent of
+/// wall clock time. Front ends use a `Clock` to determine up to which
+/// frame to advance it.
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+  /// The instant at which frame `offset` started.
+  start: Instant,
+  /// The frame starting at `start`.
+  offset: Frame,
+}
+
+impl Clock {
+  /// Create a new clock with frame zero starting at `start`.
+  #[inline]
+  pub fn new(start: Instant) -> Self {
+    Self::with_frame(start, 0)
+  }
+
+  /// Create a new clock with the given frame starting at `start`.
+  ///
+  /// This constructor is useful for continuing a game that has
+  /// already been simulated up to `frame`.
+  #[inline]
+  pub fn with_frame(start: Instant, frame: Frame) -> Self {
+    Self {
+      start,
+      offset: frame,
+    }
+  }
+
+  /// Retrieve the frame current at the given instant.
+  pub fn frame(&self, now: Instant) -> Frame {
+    let elapsed = now.duration_since(self.start).as_nanos();
+    self.offset + (elapsed * u128::from(FRAMES_PER_SEC) / NANOS_PER_SEC) as Frame
+  }
+
+  /// Retrieve the instant at which the given frame starts.
+  ///
+  /// Frames preceding the clock's starting frame are reported as
+  /// starting along with it.
+  pub fn instant(&self, frame: Frame) -> Instant {
+    let frame = frame.saturating_sub(self.offset);
+    let nanos = (u128::from(frame) * NANOS_PER_SEC).div_ceil(u128::from(FRAMES_PER_SEC));
+    self.start + Duration::from_nanos(nanos as u64)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we convert between durations and frames correctly.
+  #[test]
+  fn duration_conversion() {
+    assert_eq!(duration_to_frames(Duration::ZERO), 0);
+    assert_eq!(duration_to_frames(Duration::from_millis(500)), 30);
+    assert_eq!(duration_to_frames(Duration::from_millis(501)), 31);
+    assert_eq!(duration_to_frames(Duration::from_secs(180)), 10800);
+
+    assert_eq!(frames_to_duration(0), Duration::ZERO);
+    assert_eq!(frames_to_duration(6), Duration::from_millis(100));
+    assert_eq!(frames_to_duration(10800), Duration::from_secs(180));
+  }
+
+  /// Make sure that a [`Clock`] maps the start of each frame back onto
+  /// the frame itself.
+  #[test]
+  fn clock_frames() {
+    let start = Instant::now();
+    let clock = Clock::new(start);
+
+    assert_eq!(clock.frame(start), 0);
+    assert_eq!(clock.frame(start + Duration::from_millis(16)), 0);
+    assert_eq!(clock.frame(start + Duration::from_millis(17)), 1);
+    assert_eq!(clock.frame(start + Duration::from_secs(1)), 60);
+
+    for frame in [0, 1, 2, 59, 60, 61, 12345] {
+      let instant = clock.instant(frame);
+      assert_eq!(clock.frame(instant), frame);
+      assert!(clock.frame(instant.checked_sub(Duration::from_nanos(1)).unwrap()) < frame.max(1));
+    }
+  }
+
+  /// Check that a [`Clock`] can start at an arbitrary frame.
+  #[test]
+  fn clock_offset() {
+    let start = Instant::now();
+    let clock = Clock::with_frame(start, 1000);
+
+    assert_eq!(clock.frame(start), 1000);
+    assert_eq!(clock.frame(start + Duration::from_secs(1)), 1060);
+    assert_eq!(clock.instant(1000), start);
+    assert_eq!(clock.instant(10), start);
+    assert_eq!(clock.frame(clock.instant(1234)), 1234);
+  }
+}
@@ -26,7 +26,7 @@ use crate::keys;
 
 /// Retrieve the default path to the program's configuration file.
 #[cfg(not(target_arch = "wasm32"))]
-fn default_config_path() -> Result<PathBuf> {
+pub(crate) fn default_config_path() -> Result<PathBuf> {
   use dirs::config_dir;
 
   let config = config_dir()
@@ -54,7 +54,7 @@ fn load_config_doc(path: &Path) -> Result<Option<DocumentMut>> {
   Ok(Some(doc))
 }
 
-fn load_config(path: &Path) -> Result<Config> {
+pub(crate) fn load_config(path: &Path) -> Result<Config> {
   let doc = load_config_doc(path)?;
 
   if let Some(doc) = doc {
@@ -70,11 +70,31 @@ fn load_config(path: &Path) -> Result<Config> {
 }
 
 
-fn save_config(config: &Config, path: &Path) -> Result<()> {
+pub(crate) fn save_config(config: &Config, path: &Path) -> Result<()> {
   macro_rules! update {
     ($doc:expr, $field:expr, as int) => {{
       $doc[stringify!($field)] = toml_edit::value(i64::from($field));
     }};
+    ($doc:expr, $field:expr, as str) => {{
+      $doc[stringify!($field)] = toml_edit::value($field.as_str());
+    }};
+    ($doc:expr, $field:expr, as array) => {{
+      $doc[stringify!($field)] = toml_edit::value(toml_edit::Array::from_iter($field));
+    }};
+    ($doc:expr, $field:expr, as path) => {{
+      if let Some(path) = &$field {
+        let path = path
+          .to_str()
+          .with_context(|| format!("path `{}` is not valid UTF-8", path.display()))?;
+        $doc[stringify!($field)] = toml_edit::value(path);
+      } else if let Some(table) = $doc.as_table_like_mut() {
+        let _item = table.remove(stringify!($field));
+      }
+    }};
+    ($doc:expr, $field:expr, as value) => {{
+      let value = Serialize::serialize(&$field, toml_edit::ser::ValueSerializer::new())?;
+      $doc[stringify!($field)] = toml_edit::value(value);
+    }};
     ($doc:expr, $field:expr) => {{
       $doc[stringify!($field)] = toml_edit::value($field);
     }};
@@ -104,9 +124,45 @@ fn save_config(config: &Config, path: &Path) -> Result<()> {
           field_width,
           field_height,
           preview_stone_count,
+          lock_delay_ms,
+          lock_reset_limit,
+          scoring,
+          randomizer,
+          stone_set,
+          stones,
+          gravity,
+          gravity_table,
+          gravity_base,
+          gravity_growth,
+          gravity_exponent,
+          rotation_system,
+          mode,
+          sprint_lines,
+          ultra_time_secs,
+          dig_rows,
+          dig_interval_ms,
           enable_ai,
+          ai_lookahead,
+          ai_time_budget_ms,
+          ai_difficulty,
+          ai_bot,
+          ai_bot_timeout_ms,
+          enable_hold,
+          enable_ghost,
           enable_dark_mode,
         },
+      ai:
+        game::AiWeights {
+          holes,
+          coverage,
+          stack,
+          roughness,
+          high_point,
+          high_point_exponent,
+          move_down,
+          move_sideways,
+          rotate,
+        },
     } = config.clone();
 
     let keyboard = &mut doc["keyboard"];
@@ -119,9 +175,44 @@ fn save_config(config: &Config, path: &Path) -> Result<()> {
     update!(game, field_width, as int);
     update!(game, field_height, as int);
     update!(game, preview_stone_count, as int);
+    update!(game, lock_delay_ms, as int);
+    update!(game, lock_reset_limit, as int);
+    update!(game, scoring, as str);
+    update!(game, randomizer, as str);
+    update!(game, stone_set, as str);
+    update!(game, stones, as value);
+    update!(game, gravity, as str);
+    update!(game, gravity_table, as array);
+    update!(game, gravity_base);
+    update!(game, gravity_growth);
+    update!(game, gravity_exponent);
+    update!(game, rotation_system, as str);
+    update!(game, mode, as str);
+    update!(game, sprint_lines, as int);
+    update!(game, ultra_time_secs, as int);
+    update!(game, dig_rows, as int);
+    update!(game, dig_interval_ms, as int);
     update!(game, enable_ai);
+    update!(game, ai_lookahead, as int);
+    update!(game, ai_time_budget_ms, as int);
+    update!(game, ai_difficulty, as str);
+    update!(game, ai_bot, as path);
+    update!(game, ai_bot_timeout_ms, as int);
+    update!(game, enable_hold);
+    update!(game, enable_ghost);
     update!(game, enable_dark_mode);
 
+    let ai = &mut doc["ai"];
+    update!(ai, holes);
+    update!(ai, coverage);
+    update!(ai, stack);
+    update!(ai, roughness);
+    update!(ai, high_point);
+    update!(ai, high_point_exponent, as int);
+    update!(ai, move_down);
+    update!(ai, move_sideways);
+    update!(ai, rotate);
+
     let () = write(path, doc.to_string())?;
   }
 
@@ -139,6 +230,9 @@ pub struct Config {
   /// Configuration of the game itself.
   #[serde(default)]
   pub game: game::Config,
+  /// The weights the AI uses for evaluating its options.
+  #[serde(default)]
+  pub ai: game::AiWeights,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -190,7 +284,31 @@ lines_for_level = 10
 field_width = 10
 field_height = 20
 preview_stone_count = 1
+lock_delay_ms = 500
+lock_reset_limit = 15
+scoring = "legacy"
+randomizer = "uniform"
+stone_set = "tetrominoes"
+stones = []
+gravity = "classic"
+gravity_table = []
+gravity_base = 1.0
+gravity_growth = 0.2
+gravity_exponent = 1.0
+rotation_system = "nrs"
+mode = "marathon"
+sprint_lines = 40
+ultra_time_secs = 180
+dig_rows = 10
+dig_interval_ms = 0
 enable_ai = false
+ai_lookahead = 1
+ai_time_budget_ms = 0
+ai_difficulty = "perfect"
+ai_bot = "/usr/bin/tetromino-bot"
+ai_bot_timeout_ms = 1000
+enable_hold = false
+enable_ghost = false
 enable_dark_mode = false
     "#;
     assert!(from_toml_str::<Config>(config).is_ok());
@@ -250,4 +368,52 @@ lines_for_level = 10
     let loaded = load_config(path).unwrap();
     assert_eq!(loaded, config);
   }
+
+  /// Check that custom stone definitions survive a round trip through
+  /// the configuration file.
+  #[test]
+  fn save_config_custom_stones() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path();
+
+    let mut config = Config::default();
+    config.game.stone_set = game::StoneSet::Custom;
+    config.game.stones = vec![
+      game::StoneConfig {
+        shape: vec![".#.".to_string(), "###".to_string()],
+        color: Some(game::PieceColor::Cyan),
+      },
+      game::StoneConfig {
+        shape: vec!["##".to_string()],
+        color: None,
+      },
+    ];
+    let () = save_config(&config, path).unwrap();
+
+    let loaded = load_config(path).unwrap();
+    assert_eq!(loaded, config);
+  }
+
+  /// Check that AI weights can be configured partially, with defaults
+  /// filling in the rest, and that they survive a round trip through
+  /// the configuration file.
+  #[test]
+  fn save_config_ai_weights() {
+    let config = r#"
+[ai]
+holes = 5.0
+high_point_exponent = 8
+    "#;
+    let config = from_toml_str::<Config>(config).unwrap();
+    assert_eq!(config.ai.holes, 5.0);
+    assert_eq!(config.ai.high_point_exponent, 8);
+    assert_eq!(config.ai.roughness, game::AiWeights::default().roughness);
+
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path();
+    let () = save_config(&config, path).unwrap();
+
+    let loaded = load_config(path).unwrap();
+    assert_eq!(loaded, config);
+  }
 }
@@ -1,10 +1,13 @@
 // Copyright (C) 2023 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use serde::Deserialize;
+use serde::Serialize;
 
 /// A enumeration of the actions to be performed on a stone.
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum Action {
   Merge,
   MoveDown,
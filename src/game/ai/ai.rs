@@ -1,59 +1,492 @@
 // Copyright (C) 2023-2026 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-#[cfg(debug_assertions)]
+use std::cell::RefCell;
+#[cfg(target_arch = "wasm32")]
 use std::rc::Rc;
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::AtomicBool;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::Ordering;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::Receiver;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::Sender;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::channel;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread::spawn;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use anyhow::Result;
+
+use crate::Instant;
 
 use super::Action;
+use super::Arena;
+use super::Bot;
 use super::Field;
 use super::Stone;
+use super::Suggestion;
+use super::Weights;
 use super::actions;
+use super::placement;
 use super::search;
+use super::search_ranked;
+use super::search_target;
 
 
-/// A type used for calculating and managing the estimated best actions
-/// for playing a game of Tetris.
+/// Ask `bot` for the actions to perform on `stone`, along with the
+/// field to expect once they have been performed, if known.
+fn ask(
+  arena: &mut Arena,
+  bot: &Bot,
+  field: &Field,
+  weights: &Weights,
+  stone: &Stone,
+  next_stones: &[Stone],
+  hold: Option<&Stone>,
+) -> Result<(Vec<Action>, Option<Field>)> {
+  match bot.suggest(field, stone, next_stones, hold)? {
+    Suggestion::Actions(mut actions) => {
+      // Once merged, the stone can no longer be acted upon, so any
+      // actions following the merge are ignored.
+      if let Some(merge) = actions.iter().position(|action| *action == Action::Merge) {
+        let () = actions.truncate(merge + 1);
+      }
+      Ok((actions, None))
+    },
+    Suggestion::Placement(pieces) => {
+      let best = search_target(arena, field, weights, stone, &pieces)
+        .with_context(|| format!("placement {pieces:?} cannot be reached"))?;
+      let (actions, field) = actions(arena, Some(best));
+      Ok((actions.collect(), field.cloned()))
+    },
+  }
+}
+
+
+/// The means by which an [`AI`] plans its actions.
+#[derive(Clone, Debug)]
+pub(crate) enum Planner {
+  /// Search for the best actions, within the given time budget, if
+  /// any.
+  Search(Option<Duration>),
+  /// Ask an external bot.
+  Bot(Arc<Bot>),
+}
+
+
+/// Everything needed for calculating the plan for a single stone.
 #[derive(Debug)]
-pub(crate) struct AI {
+struct Request {
+  /// The means by which to calculate the plan.
+  planner: Planner,
+  /// The field to plan on.
+  field: Field,
+  /// The weights to search with.
+  weights: Weights,
+  /// The stone to plan for.
+  stone: Stone,
+  /// The preview stones to take into account.
+  next_stones: Vec<Stone>,
+  /// The stone on hold, if any.
+  hold: Option<Stone>,
+  /// The rank of the placement to pick, with zero being the best one.
+  rank: usize,
+}
+
+
+/// The actions calculated for a single stone.
+#[derive(Debug, Default)]
+struct Plan {
   /// The list of actions to be performed.
   actions: Vec<Action>,
-  /// The index of the next action.
-  index: usize,
   /// The field that we expect to have once all actions have been
   /// executed.
   #[cfg(debug_assertions)]
-  expected_field: Option<Rc<Field>>,
+  expected_field: Option<Field>,
+  /// The location at which the stone gets merged once all actions have
+  /// been executed, if known.
+  placement: Option<Stone>,
+  /// The time it took to calculate the plan, if not yet reported.
+  time: Option<Duration>,
 }
 
-impl AI {
-  /// Create a new `AI` using the provided `Field`, `Stone`, and
-  /// an optional list of preview stones.
-  pub fn new(field: &Field, stone: &Stone, next_stones: &[Stone]) -> Self {
-    let best = search(field, stone, next_stones);
-    let (actions, _field) = actions(best);
+impl Plan {
+  /// Calculate the plan for the given request, creating all search
+  /// states inside of `arena`.
+  ///
+  /// When searching, the best plan is picked, or a deliberately worse
+  /// one if the requested rank is non-zero. Bots make their own
+  /// decisions and the rank does not apply to them. A search ends
+  /// early once `cancelled` reports that the plan is no longer needed.
+  fn calculate(arena: &mut Arena, request: &Request, cancelled: impl Fn() -> bool) -> Self {
+    let Request {
+      planner,
+      field,
+      weights,
+      stone,
+      next_stones,
+      hold,
+      rank,
+    } = request;
+
+    let start = Instant::now();
+    let (actions, _field, placement) = match planner {
+      Planner::Search(budget) => {
+        let deadline = budget.map(|budget| start + budget);
+        let expired = || cancelled() || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        let best = if *rank == 0 {
+          search(arena, field, weights, stone, next_stones, expired)
+        } else {
+          search_ranked(arena, field, weights, stone, *rank)
+        };
+        let placement = placement(arena, best);
+        let (actions, field) = actions(arena, best);
+        (actions.collect(), field.cloned(), placement)
+      },
+      Planner::Bot(bot) => {
+        let (actions, field) = ask(
+          arena,
+          bot,
+          field,
+          weights,
+          stone,
+          next_stones,
+          hold.as_ref(),
+        )
+        .unwrap_or_else(|err| {
+          // A misbehaving bot just leaves the stone to gravity.
+          eprintln!(
+            "warning: bot `{}` failed to play stone: {err:#}",
+            bot.name()
+          );
+          (Vec::new(), None)
+        });
+        (actions, field, None)
+      },
+    };
 
     Self {
+      actions,
       #[cfg(debug_assertions)]
       expected_field: _field,
-      actions: actions.collect(),
+      placement,
+      time: Some(Instant::now().duration_since(start)),
+    }
+  }
+}
+
+
+/// A plan that may still be in the process of being calculated.
+#[derive(Debug)]
+enum Planning {
+  /// The plan is available.
+  Ready(Plan),
+  /// The plan is being calculated by a [`Worker`].
+  #[cfg(not(target_arch = "wasm32"))]
+  Pending {
+    /// The channel over which the plan is delivered.
+    plan: Receiver<Plan>,
+    /// The flag telling the worker that the plan is no longer needed.
+    cancel: Arc<AtomicBool>,
+  },
+}
+
+impl Planning {
+  /// Retrieve the plan, waiting for the calculation to finish if it is
+  /// still in progress.
+  fn wait(&mut self) -> &mut Plan {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Self::Pending { plan, .. } = self {
+      // The worker does not terminate before replying unless there is
+      // a bug, in which case its panic has been reported already.
+      let plan = plan.recv().expect("AI worker terminated unexpectedly");
+      *self = Self::Ready(plan);
+    }
+
+    match self {
+      Self::Ready(plan) => plan,
+      #[cfg(not(target_arch = "wasm32"))]
+      Self::Pending { .. } => unreachable!(),
+    }
+  }
+
+  /// Check whether the plan is available without waiting for it.
+  fn is_ready(&mut self) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Self::Pending { plan, .. } = self
+      && let Ok(plan) = plan.try_recv()
+    {
+      *self = Self::Ready(plan);
+    }
+
+    matches!(self, Self::Ready(..))
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for Planning {
+  fn drop(&mut self) {
+    // A plan that is dropped before it is available is no longer
+    // needed and the worker can stop calculating it.
+    if let Self::Pending { cancel, .. } = self {
+      let () = cancel.store(true, Ordering::Relaxed);
+    }
+  }
+}
+
+
+/// A long-lived worker calculating plans.
+///
+/// On native targets, plans are calculated on a background thread,
+/// which terminates once the worker and all its clones are dropped. On
+/// Wasm, they are calculated synchronously. Clones share the same
+/// worker, which calculates one plan at a time.
+#[derive(Clone, Debug)]
+pub(crate) struct Worker {
+  /// The channel over which requests are sent to the worker thread,
+  /// along with the flag indicating their cancellation and the
+  /// channel to deliver the plan over.
+  #[cfg(not(target_arch = "wasm32"))]
+  requests: Sender<(Request, Arc<AtomicBool>, Sender<Plan>)>,
+  /// The arena used for searching, kept around for reuse.
+  #[cfg(target_arch = "wasm32")]
+  arena: Rc<RefCell<Arena>>,
+}
+
+impl Worker {
+  #[cfg(not(target_arch = "wasm32"))]
+  pub fn new() -> Self {
+    let (requests, receiver) = channel::<(Request, Arc<AtomicBool>, Sender<Plan>)>();
+    let _handle = spawn(move || {
+      // All searches reuse the memory allocated by previous ones.
+      let mut arena = Arena::default();
+      for (request, cancel, reply) in receiver {
+        let cancelled = || cancel.load(Ordering::Relaxed);
+        if cancelled() {
+          continue
+        }
+
+        let plan = Plan::calculate(&mut arena, &request, cancelled);
+        // The plan may have been dropped in the mean time, in which
+        // case nobody is interested in it anymore.
+        let _result = reply.send(plan);
+      }
+    });
+
+    Self { requests }
+  }
+
+  #[cfg(target_arch = "wasm32")]
+  pub fn new() -> Self {
+    Self {
+      arena: Rc::new(RefCell::new(Arena::default())),
+    }
+  }
+
+  /// Start calculating the plan for the given request.
+  #[cfg(not(target_arch = "wasm32"))]
+  fn start(&self, request: Request) -> Planning {
+    let (reply, plan) = channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+    // SANITY: The worker thread only terminates once we hang up,
+    //         unless there is a bug.
+    let () = self
+      .requests
+      .send((request, Arc::clone(&cancel), reply))
+      .expect("AI worker terminated unexpectedly");
+    Planning::Pending { plan, cancel }
+  }
+
+  /// Calculate the plan for the given request.
+  #[cfg(target_arch = "wasm32")]
+  fn start(&self, request: Request) -> Planning {
+    let mut arena = self.arena.borrow_mut();
+    Planning::Ready(Plan::calculate(&mut arena, &request, || false))
+  }
+}
+
+
+/// A type used for calculating and managing the estimated best actions
+/// for playing a game of Tetris.
+///
+/// The calculation happens in the background, where supported. All
+/// methods inquiring actions wait for it to finish.
+#[derive(Debug)]
+pub(crate) struct AI {
+  /// The plan for the current stone.
+  plan: RefCell<Planning>,
+  /// The index of the next action.
+  index: usize,
+  /// The means by which plans are calculated.
+  planner: Planner,
+  /// The rank of the placement planned for the current stone, with
+  /// zero being the best one.
+  rank: usize,
+  /// The worker calculating plans, possibly shared with other `AI`
+  /// instances.
+  worker: Worker,
+}
+
+impl AI {
+  /// Create a new `AI` using the provided `Field`, `Stone`, and
+  /// an optional list of preview stones as well as the stone on hold.
+  ///
+  /// If searching with a time budget, the search for the best actions
+  /// ends once it is used up, and the best ones found so far are
+  /// used.
+  ///
+  /// A non-zero `rank` makes the `AI` deliberately pick a worse
+  /// placement for the stone, namely the `rank`-th best one when
+  /// considering the stone on its own.
+  ///
+  /// Plans are calculated by `worker`.
+  #[expect(clippy::too_many_arguments)]
+  pub fn new(
+    worker: &Worker,
+    planner: Planner,
+    field: &Field,
+    weights: &Weights,
+    stone: &Stone,
+    next_stones: &[Stone],
+    hold: Option<&Stone>,
+    rank: usize,
+  ) -> Self {
+    let mut slf = Self::with_actions(worker, Vec::new(), planner);
+    slf.rank = rank;
+    let () = slf.plan(field, weights, stone, next_stones, hold);
+    slf
+  }
+
+  /// Create an `AI` that continues with the provided actions, as
+  /// previously retrieved via [`AI::remaining`].
+  pub fn with_actions(worker: &Worker, actions: Vec<Action>, planner: Planner) -> Self {
+    let plan = Plan {
+      actions,
+      // An `AI` continuing with a list of actions does not know the
+      // field to expect.
+      #[cfg(debug_assertions)]
+      expected_field: None,
+      placement: None,
+      time: None,
+    };
+
+    Self {
+      plan: RefCell::new(Planning::Ready(plan)),
       index: 0,
+      planner,
+      rank: 0,
+      worker: worker.clone(),
     }
   }
 
-  /// Advance to the next stone.
+  /// Start planning for the given stone, replacing the current plan.
+  fn plan(
+    &mut self,
+    field: &Field,
+    weights: &Weights,
+    stone: &Stone,
+    next_stones: &[Stone],
+    hold: Option<&Stone>,
+  ) {
+    let request = Request {
+      planner: self.planner.clone(),
+      field: field.clone(),
+      weights: *weights,
+      stone: stone.clone(),
+      next_stones: next_stones.to_vec(),
+      hold: hold.cloned(),
+      rank: self.rank,
+    };
+    // Replacing a plan still being calculated cancels it.
+    self.plan = RefCell::new(self.worker.start(request));
+    self.index = 0;
+  }
+
+  /// Advance to the next stone, planning to merge it at the `rank`-th
+  /// best location (see [`AI::new`]).
   ///
   /// # Notes
   /// This method assumes (and potentially checks) that `field` is
   /// *actually* the previously calculated field.
-  pub fn advance_stone(&mut self, field: &Field, stone: &Stone, next_stones: &[Stone]) {
+  pub fn advance_stone(
+    &mut self,
+    field: &Field,
+    weights: &Weights,
+    stone: &Stone,
+    next_stones: &[Stone],
+    hold: Option<&Stone>,
+    rank: usize,
+  ) {
+    let _plan = self.plan.get_mut().wait();
     #[cfg(debug_assertions)]
-    debug_assert_eq!(self.expected_field.as_deref(), Some(field));
-    *self = Self::new(field, stone, next_stones);
+    if let Some(expected_field) = &_plan.expected_field {
+      debug_assert_eq!(expected_field, field);
+    }
+    self.rank = rank;
+    let () = self.plan(field, weights, stone, next_stones, hold);
+  }
+
+  /// Plan anew for the current stone, starting at its current
+  /// location, e.g., because it moved differently than planned.
+  ///
+  /// The rank of the placement to pick is retained. As replanning may
+  /// happen repeatedly for a single stone, searches do not take into
+  /// account preview stones, to keep them cheap. Bots are asked only
+  /// once per stone, so an `AI` driven by one keeps its remaining
+  /// actions instead.
+  pub fn replan(&mut self, field: &Field, weights: &Weights, stone: &Stone) {
+    match self.planner {
+      Planner::Search(..) => self.plan(field, weights, stone, &[], None),
+      Planner::Bot(..) => (),
+    }
+  }
+
+  /// Check whether the actions for the current stone are available,
+  /// without waiting for them.
+  pub fn is_ready(&self) -> bool {
+    self.plan.borrow_mut().is_ready()
+  }
+
+  /// Wait for the actions for the current stone to become available.
+  ///
+  /// Returns the time it took to calculate them, if it has not been
+  /// reported before.
+  pub fn wait(&mut self) -> Option<Duration> {
+    self.plan.get_mut().wait().time.take()
   }
 
   /// Take a peek at the next upcoming [`Action`].
   pub fn peek(&self) -> Option<Action> {
-    self.actions.get(self.index).copied()
+    self
+      .plan
+      .borrow_mut()
+      .wait()
+      .actions
+      .get(self.index)
+      .copied()
+  }
+
+  /// Retrieve the location at which the current stone gets merged
+  /// once all actions have been performed, if known.
+  pub fn placement(&self) -> Option<Stone> {
+    self.plan.borrow_mut().wait().placement.clone()
+  }
+
+  /// Retrieve the actions yet to be performed.
+  pub fn remaining(&self) -> Vec<Action> {
+    self
+      .plan
+      .borrow_mut()
+      .wait()
+      .actions
+      .get(self.index..)
+      .unwrap_or_default()
+      .to_vec()
   }
 }
 
@@ -61,8 +494,8 @@ impl Iterator for AI {
   type Item = Action;
 
   fn next(&mut self) -> Option<Self::Item> {
-    let action = self.actions.get(self.index);
+    let action = self.plan.get_mut().wait().actions.get(self.index).copied();
     self.index = self.index.saturating_add(1);
-    action.copied()
+    action
   }
 }
@@ -0,0 +1,487 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::io::BufRead as _;
+use std::io::BufReader;
+use std::io::Result as IoResult;
+use std::io::Write as _;
+use std::path::Path;
+use std::process::Child;
+use std::process::ChildStdin;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::channel;
+use std::thread::spawn;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use anyhow::Result;
+use anyhow::bail;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Instant;
+use crate::Point;
+
+use super::super::Fieldlike as _;
+use super::super::RotationSystem;
+use super::super::Stonelike as _;
+use super::Action;
+use super::Field;
+use super::Stone;
+
+
+/// The time a bot may take to start up and acknowledge the rules.
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+
+/// A message sent to a bot.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+  /// The rules of the game being played.
+  Rules {
+    width: i16,
+    height: i16,
+    rotation_system: RotationSystem,
+  },
+  /// A request for the placement of the active stone.
+  Suggest {
+    field: Vec<Vec<bool>>,
+    stone: Vec<Point<i16>>,
+    queue: Vec<Vec<Point<i16>>>,
+    hold: Option<Vec<Point<i16>>>,
+  },
+  /// A request for the bot to exit.
+  Quit,
+}
+
+
+/// A message received from a bot.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Response {
+  /// Information about the bot.
+  Info { name: String },
+  /// An indication that the bot is ready to play.
+  Ready,
+  /// The bot's suggestion for the active stone.
+  Suggestion {
+    #[serde(default)]
+    placement: Option<Vec<Point<i16>>>,
+    #[serde(default)]
+    actions: Option<Vec<Action>>,
+  },
+  /// An error the bot encountered.
+  Error { reason: String },
+}
+
+
+/// A suggestion made by a bot for the active stone.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Suggestion {
+  /// The locations of the stone's pieces once merged.
+  Placement(Vec<Point<i16>>),
+  /// The actions to perform on the stone.
+  Actions(Vec<Action>),
+}
+
+
+/// The running bot process, along with the means for communicating
+/// with it.
+#[derive(Debug)]
+struct Process {
+  child: Child,
+  stdin: ChildStdin,
+  /// The lines the bot writes to its standard output, as read by a
+  /// dedicated thread.
+  lines: Receiver<IoResult<String>>,
+  /// The number of responses to requests we gave up waiting for, which
+  /// are still to arrive.
+  stale: usize,
+}
+
+impl Process {
+  fn send(&mut self, request: &Request) -> Result<()> {
+    let mut line = serde_json::to_vec(request).context("failed to serialize request")?;
+    let () = line.push(b'\n');
+    let () = self
+      .stdin
+      .write_all(&line)
+      .and_then(|()| self.stdin.flush())
+      .context("failed to send request")?;
+    Ok(())
+  }
+
+  fn receive(&mut self, timeout: Duration) -> Result<Response> {
+    let deadline = Instant::now() + timeout;
+    let line = loop {
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      let line = match self.lines.recv_timeout(remaining) {
+        Ok(line) => line.context("failed to receive response")?,
+        Err(RecvTimeoutError::Timeout) => {
+          // The response may still arrive later on, at which point we
+          // have to skip it.
+          self.stale += 1;
+          bail!("bot did not respond within {timeout:?}")
+        },
+        Err(RecvTimeoutError::Disconnected) => bail!("bot exited unexpectedly"),
+      };
+
+      if self.stale > 0 {
+        self.stale -= 1;
+      } else {
+        break line
+      }
+    };
+
+    let response = serde_json::from_str::<Response>(&line)
+      .with_context(|| format!("received invalid response `{line}`"))?;
+    if let Response::Error { reason } = response {
+      bail!("bot reported error: {reason}")
+    }
+    Ok(response)
+  }
+}
+
+impl Drop for Process {
+  fn drop(&mut self) {
+    // We ask the bot to exit, but do not rely on it doing so.
+    let _result = self.send(&Request::Quit);
+    let _result = self.child.kill();
+    let _status = self.child.wait();
+  }
+}
+
+
+/// An external bot playing the game in place of the builtin AI.
+///
+/// Bots are driven via a line based JSON protocol over their standard
+/// input and output, modeled on the Tetris Bot Protocol. Each message
+/// is a JSON object with a `type` member denoting its kind.
+#[derive(Debug)]
+pub(crate) struct Bot {
+  /// The name the bot reported.
+  name: String,
+  /// The time the bot may take to respond to a request for a
+  /// suggestion.
+  timeout: Duration,
+  /// The bot's process.
+  process: Mutex<Process>,
+}
+
+impl Bot {
+  /// Launch the bot at `path` and let it know about the rules of the
+  /// game.
+  ///
+  /// The bot has to respond to each request for a suggestion within
+  /// `timeout`.
+  pub fn launch(
+    path: &Path,
+    width: i16,
+    height: i16,
+    rotation_system: RotationSystem,
+    timeout: Duration,
+  ) -> Result<Self> {
+    let mut child = Command::new(path)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .spawn()
+      .with_context(|| format!("failed to launch bot `{}`", path.display()))?;
+    // SANITY: We requested both streams to be piped.
+    let stdin = child.stdin.take().unwrap();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+
+    // Reading from the bot blocks, so we do so on a separate thread,
+    // which allows us to stop waiting for a response. The thread exits
+    // once the bot closes its standard output, at the latest when it
+    // gets killed.
+    let (sender, lines) = channel();
+    let _handle = spawn(move || {
+      for line in stdout.lines() {
+        let failed = line.is_err();
+        if sender.send(line).is_err() || failed {
+          break
+        }
+      }
+    });
+
+    let mut process = Process {
+      child,
+      stdin,
+      lines,
+      stale: 0,
+    };
+
+    let name = match process.receive(LAUNCH_TIMEOUT)? {
+      Response::Info { name } => name,
+      response => bail!("expected `info` from bot but received {response:?}"),
+    };
+
+    let rules = Request::Rules {
+      width,
+      height,
+      rotation_system,
+    };
+    let () = process.send(&rules)?;
+    match process.receive(LAUNCH_TIMEOUT)? {
+      Response::Ready => (),
+      response => bail!("expected `ready` from bot `{name}` but received {response:?}"),
+    }
+
+    let slf = Self {
+      name,
+      timeout,
+      process: Mutex::new(process),
+    };
+    Ok(slf)
+  }
+
+  /// Ask the bot for its suggestion for `stone`.
+  ///
+  /// The stone on `hold` is reported for information only, as bots
+  /// cannot put stones on hold themselves.
+  pub fn suggest(
+    &self,
+    field: &Field,
+    stone: &Stone,
+    next_stones: &[Stone],
+    hold: Option<&Stone>,
+  ) -> Result<Suggestion> {
+    // Stones not in play are described by their shape, with the bottom
+    // left corner of their bounding box at the origin.
+    let shape = |stone: &Stone| {
+      let bounds = stone.bounds();
+      let origin = Point::new(bounds.x, bounds.y);
+      stone
+        .pieces()
+        .map(|piece| piece - origin)
+        .collect::<Vec<_>>()
+    };
+    let request = Request::Suggest {
+      field: field
+        .rows()
+        .iter()
+        .map(|row| (0..field.width()).map(|x| row & (1 << x) != 0).collect())
+        .collect(),
+      stone: stone.pieces().collect(),
+      queue: next_stones.iter().map(shape).collect(),
+      hold: hold.map(shape),
+    };
+
+    // If a previous request panicked, the process is still in a usable
+    // state, as the panic could not have happened mid-message.
+    let mut process = self.process.lock().unwrap_or_else(PoisonError::into_inner);
+    let () = process.send(&request)?;
+    let suggestion = match process.receive(self.timeout)? {
+      Response::Suggestion {
+        actions: Some(actions),
+        ..
+      } => Suggestion::Actions(actions),
+      Response::Suggestion {
+        placement: Some(placement),
+        ..
+      } => Suggestion::Placement(placement),
+      response => {
+        bail!("expected `suggestion` with placement or actions but received {response:?}")
+      },
+    };
+    Ok(suggestion)
+  }
+
+  /// Retrieve the name the bot reported.
+  #[inline]
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::fs::read_to_string;
+
+  use serde_json::Value;
+  use serde_json::json;
+
+  use tempfile::TempDir;
+
+  use super::super::util::field;
+  use super::super::util::stone;
+  use super::super::util::write_bot;
+
+
+  /// Launch a bot answering each request via `respond`.
+  fn launch(dir: &TempDir, respond: &str) -> Result<Bot> {
+    let path = write_bot(dir.path(), respond);
+    Bot::launch(
+      &path,
+      4,
+      3,
+      RotationSystem::default(),
+      Duration::from_secs(1),
+    )
+  }
+
+  /// Ask `bot` for a suggestion in a small, fixed game state.
+  fn suggest(bot: &Bot) -> Result<Suggestion> {
+    let field = field! {"
+      ....
+      ....
+      #..#
+    "};
+    let mut stone = stone! {"
+      ##
+      ##
+    "};
+    let result = field.reset_stone(&mut stone);
+    assert!(result);
+
+    let next = stone! {"
+      ####
+    "};
+    let hold = stone! {"
+      .#
+      ##
+      #.
+    "};
+    bot.suggest(&field, &stone, &[next], Some(&hold))
+  }
+
+
+  /// Check that we can retrieve a list of actions from a bot.
+  #[test]
+  fn bot_actions() {
+    let dir = TempDir::new().unwrap();
+    let bot = launch(
+      &dir,
+      r#"echo '{"type":"suggestion","actions":["move_left","merge"]}'"#,
+    )
+    .unwrap();
+    assert_eq!(bot.name(), "test-bot");
+
+    let suggestion = suggest(&bot).unwrap();
+    assert_eq!(
+      suggestion,
+      Suggestion::Actions(vec![Action::MoveLeft, Action::Merge])
+    );
+  }
+
+  /// Check that we can retrieve a placement from a bot.
+  #[test]
+  fn bot_placement() {
+    let dir = TempDir::new().unwrap();
+    let bot = launch(
+      &dir,
+      r#"echo '{"type":"suggestion","placement":[[1,0],[2,0],[1,1],[2,1]]}'"#,
+    )
+    .unwrap();
+
+    let suggestion = suggest(&bot).unwrap();
+    assert_eq!(
+      suggestion,
+      Suggestion::Placement(vec![
+        Point::new(1, 0),
+        Point::new(2, 0),
+        Point::new(1, 1),
+        Point::new(2, 1),
+      ])
+    );
+  }
+
+  /// Check that we describe the game state to a bot as expected.
+  #[test]
+  fn bot_request() {
+    let dir = TempDir::new().unwrap();
+    let request = dir.path().join("request.json");
+    let respond = format!(
+      r#"echo "$request" > '{}'; echo '{{"type":"suggestion","actions":[]}}'"#,
+      request.display()
+    );
+    let bot = launch(&dir, &respond).unwrap();
+    let _suggestion = suggest(&bot).unwrap();
+
+    let request = serde_json::from_str::<Value>(&read_to_string(&request).unwrap()).unwrap();
+    let expected = json!({
+      "type": "suggest",
+      "field": [
+        [true, false, false, true],
+        [false, false, false, false],
+        [false, false, false, false],
+      ],
+      "stone": [[1, 1], [2, 1], [1, 2], [2, 2]],
+      "queue": [[[0, 0], [1, 0], [2, 0], [3, 0]]],
+      "hold": [[0, 0], [0, 1], [1, 1], [1, 2]],
+    });
+    assert_eq!(request, expected);
+  }
+
+  /// Check that misbehaving bots are reported as such.
+  #[test]
+  fn bot_errors() {
+    let dir = TempDir::new().unwrap();
+    let bot = launch(&dir, r#"echo '{"type":"error","reason":"no idea"}'"#).unwrap();
+    let err = suggest(&bot).unwrap_err();
+    assert_eq!(err.to_string(), "bot reported error: no idea");
+
+    let bot = launch(&dir, r#"echo '{"type":"ready"}'"#).unwrap();
+    let err = suggest(&bot).unwrap_err();
+    assert!(
+      err.to_string().starts_with("expected `suggestion`"),
+      "{err}"
+    );
+
+    let bot = launch(&dir, "echo 'garbage'").unwrap();
+    let err = suggest(&bot).unwrap_err();
+    assert_eq!(err.to_string(), "received invalid response `garbage`");
+
+    let bot = launch(&dir, "exit 1").unwrap();
+    let err = suggest(&bot).unwrap_err();
+    assert_eq!(err.to_string(), "bot exited unexpectedly");
+
+    let err = Bot::launch(
+      &dir.path().join("does-not-exist"),
+      4,
+      3,
+      RotationSystem::default(),
+      Duration::from_secs(1),
+    )
+    .unwrap_err();
+    assert!(err.to_string().starts_with("failed to launch bot"), "{err}");
+  }
+
+  /// Check that we stop waiting for a bot that is slow to respond and
+  /// skip its late response later on.
+  #[test]
+  fn bot_timeout() {
+    let dir = TempDir::new().unwrap();
+    let slow = dir.path().join("slow");
+    let respond = format!(
+      r#"if [ ! -e '{slow}' ]; then
+    touch '{slow}'
+    sleep 1.5
+    echo '{{"type":"suggestion","actions":["move_left","merge"]}}'
+  else
+    echo '{{"type":"suggestion","actions":["move_right","merge"]}}'
+  fi"#,
+      slow = slow.display()
+    );
+    let bot = launch(&dir, &respond).unwrap();
+
+    let err = suggest(&bot).unwrap_err();
+    assert_eq!(err.to_string(), "bot did not respond within 1s");
+
+    let suggestion = suggest(&bot).unwrap();
+    assert_eq!(
+      suggestion,
+      Suggestion::Actions(vec![Action::MoveRight, Action::Merge])
+    );
+  }
+}
@@ -5,28 +5,97 @@ use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
 use std::ops::Index;
-use std::ops::IndexMut;
 
 use crate::point::Point;
 
 use super::super::Fieldlike;
 use super::super::Matrix;
+use super::super::RotationSystem;
+use super::super::Stonelike as _;
 use super::Stone;
 
 
+/// The type used for representing a single row of a [`Field`], with one
+/// bit per column.
+pub(crate) type Row = u32;
+
+
+/// A Tetris field as seen by the AI.
+///
+/// Each row of the field is represented by a bit mask, in which bit
+/// `x` is set if column `x` is occupied by a piece. As a result, the
+/// field can be at most [`Field::MAX_WIDTH`] wide.
 #[derive(Clone, PartialEq)]
 pub(crate) struct Field {
-  /// The matrix (2D array) of "pieces".
-  matrix: Matrix<Option<()>>,
+  /// The rows of the field, from bottom to top.
+  rows: Vec<Row>,
+  /// The width of the field.
+  width: i16,
+  /// The rotation system used for rotating stones.
+  rotation: RotationSystem,
 }
 
 impl Field {
+  /// The maximum width of a field.
+  pub const MAX_WIDTH: i16 = Row::BITS as i16;
+
+  /// # Panics
+  /// This constructor panics if the matrix is wider than
+  /// [`Field::MAX_WIDTH`].
   #[inline]
-  pub fn from_matrix<P>(matrix: &Matrix<Option<P>>) -> Self {
+  pub fn from_matrix<P>(matrix: &Matrix<Option<P>>, rotation: RotationSystem) -> Self {
+    assert!(
+      matrix.width() <= Self::MAX_WIDTH,
+      "field width {} exceeds supported maximum of {}",
+      matrix.width(),
+      Self::MAX_WIDTH
+    );
+
+    let rows = (0..matrix.height())
+      .map(|y| {
+        matrix
+          .iter_line(y)
+          .enumerate()
+          .filter(|(_x, piece)| piece.is_some())
+          .fold(0, |row, (x, _piece)| row | (1 << x))
+      })
+      .collect();
+
     Self {
-      matrix: matrix.to_other(|option| option.as_ref().map(|_piece| ())),
+      rows,
+      width: matrix.width(),
+      rotation,
     }
   }
+
+  /// Retrieve the rows of the field, from bottom to top.
+  #[inline]
+  pub fn rows(&self) -> &[Row] {
+    &self.rows
+  }
+
+  /// Retrieve the bit mask of a row in which all columns are occupied.
+  #[inline]
+  fn full_row(&self) -> Row {
+    Row::MAX >> (Self::MAX_WIDTH - self.width)
+  }
+
+  /// Check whether the given location is within the bounds of the field
+  /// and not occupied by a piece.
+  #[inline]
+  fn is_free(&self, location: Point<i16>) -> bool {
+    (0..self.width).contains(&location.x)
+      && self
+        .rows
+        .get(location.y as usize)
+        .is_some_and(|row| row & (1 << location.x) == 0)
+  }
+
+  /// Retrieve the rotation system used for rotating stones.
+  #[inline]
+  pub fn rotation_system(&self) -> RotationSystem {
+    self.rotation
+  }
 }
 
 impl Index<Point<i16>> for Field {
@@ -34,52 +103,60 @@ impl Index<Point<i16>> for Field {
 
   #[inline]
   fn index(&self, index: Point<i16>) -> &Self::Output {
-    &self.matrix[(index.x, index.y)]
-  }
-}
+    debug_assert!((0..self.width).contains(&index.x), "{index:?}");
 
-impl IndexMut<Point<i16>> for Field {
-  #[inline]
-  fn index_mut(&mut self, index: Point<i16>) -> &mut Self::Output {
-    &mut self.matrix[(index.x, index.y)]
+    if self.rows[index.y as usize] & (1 << index.x) != 0 {
+      &Some(())
+    } else {
+      &None
+    }
   }
 }
 
 impl Fieldlike<Stone> for Field {
   #[inline]
   fn width(&self) -> i16 {
-    self.matrix.width()
+    self.width
   }
 
   #[inline]
   fn height(&self) -> i16 {
-    self.matrix.height()
+    self.rows.len() as i16
+  }
+
+  #[inline]
+  fn collides(&self, stone: &Stone) -> bool {
+    stone.pieces().any(|location| !self.is_free(location))
+  }
+
+  #[inline]
+  fn place(&mut self, location: Point<i16>, _piece: ()) -> Option<()> {
+    debug_assert!((0..self.width).contains(&location.x), "{location:?}");
+
+    let row = &mut self.rows[location.y as usize];
+    let bit = 1 << location.x;
+    let prev = *row & bit != 0;
+    *row |= bit;
+    prev.then_some(())
   }
 
   #[inline]
   fn line_complete(&self, line: i16) -> bool {
-    self.matrix.iter_line(line).all(Option::is_some)
+    self.rows[line as usize] == self.full_row()
   }
 
   #[inline]
   fn remove_line(&mut self, line: i16) {
-    self.matrix.remove_line(line)
+    let _row = self.rows.remove(line as usize);
+    let () = self.rows.push(0);
   }
 }
 
 impl Debug for Field {
   fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-    for y in (0..self.height()).rev() {
-      for x in 0..self.width() {
-        write!(
-          f,
-          "{}",
-          if self.matrix[(x, y)].is_some() {
-            '#'
-          } else {
-            '.'
-          }
-        )?;
+    for row in self.rows.iter().rev() {
+      for x in 0..self.width {
+        write!(f, "{}", if row & (1 << x) != 0 { '#' } else { '.' })?;
       }
       writeln!(f)?;
     }
@@ -92,7 +169,6 @@ impl Debug for Field {
 mod tests {
   use super::*;
 
-  use super::super::super::Stonelike as _;
   use super::super::util::field;
   use super::super::util::stone;
 
@@ -147,6 +223,53 @@ mod tests {
   }
 
 
+  /// Check that we calculate the drop distance of a stone correctly.
+  #[test]
+  fn stone_drop_distance() {
+    let mut stone = stone! {"
+      .#.
+      ###
+    "};
+    let field = field! {"
+      .......
+      .......
+      .......
+      #......
+      ##.....
+      ##...##
+    "};
+
+    let () = stone.move_to(Point::new(0, 4));
+    assert_eq!(field.drop_distance(&stone), 1);
+
+    let () = stone.move_to(Point::new(2, 4));
+    assert_eq!(field.drop_distance(&stone), 4);
+
+    let () = stone.move_to(Point::new(4, 4));
+    assert_eq!(field.drop_distance(&stone), 3);
+  }
+
+
+  /// Check that fields of the maximum supported width are handled
+  /// correctly.
+  #[test]
+  fn max_width() {
+    let mut matrix = Matrix::<Option<()>>::new(Field::MAX_WIDTH, 2);
+    let () = (1..Field::MAX_WIDTH).for_each(|x| matrix[(x, 0)] = Some(()));
+    let mut field = Field::from_matrix(&matrix, RotationSystem::default());
+    assert!(!field.line_complete(0));
+    assert!(field[Point::new(Field::MAX_WIDTH - 1, 0)].is_some());
+    assert!(field[Point::new(0, 0)].is_none());
+
+    let _prev = field.place(Point::new(0, 0), ());
+    assert!(field.line_complete(0));
+
+    let removed = field.remove_complete_lines(0..2);
+    assert_eq!(removed, 1);
+    assert_eq!(field.rows(), [0, 0]);
+  }
+
+
   /// Make sure that removal of completed lines works as it should.
   #[test]
   fn line_clearing() {
@@ -3,6 +3,7 @@
 
 mod action;
 mod ai;
+mod bot;
 mod cost;
 mod field;
 mod orientation;
@@ -10,18 +11,29 @@ mod search;
 mod state;
 mod stone;
 #[cfg(test)]
-mod util;
+pub(super) mod util;
 mod visited;
+mod weights;
 
+use bot::Suggestion;
 use cost::Cost;
-use orientation::Orientation;
 use orientation::Orientations;
 use search::actions;
+use search::placement;
 use search::search;
-use state::State;
+use search::search_ranked;
+use search::search_target;
+use state::Arena;
+use state::StateId;
 use visited::VisitedStones;
 
 pub(super) use action::Action;
 pub(super) use ai::AI;
+pub(super) use ai::Planner;
+pub(super) use ai::Worker;
+pub(super) use bot::Bot;
 pub(super) use field::Field;
+use field::Row;
+pub(super) use orientation::Orientation;
 pub(super) use stone::Stone;
+pub use weights::Weights;
@@ -6,10 +6,13 @@ use std::num::NonZeroU8;
 use std::ops::BitOr;
 use std::ops::BitOrAssign;
 
+use serde::Deserialize;
+use serde::Serialize;
+
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub(super) enum Orientation {
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub(crate) enum Orientation {
   // Note: Values are assigned in such a way that bit rotation direction
   //       matches logical rotation direction.
   Rotated0 = 0b1000,
@@ -1,86 +1,102 @@
 // Copyright (C) 2023-2025 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::cmp::Ordering;
+use std::cmp::min;
 use std::collections::BinaryHeap;
-use std::iter::from_fn;
-use std::ops::Deref as _;
-use std::rc::Rc;
 
 use crate::Point;
 
 use super::super::Fieldlike as _;
 use super::super::Stonelike as _;
 use super::Action;
+use super::Arena;
 use super::Cost;
 use super::Field;
-use super::State;
+use super::Row;
+use super::StateId;
 use super::Stone;
+use super::Weights;
 
 
+/// The maximum number of states continued with the next stone.
+///
+/// Keep the documentation of `Config::ai_lookahead` in sync.
+const BEAM_WIDTH: usize = 32;
+
+
+/// Extract the sequence of actions for the first stone, leading up to
+/// the given state, along with the field resulting from it.
 pub(super) fn actions(
-  state: Option<Rc<State>>,
-) -> (impl Iterator<Item = Action>, Option<Rc<Field>>) {
-  let mut field = state.as_ref().map(|state| Rc::clone(&state.field));
-  let mut prev_field = None;
-  let mut prev = Option::<Rc<State>>::None;
-  let mut next = state.clone();
-  let mut first = state;
-  let mut merge = None;
+  arena: &Arena,
+  state: Option<StateId>,
+) -> (impl Iterator<Item = Action> + use<>, Option<&Field>) {
+  let mut actions = Vec::new();
+  let mut field = None;
+  let mut next = state;
 
   // The states provided form a linked list from "best" one to the
-  // initial state. But that's not the order we need: we need a list
-  // starting with the initial state. So here we effectively invert
-  // everything.
-  while let Some(state) = next {
-    let parent = state.parent.clone();
-    let state = state.reparent(prev);
-
-    // On top of inverting, we also make a cut off whenever there is no
-    // action. A state without an action is one after the stone changed.
-    // What we are ultimately interested in reporting is the action
-    // sequence for just the first stone, but not any of the preview
-    // ones. Those were just used to improve the precision of the
-    // estimation.
+  // initial state. We walk this list and make a cut off whenever we
+  // encounter a merge. What we are ultimately interested in reporting
+  // is the action sequence for just the first stone, but not any of
+  // the preview ones. Those were just used to improve the precision of
+  // the estimation.
+  while let Some(id) = next {
+    let state = &arena[id];
     match state.action {
       Some(Action::Merge) => {
-        prev_field = field;
-        field = parent.as_ref().map(|parent| Rc::clone(&parent.field));
-        merge = state.action;
-        prev = None
+        let () = actions.clear();
+        let () = actions.push(Action::Merge);
+        field = Some(arena.field(id));
       },
-      None => {
-        debug_assert_eq!(parent, None);
-        break
+      Some(action) => {
+        let () = actions.push(action);
       },
-      Some(_) => {
-        prev = Some(Rc::clone(&state));
+      None => {
+        debug_assert_eq!(state.parent, None);
       },
     }
-
-    first = Some(state);
-    next = parent;
+    next = state.parent;
   }
 
-  let mut next = first;
+  // We collected actions in reverse order, but they need to be
+  // performed starting with the initial state.
+  (actions.into_iter().rev(), field)
+}
 
-  let it = from_fn(move || {
-    while let Some(state) = &next {
-      let parent = state.parent.clone();
-      if let action @ Some(..) = state.action {
-        next = parent;
-        return action
-      } else {
-        next = parent;
-      }
+/// Determine the location at which the first stone ends up being
+/// merged, given the best state found by a [`search`].
+pub(super) fn placement(arena: &Arena, state: Option<StateId>) -> Option<Stone> {
+  let mut next = state;
+  let mut stone = None;
+
+  // As in `actions`, states form a linked list from the "best" one to
+  // the initial state. The last merge we encounter is that of the
+  // first stone, and its parent still has said stone at its final
+  // location.
+  while let Some(id) = next {
+    let state = &arena[id];
+    if let Some(Action::Merge) = state.action {
+      stone = state.parent.and_then(|parent| arena[parent].stone.as_ref());
     }
+    next = state.parent;
+  }
+  stone.cloned()
+}
 
-    merge.take()
-  });
-
-  (it, prev_field)
+/// Invoke `f` with the index of each bit set in `row`.
+#[inline]
+fn for_each_bit<F>(mut row: Row, mut f: F)
+where
+  F: FnMut(usize),
+{
+  while row != 0 {
+    let () = f(row.trailing_zeros() as usize);
+    row &= row - 1;
+  }
 }
 
-fn estimate_cost(field: &Field) -> Cost {
+fn estimate_cost(field: &Field, weights: &Weights) -> Cost {
   let width = f32::from(field.width());
   let height = f32::from(field.height());
   let center_x = width / 2.0;
@@ -95,27 +111,26 @@ fn estimate_cost(field: &Field) -> Cost {
   let mut coverage = 0i16;
   let mut stack_size = 0f32;
 
-  for x in 0..field.width() {
-    let mut top_piece_y = None;
-    let mut pieces = 0i16;
-
-    for y in (0..field.height()).rev() {
-      let location = Point::new(x, y);
-      let piece = field[location];
-
-      if piece.is_some() {
-        pieces += 1;
-      }
+  // The height of each column, i.e., the y coordinate right above its
+  // top most piece.
+  let mut heights = [0i16; Field::MAX_WIDTH as usize];
+  // The number of pieces in each column above the current row.
+  let mut pieces = [0i16; Field::MAX_WIDTH as usize];
+  // The columns in which we have seen a piece already.
+  let mut covered = Row::MIN;
+
+  for (y, row) in (0..field.height()).zip(field.rows()).rev() {
+    let () = for_each_bit(row & !covered, |x| heights[x] = y + 1);
+    covered |= row;
+
+    let row_holes = covered & !row;
+    holes += row_holes.count_ones() as i16;
+    let () = for_each_bit(row_holes, |x| coverage += pieces[x]);
+    let () = for_each_bit(*row, |x| pieces[x] += 1);
+  }
 
-      if top_piece_y.is_none() && piece.is_some() {
-        top_piece_y = Some(f32::from(y + 1));
-      }
-
-      if piece.is_none() && top_piece_y.is_some() {
-        holes += 1;
-        coverage += pieces;
-      }
-    }
+  for (x, height) in (0..field.width()).zip(heights) {
+    let top_piece_y = (height > 0).then(|| f32::from(height));
 
     // Favor tangentially lower "stacks" in the center and higher
     // towards the sides. That's a consequence of it requiring more
@@ -139,14 +154,14 @@ fn estimate_cost(field: &Field) -> Cost {
   let high_point_cost = top_most_piece_y / height;
 
   let mut cost = Cost::none();
-  cost += 4.0 * holes_cost;
-  cost += 0.1 * cover_cost;
-  cost += stack_cost;
-  cost += 1.5 * rough_cost;
+  cost += weights.holes as f32 * holes_cost;
+  cost += weights.coverage as f32 * cover_cost;
+  cost += weights.stack as f32 * stack_cost;
+  cost += weights.roughness as f32 * rough_cost;
   // We value the top most piece very highly. That's effectively our way
   // of favoring the clearing of lines, but also making clearing of
   // lines more important as the field gets filled more overall.
-  cost += 8.0 * high_point_cost.powi(16);
+  cost += weights.high_point as f32 * high_point_cost.powi(i32::from(weights.high_point_exponent));
 
   cost
 }
@@ -160,14 +175,127 @@ fn update_field(mut field: Field, stone: Stone) -> Field {
   field
 }
 
+/// A [`State`][super::state::State] yet to be examined.
+struct Open {
+  /// The state's total cost.
+  cost: Cost,
+  /// The state itself.
+  state: StateId,
+}
+
+impl PartialEq for Open {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Open {
+  fn cmp(&self, other: &Self) -> Ordering {
+    // Just like `State` objects, we order by "minimum cost", for the
+    // cheapest state to be examined first.
+    self.cost.cmp(&other.cost).reverse()
+  }
+}
+
+/// Enumerate all locations at which the stone of the given state can
+/// be merged, along with the resulting field and its cost.
+///
+/// Each location is reported by the last [`State`][super::state::State] before the merge,
+/// which is reached via the cheapest sequence of actions.
+fn merges(arena: &mut Arena, weights: &Weights, state: StateId) -> Vec<(StateId, Field, Cost)> {
+  let mut merges = Vec::new();
+  let mut open = BinaryHeap::new();
+  let () = open.push(Open {
+    cost: arena[state].cost(),
+    state,
+  });
+
+  while let Some(Open { state, .. }) = open.pop() {
+    debug_assert!(
+      arena[state].stone.is_some(),
+      "encountered unexpected final state: {:?}",
+      arena[state]
+    );
+
+    // Note that although we do not insert visited states into the list
+    // of open states directly, ones in there might become visited
+    // between insertion and removal if expansion of another state
+    // yielded the same stone -- skip those ones here.
+    if arena.was_visited(state) {
+      continue
+    }
+
+    let () = arena.visit(state);
+
+    if arena.has_collision(state) {
+      if arena[state].action == Some(Action::MoveDown) {
+        // SANITY: There has to exist a parent because we just made
+        //         sure that the stone had a `MoveDown` action, which
+        //         means the state must have been derived at least
+        //         once.
+        let parent = arena[state].parent.unwrap();
+        // SANITY: The parent state cannot have been final or we would
+        //         not have a child. If it's not final, it must have a
+        //         stone.
+        let stone = arena[parent].stone.as_ref().unwrap();
+        let field = update_field(arena.field(parent).clone(), stone.clone());
+        let cost = estimate_cost(&field, weights);
+        let () = merges.push((parent, field, cost));
+      }
+    } else {
+      let mut expander = arena.expand(state);
+      while let Some(expanded) = expander.next(arena, weights) {
+        if arena.was_visited(expanded) {
+          continue
+        }
+
+        // Note that one important fact here is that the expansion
+        // itself does not check whether an expanded state is valid,
+        // i.e., whether the corresponding stone is actually within
+        // the bounds of the field; this means we might insert an
+        // "invalid" stone here, but this is by design because this is
+        // essentially how we check for a reached goal
+        let () = open.push(Open {
+          cost: arena[expanded].cost(),
+          state: expanded,
+        });
+      }
+    }
+  }
+
+  merges
+}
+
 /// Search for an action sequence that is expected to yield the best
 /// possible outcome.
 ///
 /// The search is inspired by the A* algorithm. However, we don't search
 /// for a "goal", as there is no such thing here. We search for what we
 /// suspect to be the best possible next state given the provided set of
-/// stones. This is an estimate based on a set of partly subjective
-/// heuristics. In the end, we still do an exhaustive fill state search.
+/// stones. This is an estimate based on a set of heuristics, weighted
+/// as per [`Weights`][super::Weights], which can be evolved from
+/// simulated games via `tetromino --tune`.
+///
+/// Stones are considered one after the other: for each state we do an
+/// exhaustive fill state search for all locations at which its stone
+/// can be merged. Of the resulting states, only the `BEAM_WIDTH` most
+/// promising ones are continued with the next stone, which keeps the
+/// effort linear in the number of stones.
+///
+/// If `expired` reports that the search is to end before all stones
+/// have been considered, the most promising state found so far is
+/// reported. All locations of the first stone are always evaluated,
+/// though.
+///
+/// All states are created inside of `arena`, which is cleared first.
 // TODO: It would probably be better to employ the following algorithm:
 //       1) we maintain some notion of a frontier on which stones will
 //          be dropped (i.e., where dropped pieces start)
@@ -181,91 +309,76 @@ fn update_field(mut field: Field, stone: Stone) -> Field {
 //       There may be some way here to improve incrementality for
 //       subsequent stones that could speed things up further (though
 //       details are unclear).
-pub(super) fn search(field: &Field, stone: &Stone, next_stones: &[Stone]) -> Option<Rc<State>> {
+pub(super) fn search(
+  arena: &mut Arena,
+  field: &Field,
+  weights: &Weights,
+  stone: &Stone,
+  next_stones: &[Stone],
+  expired: impl Fn() -> bool,
+) -> Option<StateId> {
   debug_assert!(!field.collides(stone));
 
+  let () = arena.clear();
+
   let stone_count =
     u8::try_from(next_stones.len()).expect("more than 255 preview stones are unsupported");
 
-  let mut best = Option::<Rc<State>>::None;
-  let mut open = BinaryHeap::new();
+  let cost = estimate_cost(field, weights);
+  let mut beam = vec![arena.initial(field.clone(), cost, stone.clone(), stone_count)];
 
-  let cost = estimate_cost(field);
-  let state = State::initial(field.clone(), cost, stone.clone(), stone_count);
-  let () = open.push(state);
+  for next_stone in next_stones {
+    let mut merged = Vec::new();
 
-  while let Some(state) = open.pop() {
-    debug_assert!(
-      state.stone.is_some(),
-      "encountered unexpected final state: {state:?}"
-    );
+    for state in &beam {
+      // Once the first stone got merged, the beam always contains a
+      // viable outcome, the most promising of which we report if we
+      // ran out of time.
+      if arena[*state].index > 0 && expired() {
+        return beam.first().copied()
+      }
 
-    // Note that although we do not insert visited states into the list
-    // of open states directly, ones in there might become visited
-    // between insertion and removal if expansion of another state
-    // yielded the same stone -- skip those ones here.
-    if state.was_visited() {
-      continue
+      for (parent, field, cost) in merges(arena, weights, *state) {
+        let mut next_stone = next_stone.clone();
+        let result = field.reset_stone(&mut next_stone);
+        // If we ended up hitting a collision on reset, this is not a
+        // viable state after all, but otherwise we can continue the
+        // search with it.
+        if result {
+          let () = merged.push(arena.merged(parent, field, cost, next_stone));
+        }
+      }
     }
 
-    let () = state.visit();
+    // `State` objects are ordered such that "greater" means cheaper.
+    let () = merged.sort_by(|state1, state2| arena[*state2].cmp(&arena[*state1]));
+    let () = merged.truncate(BEAM_WIDTH);
+    beam = merged;
+  }
 
-    if state.has_collision() {
-      if state.action == Some(Action::MoveDown) {
-        // SANITY: There has to exist a parent because we just made
-        //         sure that the stone had a `MoveDown` action, which
-        //         means the state must have been derived at least
-        //         once.
-        let parent = state.parent.as_ref().unwrap();
-        // SANITY: The parent state cannot have been final or we would
-        //         not have a child. If it's not final, it must have a
-        //         stone.
-        let stone = parent.stone.as_ref().unwrap();
-        let field = update_field(parent.field.deref().clone(), stone.clone());
-        let cost = estimate_cost(&field);
-
-        if let Some(next_stone) = next_stones.get(usize::from(parent.index)) {
-          let mut next_stone = next_stone.clone();
-          let result = field.reset_stone(&mut next_stone);
-          // If we ended up hitting a collision on reset, this is not a
-          // viable state after all, but otherwise we can continue the
-          // search with it.
-          if result {
-            let () = open.push(parent.merged(field, cost, next_stone));
-          }
-        } else {
-          let best_cost = best
-            .as_ref()
-            // For reporting the final best state, we only look at the
-            // actual field cost. All other costs are really only used
-            // to guide the search.
-            .map(|state| state.field_cost)
-            .unwrap_or_else(Cost::max);
-
-          if cost < best_cost {
-            // Strictly speaking `parent` already has a cost associated
-            // with it, but really that one is about the *initial* state
-            // of the associated field. When we reach this point we have
-            // derived it so far that the associated stone has been merged
-            // and so really what we are working with here *is* the
-            // relevant cost.
-            best = Some(State::finalize(parent, field, cost));
-          }
-        }
-      }
-    } else {
-      for expanded in state.expand() {
-        if expanded.was_visited() {
-          continue
-        }
+  let mut best = Option::<StateId>::None;
+
+  for state in &beam {
+    if arena[*state].index > 0 && expired() {
+      return best.or_else(|| beam.first().copied())
+    }
 
-        // Note that one important fact here is that the expand()
-        // function itself does not check whether an expanded state is
-        // valid, i.e., whether the corresponding stone is actually
-        // within the bounds of the field; this means we might insert
-        // an "invalid" stone here, but this is by design because this
-        // is essentially how we check for a reached goal
-        let () = open.push(expanded);
+    for (parent, field, cost) in merges(arena, weights, *state) {
+      let best_cost = best
+        // For reporting the final best state, we only look at the
+        // actual field cost. All other costs are really only used
+        // to guide the search.
+        .map(|state| arena[state].field_cost)
+        .unwrap_or_else(Cost::max);
+
+      if cost < best_cost {
+        // Strictly speaking `parent` already has a cost associated
+        // with it, but really that one is about the *initial* state
+        // of the associated field. When we reach this point we have
+        // derived it so far that the associated stone has been merged
+        // and so really what we are working with here *is* the
+        // relevant cost.
+        best = Some(arena.finalize(parent, field, cost));
       }
     }
   }
@@ -273,6 +386,64 @@ pub(super) fn search(field: &Field, stone: &Stone, next_stones: &[Stone]) -> Opt
   best
 }
 
+/// Search for an action sequence merging `stone` at the `rank`-th best
+/// location, with zero being the best one.
+///
+/// Contrary to [`search`], locations are judged solely by the field
+/// resulting from the merge, without taking into account any preview
+/// stones. If there are no more than `rank` locations, the worst one is
+/// picked.
+pub(super) fn search_ranked(
+  arena: &mut Arena,
+  field: &Field,
+  weights: &Weights,
+  stone: &Stone,
+  rank: usize,
+) -> Option<StateId> {
+  debug_assert!(!field.collides(stone));
+
+  let () = arena.clear();
+
+  let cost = estimate_cost(field, weights);
+  let initial = arena.initial(field.clone(), cost, stone.clone(), 0);
+  let mut merges = merges(arena, weights, initial);
+  let () = merges.sort_by_key(|(_, _, cost)| *cost);
+  let index = min(rank, merges.len().checked_sub(1)?);
+  let (parent, field, cost) = merges.swap_remove(index);
+  Some(arena.finalize(parent, field, cost))
+}
+
+/// Search for an action sequence merging `stone` with its pieces ending
+/// up at the given locations, if possible.
+pub(super) fn search_target(
+  arena: &mut Arena,
+  field: &Field,
+  weights: &Weights,
+  stone: &Stone,
+  pieces: &[Point<i16>],
+) -> Option<StateId> {
+  debug_assert!(!field.collides(stone));
+
+  let () = arena.clear();
+
+  let mut target = pieces.to_vec();
+  let () = target.sort();
+
+  let cost = estimate_cost(field, weights);
+  let initial = arena.initial(field.clone(), cost, stone.clone(), 0);
+  let (parent, field, cost) =
+    merges(arena, weights, initial)
+      .into_iter()
+      .find(|(parent, _field, _cost)| {
+        arena[*parent].stone.as_ref().is_some_and(|stone| {
+          let mut pieces = stone.pieces().collect::<Vec<_>>();
+          let () = pieces.sort();
+          pieces == target
+        })
+      })?;
+  Some(arena.finalize(parent, field, cost))
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -289,18 +460,59 @@ mod tests {
   use crate::Rng;
 
   use super::super::super::Matrix;
+  use super::super::super::RotationSystem;
   use super::super::util::field;
   use super::super::util::stone;
 
 
   /// Check that we correctly create an empty action list when provided
-  /// with no [`State`].
+  /// with no [`State`][super::super::state::State].
   #[test]
   fn action_list_creation() {
-    let (mut actions, _field) = actions(None);
+    let (mut actions, _field) = actions(&Arena::default(), None);
     assert_eq!(actions.next(), None);
   }
 
+  /// Check that we report the location at which the first stone gets
+  /// merged as its placement.
+  #[test]
+  fn search_placement() {
+    let stone = stone! {"
+      ##
+      ##
+    "};
+    let field = field! {"
+      ......
+      ......
+      ......
+      ####..
+      ####..
+    "};
+
+    let mut arena = Arena::default();
+    assert!(placement(&arena, None).is_none());
+
+    let mut stone = stone.clone();
+    let result = field.reset_stone(&mut stone);
+    assert!(result);
+
+    let best = search(&mut arena, &field, &Weights::default(), &stone, &[], || {
+      false
+    });
+    let placement = placement(&arena, best).unwrap();
+    let mut pieces = placement.pieces().collect::<Vec<_>>();
+    let () = pieces.sort();
+    assert_eq!(
+      pieces,
+      [
+        Point::new(4, 0),
+        Point::new(4, 1),
+        Point::new(5, 0),
+        Point::new(5, 1)
+      ]
+    );
+  }
+
   /// Check that the equal fields yield same utilities.
   #[test]
   fn cost_same_field() {
@@ -312,11 +524,33 @@ mod tests {
       ######
     "};
 
-    let cost1 = estimate_cost(&field);
-    let cost2 = estimate_cost(&field);
+    let cost1 = estimate_cost(&field, &Weights::default());
+    let cost2 = estimate_cost(&field, &Weights::default());
     assert_eq!(cost1, cost2);
   }
 
+  /// Check that the configured weights are honored when evaluating a
+  /// field.
+  #[test]
+  fn cost_custom_weights() {
+    let field1 = field! {"
+      ......
+      ......
+      ......
+      ..#...
+      ##.###
+    "};
+    let weights1 = Weights::default();
+    let weights2 = Weights {
+      holes: 2.0 * weights1.holes,
+      ..Default::default()
+    };
+
+    let cost1 = estimate_cost(&field1, &weights1);
+    let cost2 = estimate_cost(&field1, &weights2);
+    assert!(cost1 < cost2, "{cost1} vs. {cost2}");
+  }
+
   /// Make sure that utility increases few lines are occupied in the
   /// field.
   #[test]
@@ -335,8 +569,8 @@ mod tests {
       ......
       #...##
     "};
-    let cost1 = estimate_cost(&field1);
-    let cost2 = estimate_cost(&field2);
+    let cost1 = estimate_cost(&field1, &Weights::default());
+    let cost2 = estimate_cost(&field2, &Weights::default());
     assert!(cost1 > cost2, "{cost1} vs. {cost2}");
   }
 
@@ -357,8 +591,8 @@ mod tests {
       ..#...
       ######
     "};
-    let cost1 = estimate_cost(&field1);
-    let cost2 = estimate_cost(&field2);
+    let cost1 = estimate_cost(&field1, &Weights::default());
+    let cost2 = estimate_cost(&field2, &Weights::default());
     assert!(cost1 < cost2, "{cost1} vs. {cost2}");
   }
 
@@ -380,8 +614,8 @@ mod tests {
       ......
       ...#..
     "};
-    let cost1 = estimate_cost(&field1);
-    let cost2 = estimate_cost(&field2);
+    let cost1 = estimate_cost(&field1, &Weights::default());
+    let cost2 = estimate_cost(&field2, &Weights::default());
     assert!(cost1 < cost2, "{cost1} vs. {cost2}");
 
     let field1 = field! {"
@@ -398,8 +632,8 @@ mod tests {
       .........
       .....####
     "};
-    let cost1 = estimate_cost(&field1);
-    let cost2 = estimate_cost(&field2);
+    let cost1 = estimate_cost(&field1, &Weights::default());
+    let cost2 = estimate_cost(&field2, &Weights::default());
     assert!(cost1 > cost2, "{cost1} vs. {cost2}");
   }
 
@@ -421,8 +655,8 @@ mod tests {
       #.....
       ######
     "};
-    let cost1 = estimate_cost(&field1);
-    let cost2 = estimate_cost(&field2);
+    let cost1 = estimate_cost(&field1, &Weights::default());
+    let cost2 = estimate_cost(&field2, &Weights::default());
     assert!(cost1 > cost2, "{cost1} vs. {cost2}");
   }
 
@@ -444,8 +678,8 @@ mod tests {
       ..#...
       ##.###
     "};
-    let cost1 = estimate_cost(&field1);
-    let cost2 = estimate_cost(&field2);
+    let cost1 = estimate_cost(&field1, &Weights::default());
+    let cost2 = estimate_cost(&field2, &Weights::default());
     assert!(cost1 < cost2, "{cost1} vs. {cost2}");
   }
 
@@ -473,8 +707,8 @@ mod tests {
       ##.#.#..#.
       ####.#####
     "};
-    let cost1 = estimate_cost(&field1);
-    let cost2 = estimate_cost(&field2);
+    let cost1 = estimate_cost(&field1, &Weights::default());
+    let cost2 = estimate_cost(&field2, &Weights::default());
     assert!(cost1 < cost2, "{cost1} vs. {cost2}");
   }
 
@@ -552,8 +786,8 @@ mod tests {
       #.#.#.#.#.#.#.#.#.#.
       #.#.#.#.#.#.#.#.#.#.
     "};
-    let cost1 = estimate_cost(&field1);
-    let cost2 = estimate_cost(&field2);
+    let cost1 = estimate_cost(&field1, &Weights::default());
+    let cost2 = estimate_cost(&field2, &Weights::default());
     assert!(cost1 > cost2, "{cost1} vs. {cost2}");
   }
 
@@ -629,8 +863,8 @@ mod tests {
       #...#
       #...#
     "};
-    let cost1 = estimate_cost(&field1);
-    let cost2 = estimate_cost(&field2);
+    let cost1 = estimate_cost(&field1, &Weights::default());
+    let cost2 = estimate_cost(&field2, &Weights::default());
     assert!(cost1 > cost2, "{cost1} vs. {cost2}");
   }
 
@@ -647,8 +881,12 @@ mod tests {
         Action::MoveDown => stone.move_down(),
         Action::MoveLeft => stone.move_left(),
         Action::MoveRight => stone.move_right(),
-        Action::RotateLeft => stone.rotate_left(),
-        Action::RotateRight => stone.rotate_right(),
+        Action::RotateLeft => {
+          let _rotated = field.rotation_system().rotate(&field, &mut stone, true);
+        },
+        Action::RotateRight => {
+          let _rotated = field.rotation_system().rotate(&field, &mut stone, false);
+        },
         Action::Merge => {
           let bounds = stone.bounds();
           let y_range = bounds.y..bounds.y + bounds.h;
@@ -674,10 +912,18 @@ mod tests {
     let result = field.reset_stone(&mut stone);
     assert!(result);
 
-    let best = search(&field, &stone, next_stones);
-    let (actions, expected_field) = actions(best);
+    let mut arena = Arena::default();
+    let best = search(
+      &mut arena,
+      &field,
+      &Weights::default(),
+      &stone,
+      next_stones,
+      || false,
+    );
+    let (actions, expected_field) = actions(&arena, best);
     let field = replay(field, stone, actions);
-    assert_eq!(Some(&field), expected_field.as_deref());
+    assert_eq!(Some(&field), expected_field);
     field
   }
 
@@ -907,6 +1153,7 @@ mod tests {
     let mut count = 0;
     let mut factory = stone_factory(1337);
     let mut next_stones = factory.by_ref().take(1).collect::<VecDeque<_>>();
+    let mut arena = Arena::default();
 
     loop {
       let mut stone = next_stones.pop_front().unwrap();
@@ -917,8 +1164,15 @@ mod tests {
         break
       }
 
-      let best = search(&field, &stone, next_stones.make_contiguous());
-      let (actions, expected_field) = actions(best);
+      let best = search(
+        &mut arena,
+        &field,
+        &Weights::default(),
+        &stone,
+        next_stones.make_contiguous(),
+        || false,
+      );
+      let (actions, expected_field) = actions(&arena, best);
       field = replay(field, stone, actions);
       println!("{field:?}");
 
@@ -932,11 +1186,171 @@ mod tests {
     println!("merged {count} stones");
   }
 
+  /// Check that we can search with multiple preview stones and that the
+  /// reported action sequences yield the fields we expect.
+  #[test]
+  fn search_multiple_preview_stones() {
+    let mut field =
+      Field::from_matrix(&Matrix::<Option<()>>::new(8, 12), RotationSystem::default());
+    let mut factory = stone_factory(42);
+    let mut next_stones = factory.by_ref().take(3).collect::<VecDeque<_>>();
+    let mut arena = Arena::default();
+
+    for _ in 0..8 {
+      let mut stone = next_stones.pop_front().unwrap();
+      let () = next_stones.push_back(factory.next().unwrap());
+
+      let result = field.reset_stone(&mut stone);
+      assert!(result);
+
+      let best = search(
+        &mut arena,
+        &field,
+        &Weights::default(),
+        &stone,
+        next_stones.make_contiguous(),
+        || false,
+      );
+      let (actions, expected_field) = actions(&arena, best);
+      field = replay(field, stone, actions);
+      assert_eq!(Some(&field), expected_field);
+    }
+  }
+
+  /// Check that a search running out of time still reports a viable
+  /// action sequence for the first stone.
+  #[test]
+  fn search_deadline() {
+    let field = Field::from_matrix(&Matrix::<Option<()>>::new(8, 12), RotationSystem::default());
+    let mut stones = stone_factory(3).take(4).collect::<Vec<_>>();
+    let mut stone = stones.remove(0);
+    let result = field.reset_stone(&mut stone);
+    assert!(result);
+
+    let mut arena = Arena::default();
+    let best = search(
+      &mut arena,
+      &field,
+      &Weights::default(),
+      &stone,
+      &stones,
+      || true,
+    );
+    let (actions, expected_field) = actions(&arena, best);
+    let field = replay(field, stone, actions);
+    assert_eq!(Some(&field), expected_field);
+  }
+
+  /// Check that a ranked search places the stone at increasingly
+  /// worse locations.
+  #[test]
+  fn search_ranks() {
+    let stone = stone! {"
+      ##
+      ##
+    "};
+    let field = field! {"
+      ......
+      ......
+      ......
+      ####..
+      ####..
+    "};
+
+    let mut stone = stone.clone();
+    let result = field.reset_stone(&mut stone);
+    assert!(result);
+
+    let mut arena = Arena::default();
+    let fields = (0..8)
+      .map(|rank| {
+        let best = search_ranked(&mut arena, &field, &Weights::default(), &stone, rank);
+        let (actions, expected_field) = actions(&arena, best);
+        let expected_field = expected_field.cloned();
+        let field = replay(field.clone(), stone.clone(), actions);
+        assert_eq!(Some(field.clone()), expected_field);
+        field
+      })
+      .collect::<Vec<_>>();
+
+    // The best location clears both lines.
+    assert_eq!(fields[0], evaluate_single(field.clone(), &stone));
+    assert_eq!(fields[0].rows().iter().filter(|row| **row != 0).count(), 0);
+    let costs = fields
+      .iter()
+      .map(|field| estimate_cost(field, &Weights::default()))
+      .collect::<Vec<_>>();
+    assert!(costs.is_sorted(), "{costs:?}");
+    // There are only five locations, the worst of which is picked for
+    // all higher ranks.
+    assert_ne!(fields[3], fields[4]);
+    assert_eq!(fields[4], fields[7]);
+  }
+
+  /// Check that we can find the actions placing a stone at a given
+  /// location, if it is reachable.
+  #[test]
+  fn search_targets() {
+    let stone = stone! {"
+      ##
+      ##
+    "};
+    let field = field! {"
+      ......
+      ......
+      ......
+      ###...
+      ...###
+      ...###
+    "};
+
+    let mut stone = stone.clone();
+    let result = field.reset_stone(&mut stone);
+    assert!(result);
+
+    let mut arena = Arena::default();
+    let target = [
+      Point::new(4, 2),
+      Point::new(5, 3),
+      Point::new(4, 3),
+      Point::new(5, 2),
+    ];
+    let best = search_target(&mut arena, &field, &Weights::default(), &stone, &target);
+    let (actions, expected_field) = actions(&arena, best);
+    let expected_field = expected_field.cloned();
+    let merged = replay(field.clone(), stone.clone(), actions);
+    assert_eq!(Some(merged.clone()), expected_field);
+    assert_eq!(
+      merged,
+      field! {"
+        ......
+        ......
+        ....##
+        ###.##
+        ...###
+        ...###
+      "}
+    );
+
+    // The space below the overhang is enclosed.
+    let target = [
+      Point::new(0, 0),
+      Point::new(1, 0),
+      Point::new(0, 1),
+      Point::new(1, 1),
+    ];
+    let best = search_target(&mut arena, &field, &Weights::default(), &stone, &target);
+    assert_eq!(best, None);
+  }
+
   /// "Stress-test" playing a game from start to finish.
   #[test]
   #[ignore = "stress test; may take excessive time"]
   fn play_until_over() {
-    let field = Field::from_matrix(&Matrix::<Option<()>>::new(20, 40));
+    let field = Field::from_matrix(
+      &Matrix::<Option<()>>::new(20, 40),
+      RotationSystem::default(),
+    );
     play(field)
   }
 
@@ -1141,7 +1555,7 @@ mod tests {
     "};
 
     let () = b.iter(|| {
-      let _cost = estimate_cost(black_box(&field));
+      let _cost = estimate_cost(black_box(&field), &Weights::default());
       black_box(_cost);
     });
   }
@@ -1151,13 +1565,24 @@ mod tests {
   #[bench]
   fn bench_search(b: &mut Bencher) {
     let mut factory = stone_factory(1337);
-    let field = Field::from_matrix(&Matrix::<Option<()>>::new(20, 40));
+    let field = Field::from_matrix(
+      &Matrix::<Option<()>>::new(20, 40),
+      RotationSystem::default(),
+    );
     let mut stone = factory.next().unwrap();
     let result = field.reset_stone(&mut stone);
     assert!(result);
 
+    let mut arena = Arena::default();
     let () = b.iter(|| {
-      let _actions = search(black_box(&field), black_box(&stone), black_box(&[]));
+      let _actions = search(
+        &mut arena,
+        black_box(&field),
+        &Weights::default(),
+        black_box(&stone),
+        black_box(&[]),
+        || false,
+      );
       black_box(_actions);
     });
   }
@@ -1,10 +1,11 @@
 // Copyright (C) 2023-2025 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::ops::Deref as _;
-use std::rc::Rc;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::ops::Index;
 
 use super::super::Fieldlike as _;
 use super::super::Stonelike as _;
@@ -14,105 +15,116 @@ use super::Field;
 use super::Orientation;
 use super::Stone;
 use super::VisitedStones;
+use super::Weights;
 
 
+/// Rotate a stone inside of a field, taking into account the field's
+/// rotation system.
+fn rotate(field: &Field, stone: &mut Stone, left: bool) {
+  if !field.rotation_system().rotate(field, stone, left) {
+    // If no kick resulted in a valid position we still rotate the
+    // stone, resulting in a state with a collision that will be
+    // discarded.
+    let () = stone.rotate(field.rotation_system(), left);
+  }
+}
+
 /// Perform an action on a stone.
-fn perform_action(action: Action, mut stone: Stone) -> Stone {
+fn perform_action(action: Action, mut stone: Stone, field: &Field) -> Stone {
   let () = match action {
     Action::Merge => (),
     Action::MoveDown => stone.move_down(),
     Action::MoveLeft => stone.move_left(),
     Action::MoveRight => stone.move_right(),
-    Action::RotateLeft => stone.rotate_left(),
-    Action::RotateRight => stone.rotate_right(),
+    Action::RotateLeft => rotate(field, &mut stone, true),
+    Action::RotateRight => rotate(field, &mut stone, false),
   };
   stone
 }
 
-fn cost_for_action(action: Action, field: &Field) -> Cost {
+fn cost_for_action(action: Action, field: &Field, weights: &Weights) -> Cost {
   let width = f32::from(field.width());
   let height = f32::from(field.height());
   let piece_capacity = width * height;
 
-  match action {
+  let weight = match action {
     Action::Merge => panic!(),
-    Action::MoveDown => Cost::from(1.0 / piece_capacity),
-    Action::RotateLeft | Action::RotateRight => Cost::from(2.0 / piece_capacity),
-    Action::MoveLeft | Action::MoveRight => Cost::from(2.0 / piece_capacity),
-  }
+    Action::MoveDown => weights.move_down,
+    Action::RotateLeft | Action::RotateRight => weights.rotate,
+    Action::MoveLeft | Action::MoveRight => weights.move_sideways,
+  };
+  Cost::from(weight as f32 / piece_capacity)
 }
 
 
 /// A type helping with the "expansion" of `State` objects.
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug)]
 pub(super) enum Expander {
-  MoveDown(Rc<State>),
-  MoveLeft(Rc<State>),
-  MoveRight(Rc<State>),
-  RotateLeft(Rc<State>),
-  RotateRight(Rc<State>),
+  MoveDown(StateId),
+  MoveLeft(StateId),
+  MoveRight(StateId),
+  RotateLeft(StateId),
+  RotateRight(StateId),
   Done,
 }
 
 impl Expander {
   #[inline]
-  fn expand(state: Rc<State>) -> Self {
-    debug_assert!(!state.has_collision());
+  fn expand(arena: &Arena, state: StateId) -> Self {
+    debug_assert!(!arena.has_collision(state));
 
     Self::MoveDown(state)
   }
-}
 
-impl Iterator for Expander {
-  type Item = Rc<State>;
-
-  fn next(&mut self) -> Option<Self::Item> {
+  /// Derive the next [`State`], if any, inside the given [`Arena`],
+  /// with actions being weighted as per `weights`.
+  pub fn next(&mut self, arena: &mut Arena, weights: &Weights) -> Option<StateId> {
     loop {
       // The basic principle here is as follows: based on the last move
       // action performed we decide what further move actions are valid; if
       // our last move action was a move down all bets are off; if the last
       // move was move to the right there is no use in moving left at all;
       // similarly for a left move
-      match self.clone() {
+      match *self {
         Self::MoveDown(state) => {
-          *self = Self::MoveLeft(Rc::clone(&state));
-          break state.derive(Action::MoveDown)
+          *self = Self::MoveLeft(state);
+          break arena.derive(state, Action::MoveDown, weights)
         },
         Self::MoveLeft(state) => {
-          *self = Self::MoveRight(Rc::clone(&state));
-          if state.action != Some(Action::MoveRight) {
-            break state.derive(Action::MoveLeft)
+          *self = Self::MoveRight(state);
+          if arena[state].action != Some(Action::MoveRight) {
+            break arena.derive(state, Action::MoveLeft, weights)
           }
         },
         Self::MoveRight(state) => {
-          *self = Self::RotateLeft(Rc::clone(&state));
-          if state.action != Some(Action::MoveLeft) {
-            break state.derive(Action::MoveRight)
+          *self = Self::RotateLeft(state);
+          if arena[state].action != Some(Action::MoveLeft) {
+            break arena.derive(state, Action::MoveRight, weights)
           }
         },
         Self::RotateLeft(state) => {
-          *self = Self::RotateRight(Rc::clone(&state));
+          *self = Self::RotateRight(state);
 
-          let stone = state.stone.as_ref()?;
+          let stone = arena[state].stone.as_ref()?;
           if stone.orientation() != Orientation::Rotated180 {
             // If we are rotated by 90° we got rotated to the right once
             // in which case we do not want to rotate left -- in the
             // remaining cases (0° and 270°) we do.
             if stone.orientation() != Orientation::Rotated90 {
-              break state.derive(Action::RotateLeft)
+              break arena.derive(state, Action::RotateLeft, weights)
             }
           }
         },
         Self::RotateRight(state) => {
           *self = Self::Done;
 
-          let stone = state.stone.as_ref()?;
+          let stone = arena[state].stone.as_ref()?;
           if stone.orientation() != Orientation::Rotated180 {
             // If we are rotated by 270° we got rotated to the left once
             // in which case we do not want to rotate right -- in the
             // remaining cases (0° and 90°) we do.
             if stone.orientation() != Orientation::Rotated270 {
-              break state.derive(Action::RotateRight)
+              break arena.derive(state, Action::RotateRight, weights)
             }
           }
         },
@@ -123,34 +135,48 @@ impl Iterator for Expander {
 }
 
 
+/// The index of a [`State`] inside an [`Arena`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct StateId(u32);
+
+/// The index of a [`Field`] inside an [`Arena`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct FieldId(u32);
+
+/// The index of a set of [`VisitedStones`] inside an [`Arena`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct VisitedId(u32);
+
+
 /// An object representing one state in the search for the next action
 /// to perform on a [`Stone`].
 ///
 /// In abstract terms, a [`State`] is comprised mainly of a [`Field`]
-/// and a [`Stone`]. The `Stone` is moved by [deriving][Self::derive] a
+/// and a [`Stone`]. The `Stone` is moved by [deriving][Arena::derive] a
 /// new `State` by performing an [`Action`] on the `Stone`.
 ///
 /// Once no other movement is possible or desired, the `State` should be
-/// [merged][Self::merged] and a new `Field` and `Stone` be set. This
+/// [merged][Arena::merged] and a new `Field` and `Stone` be set. This
 /// process repeats.
 ///
 /// If all preview stones are exhausted the search can eventually be
-/// concluded and the `State` be [finalized][Self::finalize].
+/// concluded and the `State` be [finalized][Arena::finalize].
 ///
-/// `State` objects form a list via their parent `State`. Once finalized
-/// this list can be traversed and the set of actions necessary to reach
-/// this final state be extracted for later replay on the "live" objects.
+/// `State` objects live in an [`Arena`] and form a list via the index
+/// of their parent `State`. Once finalized this list can be traversed
+/// and the set of actions necessary to reach this final state be
+/// extracted for later replay on the "live" objects.
 #[derive(Debug)]
 #[repr(align(64))]
 pub(super) struct State {
   /// The parent state, i.e., the one this state was created from by
   /// applying the given action.
-  pub parent: Option<Rc<Self>>,
-  pub field: Rc<Field>,
+  pub parent: Option<StateId>,
+  pub field: FieldId,
   /// The stone currently being moved. If not present the state is
   /// considered "final".
   pub stone: Option<Stone>,
-  pub visited: Rc<RefCell<VisitedStones>>,
+  pub visited: VisitedId,
   /// The index of the next stone to evaluate. The index is incremented
   /// on every update. It has no meaning to the `State` itself, but is
   /// logically associated in it.
@@ -172,16 +198,101 @@ pub(super) struct State {
 }
 
 impl State {
-  pub fn initial(field: Field, cost: Cost, stone: Stone, count: u8) -> Rc<Self> {
+  /// Retrieve this [`State`]'s accumulated (total) cost estimate.
+  #[inline]
+  pub fn cost(&self) -> Cost {
+    self.action_cost + self.field_cost + self.stone_cost
+  }
+}
+
+impl PartialEq for State {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for State {}
+
+impl PartialOrd for State {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for State {
+  fn cmp(&self, other: &Self) -> Ordering {
+    // We want states to be ordered by "minimum cost" because they'll be
+    // used in conjunction with a max-heap. So invert the cost
+    // comparison.
+    self.cost().cmp(&other.cost()).reverse()
+  }
+}
+
+
+/// The storage for all objects created during a search.
+///
+/// Objects are referenced by index and live until the arena is
+/// [cleared][Arena::clear]. Clearing retains allocated memory, making
+/// an `Arena` cheap to reuse for subsequent searches.
+#[derive(Default)]
+pub(super) struct Arena {
+  /// All states created.
+  states: Vec<State>,
+  /// All fields created, shared by the states derived from one
+  /// another.
+  fields: Vec<Field>,
+  /// The sets of visited stones, one per field. Entries beyond
+  /// `visited_len` are unused and kept around for reuse.
+  visited: Vec<VisitedStones>,
+  /// The number of sets of visited stones in use.
+  visited_len: usize,
+}
+
+impl Arena {
+  /// Remove all objects from the arena.
+  pub fn clear(&mut self) {
+    let () = self.states.clear();
+    let () = self.fields.clear();
+    self.visited_len = 0;
+  }
+
+  fn push_state(&mut self, state: State) -> StateId {
+    let id = StateId(self.states.len() as u32);
+    let () = self.states.push(state);
+    id
+  }
+
+  /// Add a field along with an (empty) set of visited stones for it.
+  fn push_field(&mut self, field: Field) -> (FieldId, VisitedId) {
+    let (width, height) = (field.width(), field.height());
+    let visited = if let Some(visited) = self.visited.get_mut(self.visited_len) {
+      let () = visited.reset(width, height);
+      VisitedId(self.visited_len as u32)
+    } else {
+      let () = self.visited.push(VisitedStones::new(width, height));
+      VisitedId(self.visited_len as u32)
+    };
+    self.visited_len += 1;
+
+    let field_id = FieldId(self.fields.len() as u32);
+    let () = self.fields.push(field);
+    (field_id, visited)
+  }
+
+  /// Retrieve the field of the given [`State`].
+  #[inline]
+  pub fn field(&self, state: StateId) -> &Field {
+    &self.fields[self[state].field.0 as usize]
+  }
+
+  pub fn initial(&mut self, field: Field, cost: Cost, stone: Stone, count: u8) -> StateId {
     debug_assert!(!field.collides(&stone));
 
-    let slf = Self {
+    let (field, visited) = self.push_field(field);
+    let state = State {
       parent: None,
-      visited: Rc::new(RefCell::new(VisitedStones::new(
-        field.width(),
-        field.height(),
-      ))),
-      field: Rc::new(field),
+      visited,
+      field,
       stone: Some(stone),
       index: 0,
       count,
@@ -191,15 +302,16 @@ impl State {
       stone_cost: Cost::from(count),
     };
 
-    Rc::new(slf)
+    self.push_state(state)
   }
 
-  pub fn derive(self: &Rc<Self>, action: Action) -> Option<Rc<Self>> {
-    debug_assert!(!self.has_collision());
+  pub fn derive(&mut self, parent: StateId, action: Action, weights: &Weights) -> Option<StateId> {
+    debug_assert!(!self.has_collision(parent));
 
-    let Self {
+    let field = self.field(parent);
+    let State {
       parent: _,
-      field,
+      field: field_id,
       stone,
       visited,
       index,
@@ -208,191 +320,146 @@ impl State {
       action_cost,
       field_cost,
       stone_cost,
-    } = self.deref();
+    } = &self[parent];
 
-    let slf = Self {
-      parent: Some(Rc::clone(self)),
-      field: Rc::clone(field),
-      stone: Some(perform_action(action, stone.as_ref()?.clone())),
-      visited: Rc::clone(visited),
+    let state = State {
+      parent: Some(parent),
+      field: *field_id,
+      stone: Some(perform_action(action, stone.as_ref()?.clone(), field)),
+      visited: *visited,
       index: *index,
       count: *count,
       action: Some(action),
-      action_cost: *action_cost + cost_for_action(action, field),
+      action_cost: *action_cost + cost_for_action(action, field, weights),
       field_cost: *field_cost,
       stone_cost: *stone_cost,
     };
 
     // Action cost should be increasing as we make more actions.
-    debug_assert!(*action_cost < slf.action_cost);
+    debug_assert!(*action_cost < state.action_cost);
 
-    Some(Rc::new(slf))
+    Some(self.push_state(state))
   }
 
-  pub fn merged(self: &Rc<Self>, field: Field, cost: Cost, stone: Stone) -> Rc<Self> {
-    debug_assert!(!self.has_collision());
+  pub fn merged(&mut self, parent: StateId, field: Field, cost: Cost, stone: Stone) -> StateId {
+    debug_assert!(!self.has_collision(parent));
 
-    let Self {
-      parent: _,
-      field: _,
-      stone: _,
-      visited: _,
+    let State {
       index,
       count,
-      action: _,
       action_cost,
-      field_cost: _,
       stone_cost,
-    } = self.deref();
+      ..
+    } = self[parent];
 
     let index = index + 1;
+    let (field, visited) = self.push_field(field);
 
-    let slf = Self {
-      parent: Some(Rc::clone(self)),
-      visited: Rc::new(RefCell::new(VisitedStones::new(
-        field.width(),
-        field.height(),
-      ))),
-      field: Rc::new(field),
+    let state = State {
+      parent: Some(parent),
+      visited,
+      field,
       stone: Some(stone),
       index,
-      count: *count,
+      count,
       action: Some(Action::Merge),
-      action_cost: *action_cost,
+      action_cost,
       field_cost: cost,
       stone_cost: Cost::from(count - index),
     };
 
     // Stone cost should be decreasing as we move on to new stones.
-    debug_assert!(*stone_cost > slf.stone_cost);
+    debug_assert!(stone_cost > state.stone_cost);
 
-    Rc::new(slf)
+    self.push_state(state)
   }
 
-  pub fn finalize(self: &Rc<Self>, field: Field, cost: Cost) -> Rc<Self> {
-    debug_assert!(!self.has_collision());
+  pub fn finalize(&mut self, parent: StateId, field: Field, cost: Cost) -> StateId {
+    debug_assert!(!self.has_collision(parent));
 
-    let Self {
-      parent: _,
-      field: _,
-      stone: _,
+    let State {
       visited,
       index,
       count,
-      action: _,
       action_cost,
-      field_cost: _,
       stone_cost,
-    } = self.deref();
-
-    let slf = Self {
-      parent: Some(Rc::clone(self)),
-      visited: Rc::clone(visited),
-      field: Rc::new(field),
-      stone: None,
-      index: *index,
-      count: *count,
-      action: Some(Action::Merge),
-      action_cost: *action_cost,
-      field_cost: cost,
-      stone_cost: *stone_cost,
-    };
+      ..
+    } = self[parent];
 
-    Rc::new(slf)
-  }
+    let field_id = FieldId(self.fields.len() as u32);
+    let () = self.fields.push(field);
 
-  pub fn reparent(self: &Rc<Self>, parent: Option<Rc<Self>>) -> Rc<Self> {
-    let Self {
-      parent: _,
-      field,
-      stone,
+    let state = State {
+      parent: Some(parent),
       visited,
+      field: field_id,
+      stone: None,
       index,
       count,
-      action,
+      action: Some(Action::Merge),
       action_cost,
-      field_cost,
+      field_cost: cost,
       stone_cost,
-    } = self.deref();
-
-    let slf = Self {
-      parent,
-      field: Rc::clone(field),
-      stone: stone.clone(),
-      visited: Rc::clone(visited),
-      index: *index,
-      count: *count,
-      action: *action,
-      action_cost: *action_cost,
-      field_cost: *field_cost,
-      stone_cost: *stone_cost,
     };
 
-    Rc::new(slf)
+    self.push_state(state)
   }
 
-  /// "Expand" this `State` into all possible derivations.
+  /// "Expand" the given [`State`] into all possible derivations.
   #[inline]
-  pub fn expand(self: &Rc<Self>) -> Expander {
-    Expander::expand(Rc::clone(self))
+  pub fn expand(&self, state: StateId) -> Expander {
+    Expander::expand(self, state)
   }
 
   /// Mark the [`State`] as visited.
   #[inline]
-  pub fn visit(&self) {
-    debug_assert!(!self.was_visited());
-    if let Some(stone) = &self.stone {
-      let () = self.visited.borrow_mut().visit(stone);
+  pub fn visit(&mut self, state: StateId) {
+    debug_assert!(!self.was_visited(state));
+
+    let State { stone, visited, .. } = &self.states[state.0 as usize];
+    if let Some(stone) = stone {
+      let () = self.visited[visited.0 as usize].visit(stone);
     }
   }
 
-  /// Check whether this [`State`] was already visited.
+  /// Check whether the [`State`] was already visited.
   #[inline]
-  pub fn was_visited(&self) -> bool {
-    if let Some(stone) = &self.stone {
-      self.visited.borrow().contains(stone)
+  pub fn was_visited(&self, state: StateId) -> bool {
+    let State { stone, visited, .. } = &self[state];
+    if let Some(stone) = stone {
+      self.visited[visited.0 as usize].contains(stone)
     } else {
       false
     }
   }
 
-  /// Check whether this [`State`] has a collision.
+  /// Check whether the [`State`] has a collision.
   #[inline]
-  pub fn has_collision(&self) -> bool {
-    if let Some(stone) = &self.stone {
-      self.field.collides(stone)
+  pub fn has_collision(&self, state: StateId) -> bool {
+    if let Some(stone) = &self[state].stone {
+      self.field(state).collides(stone)
     } else {
       false
     }
   }
-
-  /// Retrieve this [`State`]'s accumulated (total) cost estimate.
-  #[inline]
-  pub fn cost(&self) -> Cost {
-    self.action_cost + self.field_cost + self.stone_cost
-  }
-}
-
-impl PartialEq for State {
-  fn eq(&self, other: &Self) -> bool {
-    self.cmp(other) == Ordering::Equal
-  }
 }
 
-impl Eq for State {}
+impl Index<StateId> for Arena {
+  type Output = State;
 
-impl PartialOrd for State {
-  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-    Some(self.cmp(other))
+  #[inline]
+  fn index(&self, index: StateId) -> &Self::Output {
+    &self.states[index.0 as usize]
   }
 }
 
-impl Ord for State {
-  fn cmp(&self, other: &Self) -> Ordering {
-    // We want states to be ordered by "minimum cost" because they'll be
-    // used in conjunction with a max-heap. So invert the cost
-    // comparison.
-    self.cost().cmp(&other.cost()).reverse()
+impl Debug for Arena {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Arena")
+      .field("states", &self.states.len())
+      .field("fields", &self.fields.len())
+      .field("visited", &self.visited_len)
+      .finish()
   }
 }
 
@@ -431,34 +498,61 @@ mod tests {
     "};
     assert!(field.reset_stone(&mut stone));
 
-    let state = State::initial(field, Cost::none(), stone.clone(), 0);
-    let mut expanded = state.expand();
+    let mut arena = Arena::default();
+    let state = arena.initial(field, Cost::none(), stone.clone(), 0);
+    let mut expanded = arena.expand(state);
 
-    let next = expanded.next().unwrap();
+    let next = expanded.next(&mut arena, &Weights::default()).unwrap();
     let mut moved_down = stone.clone();
     let () = moved_down.move_down();
-    assert_stones_eq(next.stone.as_ref().unwrap(), &moved_down);
+    assert_stones_eq(arena[next].stone.as_ref().unwrap(), &moved_down);
 
-    let next = expanded.next().unwrap();
+    let next = expanded.next(&mut arena, &Weights::default()).unwrap();
     let mut moved_left = stone.clone();
     let () = moved_left.move_left();
-    assert_stones_eq(next.stone.as_ref().unwrap(), &moved_left);
+    assert_stones_eq(arena[next].stone.as_ref().unwrap(), &moved_left);
 
-    let next = expanded.next().unwrap();
+    let next = expanded.next(&mut arena, &Weights::default()).unwrap();
     let mut moved_right = stone.clone();
     let () = moved_right.move_right();
-    assert_stones_eq(next.stone.as_ref().unwrap(), &moved_right);
+    assert_stones_eq(arena[next].stone.as_ref().unwrap(), &moved_right);
 
-    let next = expanded.next().unwrap();
+    let next = expanded.next(&mut arena, &Weights::default()).unwrap();
     let mut rotated_left = stone.clone();
     let () = rotated_left.rotate_left();
-    assert_stones_eq(next.stone.as_ref().unwrap(), &rotated_left);
+    assert_stones_eq(arena[next].stone.as_ref().unwrap(), &rotated_left);
 
-    let next = expanded.next().unwrap();
+    let next = expanded.next(&mut arena, &Weights::default()).unwrap();
     let mut rotated_right = stone.clone();
     let () = rotated_right.rotate_right();
-    assert_stones_eq(next.stone.as_ref().unwrap(), &rotated_right);
+    assert_stones_eq(arena[next].stone.as_ref().unwrap(), &rotated_right);
+
+    assert_eq!(expanded.next(&mut arena, &Weights::default()), None);
+  }
+
+  /// Check that a cleared [`Arena`] can be reused.
+  #[test]
+  fn arena_reuse() {
+    let mut stone = stone! {"
+      ##
+      ##
+    "};
+    let field = field! {"
+      ....
+      ....
+      ##..
+    "};
+    assert!(field.reset_stone(&mut stone));
+
+    let mut arena = Arena::default();
+    let state = arena.initial(field.clone(), Cost::none(), stone.clone(), 0);
+    let () = arena.visit(state);
+    assert!(arena.was_visited(state));
 
-    assert_eq!(expanded.next(), None);
+    let () = arena.clear();
+    let state = arena.initial(field.clone(), Cost::none(), stone, 0);
+    assert_eq!(state, StateId(0));
+    assert!(!arena.was_visited(state));
+    assert_eq!(arena.field(state), &field);
   }
 }
@@ -7,6 +7,7 @@ use std::vec;
 
 use crate::Point;
 
+use super::super::RotationSystem;
 use super::super::Stonelike;
 #[cfg(test)]
 use super::super::matrix::Matrix;
@@ -20,26 +21,33 @@ pub(crate) struct Stone {
   pieces: Box<[((), Point<i16>)]>,
   /// The stone's orientation, relative to its initial state.
   orientation: Orientation,
+  /// The stone's rotation state, i.e., its orientation relative to the
+  /// one it was created in. Contrary to `orientation` this state is
+  /// not relative to the stone's state at the start of the search.
+  rotation_state: Orientation,
 }
 
 impl Stone {
-  /// Create an AI stone from the locations of individual pieces.
-  pub fn from_pieces<P>(pieces: P) -> Self
+  /// Create an AI stone from the locations of individual pieces and
+  /// the stone's rotation state.
+  pub fn from_pieces<P>(pieces: P, rotation_state: Orientation) -> Self
   where
     P: Iterator<Item = Point<i16>>,
   {
     Self {
       pieces: pieces.map(|location| ((), location)).collect(),
       orientation: Orientation::Rotated0,
+      rotation_state,
     }
   }
 
   #[cfg(test)]
-  pub(super) fn from_matrix<P>(matrix: &Matrix<Option<P>>) -> Self {
+  pub(crate) fn from_matrix<P>(matrix: &Matrix<Option<P>>) -> Self {
     Self::from_pieces(
       matrix
         .iter()
         .filter_map(|(p, location)| p.as_ref().map(|_p| location)),
+      Orientation::Rotated0,
     )
   }
 
@@ -75,14 +83,22 @@ impl Stonelike for Stone {
     Vec::from(self.pieces).into_iter()
   }
 
-  fn rotate(&mut self, left: bool) {
+  #[inline]
+  fn rotation_state(&self) -> Orientation {
+    self.rotation_state
+  }
+
+  fn rotate(&mut self, system: RotationSystem, left: bool) {
+    // The pivot depends on the rotation state prior to the rotation.
+    let () = rotate(self, system, left);
+
     if left {
       self.orientation = self.orientation.rotate_left();
+      self.rotation_state = self.rotation_state.rotate_left();
     } else {
       self.orientation = self.orientation.rotate_right();
+      self.rotation_state = self.rotation_state.rotate_right();
     }
-
-    rotate(self, left)
   }
 }
 
@@ -191,10 +207,10 @@ mod tests {
       Point::new(0, 2),
       Point::new(0, 3),
     ];
-    let mut stone = Stone::from_pieces(pieces.into_iter());
+    let mut stone = Stone::from_pieces(pieces.into_iter(), Orientation::Rotated0);
 
     let () = b.iter(|| {
-      let () = black_box(&mut stone).rotate(black_box(false));
+      let () = black_box(&mut stone).rotate(RotationSystem::default(), black_box(false));
     });
   }
 }
@@ -2,12 +2,18 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use std::cmp::Ordering;
+use std::fs::Permissions;
+use std::fs::set_permissions;
+use std::fs::write;
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::Path;
+use std::path::PathBuf;
 
 use super::super::Matrix;
 use super::super::Stonelike;
 
 
-pub(super) fn parse_matrix(description: &str) -> Matrix<Option<()>> {
+pub(crate) fn parse_matrix(description: &str) -> Matrix<Option<()>> {
   let lines = description.lines().map(str::trim).filter(|s| !s.is_empty());
   let w = lines
     .clone()
@@ -50,24 +56,31 @@ macro_rules! matrix {
   ($matrix:literal) => {{ $crate::game::ai::util::parse_matrix($matrix) }};
 }
 
+pub(crate) use matrix;
+
 
 macro_rules! field {
-  ($field:literal) => {{ $crate::game::ai::Field::from_matrix(&$crate::game::ai::util::parse_matrix($field)) }};
+  ($field:literal) => {{
+    $crate::game::ai::Field::from_matrix(
+      &$crate::game::ai::util::parse_matrix($field),
+      $crate::game::RotationSystem::default(),
+    )
+  }};
 }
 
-pub(super) use field;
+pub(crate) use field;
 
 
 macro_rules! stone {
   ($stone:literal) => {{ $crate::game::ai::Stone::from_matrix(&$crate::game::ai::util::parse_matrix($stone)) }};
 }
 
-pub(super) use stone;
+pub(crate) use stone;
 
 
 /// Assert that two stones have pieces at the same positions.
 #[track_caller]
-pub(super) fn assert_stones_eq<S>(stone1: &S, stone2: &S)
+pub(crate) fn assert_stones_eq<S>(stone1: &S, stone2: &S)
 where
   S: Stonelike,
 {
@@ -81,6 +94,33 @@ where
 }
 
 
+/// Write a shell script acting as a bot to `dir`, for it to be
+/// launched via [`Bot::launch`][super::Bot::launch].
+///
+/// The bot performs the handshake and then answers each request for
+/// a suggestion by running the shell commands in `respond`, which
+/// have access to the request in the `request` variable.
+pub(crate) fn write_bot(dir: &Path, respond: &str) -> PathBuf {
+  let path = dir.join("bot.sh");
+  let script = format!(
+    r#"#!/bin/sh
+echo '{{"type":"info","name":"test-bot"}}'
+read -r rules
+echo '{{"type":"ready"}}'
+while read -r request; do
+  case "$request" in
+    *'"type":"quit"'*) exit 0;;
+  esac
+  {respond}
+done
+"#
+  );
+  let () = write(&path, script).unwrap();
+  let () = set_permissions(&path, Permissions::from_mode(0o755)).unwrap();
+  path
+}
+
+
 #[cfg(test)]
 mod tests {
   /// Check that we can correctly create a `Matrix` object from a
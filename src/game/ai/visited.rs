@@ -30,6 +30,16 @@ impl VisitedStones {
     }
   }
 
+  /// Reset the object to contain no stones, for a field of the given
+  /// dimensions.
+  pub fn reset(&mut self, width: i16, height: i16) {
+    if self.field.width() == width + 2 * OFFSET && self.field.height() == height + 2 * OFFSET {
+      let () = self.field.clear();
+    } else {
+      *self = Self::new(width, height);
+    }
+  }
+
   #[inline]
   pub fn visit(&mut self, stone: &Stone) {
     debug_assert!(!self.contains(stone));
@@ -0,0 +1,55 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use serde::Deserialize;
+use serde::Serialize;
+
+
+/// The weights the AI uses for evaluating fields and the actions
+/// leading to them.
+///
+/// Field related costs are normalized to the field's capacity, while
+/// action costs are per action, relative to the field's capacity.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Weights {
+  /// The weight of holes, i.e., empty cells with a piece above them.
+  pub holes: f64,
+  /// The weight of pieces covering holes, scaled by the height of the
+  /// top most piece.
+  pub coverage: f64,
+  /// The weight of the size of the stack, with stacks in the center
+  /// being valued higher than those at the sides.
+  pub stack: f64,
+  /// The weight of the surface's roughness, i.e., the height
+  /// differences between neighboring columns.
+  pub roughness: f64,
+  /// The weight of the height of the top most piece.
+  pub high_point: f64,
+  /// The exponent the (relative) height of the top most piece is
+  /// raised to.
+  pub high_point_exponent: u8,
+  /// The cost of moving a stone down.
+  pub move_down: f64,
+  /// The cost of moving a stone to the left or right.
+  pub move_sideways: f64,
+  /// The cost of rotating a stone.
+  pub rotate: f64,
+}
+
+impl Default for Weights {
+  fn default() -> Self {
+    Self {
+      holes: 4.0,
+      coverage: 0.1,
+      stack: 1.0,
+      roughness: 1.5,
+      high_point: 8.0,
+      high_point_exponent: 16,
+      move_down: 1.0,
+      move_sideways: 2.0,
+      rotate: 2.0,
+    }
+  }
+}
@@ -1,9 +1,20 @@
 // Copyright (C) 2023-2026 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::path::PathBuf;
+
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::Difficulty;
+use super::Gravity;
+use super::Mode;
+use super::Randomizer;
+use super::RotationSystem;
+use super::Scoring;
+use super::StoneConfig;
+use super::StoneSet;
+
 
 fn default_start_level() -> u16 {
   1
@@ -25,6 +36,42 @@ fn default_preview_stone_count() -> u8 {
   1
 }
 
+fn default_gravity_base() -> f64 {
+  1.0
+}
+
+fn default_gravity_growth() -> f64 {
+  0.2
+}
+
+fn default_gravity_exponent() -> f64 {
+  1.0
+}
+
+fn default_sprint_lines() -> u16 {
+  40
+}
+
+fn default_ultra_time_secs() -> u32 {
+  180
+}
+
+fn default_dig_rows() -> u16 {
+  10
+}
+
+fn default_lock_reset_limit() -> u8 {
+  15
+}
+
+fn default_ai_lookahead() -> u8 {
+  1
+}
+
+fn default_ai_bot_timeout_ms() -> u32 {
+  1000
+}
+
 
 /// The configuration for a [`Game`][super::Game].
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
@@ -45,9 +92,104 @@ pub struct Config {
   /// The number of upcoming stones to show.
   #[serde(default = "default_preview_stone_count")]
   pub preview_stone_count: u8,
+  /// The mode the game is played in.
+  #[serde(default)]
+  pub mode: Mode,
+  /// The number of lines to clear in [`Mode::Sprint`].
+  #[serde(default = "default_sprint_lines")]
+  pub sprint_lines: u16,
+  /// The time available in [`Mode::Ultra`], in seconds.
+  #[serde(default = "default_ultra_time_secs")]
+  pub ultra_time_secs: u32,
+  /// The number of lines of garbage to start with in [`Mode::Dig`].
+  #[serde(default = "default_dig_rows")]
+  pub dig_rows: u16,
+  /// The interval at which an additional line of garbage is inserted
+  /// in [`Mode::Dig`], in milliseconds. Zero disables the insertion.
+  #[serde(default)]
+  pub dig_interval_ms: u32,
   /// Whether or not the AI is enabled initially.
   #[serde(default)]
   pub enable_ai: bool,
+  /// The maximum number of preview stones the AI takes into account
+  /// when searching for the best placement of the active stone. Only
+  /// the 32 most promising placements are carried over from one stone
+  /// to the next, so each additional stone adds about as much effort
+  /// as 32 single stone searches.
+  #[serde(default = "default_ai_lookahead")]
+  pub ai_lookahead: u8,
+  /// The time the AI may spend searching for the placement of a
+  /// stone, in milliseconds. Once it is used up, the best placement
+  /// found so far is used. Zero means no limit. Note that with a limit
+  /// the AI's decisions depend on the speed of the machine it runs on.
+  #[serde(default)]
+  pub ai_time_budget_ms: u32,
+  /// How well the AI plays, i.e., how fast it acts and how often it
+  /// deliberately places stones suboptimally.
+  #[serde(default)]
+  pub ai_difficulty: Difficulty,
+  /// The path to an external bot to play in place of the builtin AI,
+  /// if any. The bot is driven via a line based JSON protocol over its
+  /// standard input and output.
+  #[serde(default)]
+  pub ai_bot: Option<PathBuf>,
+  /// The time the external bot may take to respond to a request for
+  /// the placement of a stone, in milliseconds. If it does not respond
+  /// in time, the stone is left to gravity.
+  #[serde(default = "default_ai_bot_timeout_ms")]
+  pub ai_bot_timeout_ms: u32,
+  /// The time a stone that touched down may still be moved before it
+  /// gets locked in place, in milliseconds. Zero, the default,
+  /// disables the lock delay, locking stones as soon as they touch
+  /// down.
+  #[serde(default)]
+  pub lock_delay_ms: u32,
+  /// The maximum number of times moving or rotating a stone that
+  /// touched down resets the lock delay.
+  #[serde(default = "default_lock_reset_limit")]
+  pub lock_reset_limit: u8,
+  /// The rules used for awarding points.
+  #[serde(default)]
+  pub scoring: Scoring,
+  /// The strategy used for selecting upcoming stones.
+  #[serde(default)]
+  pub randomizer: Randomizer,
+  /// The set of stones to play with.
+  #[serde(default)]
+  pub stone_set: StoneSet,
+  /// The stones to play with when using [`StoneSet::Custom`].
+  #[serde(default)]
+  pub stones: Vec<StoneConfig>,
+  /// The curve determining how fast stones fall at each level.
+  #[serde(default)]
+  pub gravity: Gravity,
+  /// The drop speed for each level, starting at level one, in units
+  /// per second. Used by [`Gravity::Table`]; the last entry applies to
+  /// all subsequent levels.
+  #[serde(default)]
+  pub gravity_table: Vec<f64>,
+  /// The drop speed at level zero, in units per second, as used by
+  /// [`Gravity::Curve`].
+  #[serde(default = "default_gravity_base")]
+  pub gravity_base: f64,
+  /// The factor by which the level dependent part of the drop speed is
+  /// scaled, as used by [`Gravity::Curve`].
+  #[serde(default = "default_gravity_growth")]
+  pub gravity_growth: f64,
+  /// The exponent the level is raised to, as used by
+  /// [`Gravity::Curve`].
+  #[serde(default = "default_gravity_exponent")]
+  pub gravity_exponent: f64,
+  /// The rotation system governing wall kicks.
+  #[serde(default)]
+  pub rotation_system: RotationSystem,
+  /// Whether or not the player can put stones on hold.
+  #[serde(default)]
+  pub enable_hold: bool,
+  /// Whether or not to show a "ghost" of the active stone at the
+  /// location it would be dropped to.
+  #[serde(default)]
+  pub enable_ghost: bool,
   /// Whether or not to use dark mode by default.
   #[serde(default)]
   pub enable_dark_mode: bool,
@@ -61,7 +203,31 @@ impl Default for Config {
       field_width: default_field_width(),
       field_height: default_field_height(),
       preview_stone_count: default_preview_stone_count(),
+      lock_delay_ms: 0,
+      lock_reset_limit: default_lock_reset_limit(),
+      scoring: Default::default(),
+      randomizer: Default::default(),
+      stone_set: Default::default(),
+      stones: Default::default(),
+      gravity: Default::default(),
+      gravity_table: Default::default(),
+      gravity_base: default_gravity_base(),
+      gravity_growth: default_gravity_growth(),
+      gravity_exponent: default_gravity_exponent(),
+      rotation_system: Default::default(),
+      mode: Default::default(),
+      sprint_lines: default_sprint_lines(),
+      ultra_time_secs: default_ultra_time_secs(),
+      dig_rows: default_dig_rows(),
+      dig_interval_ms: Default::default(),
       enable_ai: Default::default(),
+      ai_lookahead: default_ai_lookahead(),
+      ai_time_budget_ms: Default::default(),
+      ai_difficulty: Default::default(),
+      ai_bot: Default::default(),
+      ai_bot_timeout_ms: default_ai_bot_timeout_ms(),
+      enable_hold: Default::default(),
+      enable_ghost: Default::default(),
       enable_dark_mode: Default::default(),
     }
   }
@@ -0,0 +1,164 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::Error;
+use anyhow::Result;
+use anyhow::bail;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Rng;
+
+
+/// How well the auto-playing AI plays.
+///
+/// With anything but [`Difficulty::Perfect`], the AI plays at a human
+/// pace and every now and then places a stone at a suboptimal
+/// location, making it suitable as a sparring opponent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+  /// Slow and sloppy play.
+  Easy,
+  /// Play at the pace of a casual player, with the occasional
+  /// misplaced stone.
+  Medium,
+  /// Fast play with rare mistakes.
+  Hard,
+  /// Play as well and as fast as possible.
+  #[default]
+  Perfect,
+}
+
+impl Difficulty {
+  /// Retrieve the textual representation of the difficulty, as used in
+  /// configuration files.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Easy => "easy",
+      Self::Medium => "medium",
+      Self::Hard => "hard",
+      Self::Perfect => "perfect",
+    }
+  }
+
+  /// The maximum number of actions the AI performs per second, if
+  /// limited.
+  fn actions_per_sec(&self) -> Option<u32> {
+    match self {
+      Self::Easy => Some(4),
+      Self::Medium => Some(8),
+      Self::Hard => Some(15),
+      Self::Perfect => None,
+    }
+  }
+
+  /// The minimum time between two consecutive actions of the AI.
+  pub(super) fn action_interval(&self) -> Duration {
+    self
+      .actions_per_sec()
+      .map(|actions| Duration::from_secs(1) / actions)
+      .unwrap_or_default()
+  }
+
+  /// The time the AI takes before performing the first action for a
+  /// new stone.
+  pub(super) fn reaction_time(&self) -> Duration {
+    match self {
+      Self::Easy => Duration::from_millis(600),
+      Self::Medium => Duration::from_millis(350),
+      Self::Hard => Duration::from_millis(150),
+      Self::Perfect => Duration::ZERO,
+    }
+  }
+
+  /// The likelihood of a stone being placed suboptimally, in percent,
+  /// along with the maximum rank of the placement picked instead.
+  fn mistakes(&self) -> (u32, u32) {
+    match self {
+      Self::Easy => (25, 6),
+      Self::Medium => (10, 4),
+      Self::Hard => (3, 2),
+      Self::Perfect => (0, 0),
+    }
+  }
+
+  /// Decide on the rank of the placement the AI picks for a stone,
+  /// with zero being the best one.
+  pub(super) fn pick_rank(&self, rng: &Rng) -> usize {
+    let (percent, max_rank) = self.mistakes();
+    // Note that we only consume random numbers when mistakes are
+    // possible, keeping games played perfectly unaffected.
+    if percent > 0 && rng.rand_u32() % 100 < percent {
+      1 + (rng.rand_u32() % max_rank) as usize
+    } else {
+      0
+    }
+  }
+}
+
+impl FromStr for Difficulty {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "easy" => Ok(Self::Easy),
+      "medium" => Ok(Self::Medium),
+      "hard" => Ok(Self::Hard),
+      "perfect" => Ok(Self::Perfect),
+      _ => bail!("unsupported AI difficulty `{s}`"),
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that playing perfectly never involves mistakes or delays.
+  #[test]
+  fn perfect_play() {
+    let rng = Rng::with_seed(1);
+    let difficulty = Difficulty::Perfect;
+    assert_eq!(difficulty.action_interval(), Duration::ZERO);
+    assert_eq!(difficulty.reaction_time(), Duration::ZERO);
+    assert!((0..1000).all(|_| difficulty.pick_rank(&rng) == 0));
+  }
+
+  /// Check that lower difficulties pick suboptimal placements every
+  /// now and then, within the bounds of their presets.
+  #[test]
+  fn mistake_ranks() {
+    let rng = Rng::with_seed(2);
+    let ranks = (0..1000)
+      .map(|_| Difficulty::Easy.pick_rank(&rng))
+      .collect::<Vec<_>>();
+    assert!(ranks.contains(&0));
+    assert!(ranks.iter().any(|rank| *rank > 0));
+    assert!(ranks.iter().all(|rank| *rank <= 6));
+  }
+
+  /// Check that difficulties round trip through their textual
+  /// representation.
+  #[test]
+  fn string_round_trip() {
+    for difficulty in [
+      Difficulty::Easy,
+      Difficulty::Medium,
+      Difficulty::Hard,
+      Difficulty::Perfect,
+    ] {
+      assert_eq!(
+        Difficulty::from_str(difficulty.as_str()).unwrap(),
+        difficulty
+      );
+    }
+    assert!(Difficulty::from_str("impossible").is_err());
+  }
+}
@@ -0,0 +1,2075 @@
+// Copyright (C) 2023-2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cmp::max;
+use std::cmp::min;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use anyhow::Result;
+use anyhow::ensure;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Change;
+use crate::Frame;
+use crate::Point;
+use crate::Rng;
+use crate::Tick;
+use crate::clock::duration_to_frames;
+
+use super::Clear;
+use super::Config;
+use super::Curve;
+use super::Difficulty;
+use super::DropKind;
+use super::Field;
+use super::Mode;
+use super::MoveResult;
+use super::PreviewStones;
+use super::Randomizer;
+use super::Score;
+use super::Scoring;
+use super::Session;
+use super::Speed;
+use super::Stone;
+use super::StoneConfig;
+use super::StoneFactory;
+use super::StoneSet;
+use super::Stonelike as _;
+use super::ai;
+use super::field::Snapshot as FieldSnapshot;
+use super::field::State as FieldState;
+use super::mode::Snapshot as SessionSnapshot;
+use super::preview::Snapshot as PreviewSnapshot;
+use super::score::Snapshot as ScoreSnapshot;
+use super::template::load_templates;
+use super::write_time;
+
+
+/// The time for which we highlight any completed lines while not
+/// responding to any input.
+// TODO: Make configurable.
+const CLEAR_TIME: Duration = Duration::from_millis(200);
+
+
+/// The outcome of a finished game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outcome {
+  /// The mode that was played.
+  pub mode: Mode,
+  /// The width of the field.
+  pub field_width: i16,
+  /// The height of the field.
+  pub field_height: i16,
+  /// The level the game started at.
+  pub start_level: u16,
+  /// The rules used for awarding points.
+  pub scoring: Scoring,
+  /// The level reached.
+  pub level: u16,
+  /// The number of points earned.
+  pub points: u64,
+  /// The total number of lines cleared.
+  pub lines: u32,
+  /// The time played.
+  pub time: Duration,
+  /// Whether the goal of the mode was reached.
+  pub completed: bool,
+  /// The number of lines to clear in [`Mode::Sprint`].
+  pub sprint_lines: u16,
+  /// The time to score points in, in [`Mode::Ultra`].
+  pub ultra_time: Duration,
+  /// The number of lines of garbage the field started out with in
+  /// [`Mode::Dig`].
+  pub dig_rows: u16,
+  /// The number of lines of garbage left in [`Mode::Dig`].
+  pub dig_left: u16,
+  /// Whether the AI played, or assisted by showing hints, at any point
+  /// during the game.
+  pub ai: bool,
+}
+
+impl Display for Outcome {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    let time = || {
+      let mut buffer = Vec::new();
+      let () = write_time(&mut buffer, self.time).unwrap();
+      String::from_utf8_lossy(&buffer).into_owned()
+    };
+
+    let end = if self.completed {
+      "completed"
+    } else {
+      "game over"
+    };
+    write!(
+      f,
+      "{} points @ level {}; total {} lines cleared ({end})",
+      self.points, self.level, self.lines
+    )?;
+
+    match self.mode {
+      Mode::Marathon => Ok(()),
+      Mode::Sprint if self.completed => {
+        write!(f, "\nsprint: {} lines in {}", self.sprint_lines, time())
+      },
+      Mode::Sprint => write!(
+        f,
+        "\nsprint: failed after {} of {} lines",
+        self.lines, self.sprint_lines
+      ),
+      Mode::Ultra if self.completed => write!(f, "\nultra: {} points", self.points),
+      Mode::Ultra => write!(f, "\nultra: failed with {} points", self.points),
+      Mode::Dig if self.completed => {
+        write!(f, "\ndig: {} lines of garbage in {}", self.dig_rows, time())
+      },
+      Mode::Dig => write!(
+        f,
+        "\ndig: failed with {} lines of garbage left",
+        self.dig_left
+      ),
+    }
+  }
+}
+
+
+/// Statistics about the searches the AI performed for finding the
+/// best placement of stones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+  /// The number of searches performed, one per stone.
+  pub searches: u64,
+  /// The total time spent searching.
+  pub time: Duration,
+}
+
+impl SearchStats {
+  /// Calculate the average time spent searching per stone.
+  pub fn average(&self) -> Duration {
+    u32::try_from(self.searches)
+      .ok()
+      .and_then(|searches| self.time.checked_div(searches))
+      .unwrap_or_default()
+  }
+}
+
+
+/// The settings the AI searches with.
+#[derive(Clone, Debug)]
+struct AiSettings {
+  /// The maximum number of preview stones taken into account.
+  lookahead: u8,
+  /// The time the search for the placement of a stone may take, if
+  /// limited.
+  time_budget: Option<Duration>,
+  /// How well the AI plays.
+  difficulty: Difficulty,
+  /// The path to the external bot playing in place of the builtin AI,
+  /// if any.
+  bot_path: Option<PathBuf>,
+  /// The external bot, once launched.
+  bot: Option<Arc<ai::Bot>>,
+  /// The time the bot may take to respond to a request for the
+  /// placement of a stone.
+  bot_timeout: Duration,
+  /// The weights used for evaluating fields.
+  weights: ai::Weights,
+  /// The worker calculating plans, shared by all AI instances (as well
+  /// as hints) over the lifetime of the engine.
+  worker: ai::Worker,
+}
+
+impl AiSettings {
+  /// Retrieve the means by which the AI plans its actions.
+  fn planner(&self) -> ai::Planner {
+    match &self.bot {
+      Some(bot) => ai::Planner::Bot(Arc::clone(bot)),
+      None => ai::Planner::Search(self.time_budget),
+    }
+  }
+
+  /// Launch the external bot, if one is configured and it is not
+  /// running already.
+  fn launch_bot(&mut self, field: &Field) -> Result<()> {
+    if let Some(path) = &self.bot_path
+      && self.bot.is_none()
+    {
+      let bot = ai::Bot::launch(
+        path,
+        field.width(),
+        field.height(),
+        field.rotation_system(),
+        self.bot_timeout,
+      )?;
+      self.bot = Some(Arc::new(bot));
+    }
+    Ok(())
+  }
+}
+
+
+/// The state the [`Engine`] is in.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum State {
+  Running {
+    /// The frame of the next tick, i.e., the next downward movement.
+    next_tick: Frame,
+    /// The distance, in units, the stone will have fallen by the next
+    /// tick. Fractions are carried over to subsequent ticks.
+    #[serde(default)]
+    fall: f64,
+  },
+  Paused,
+  Over,
+}
+
+
+/// A snapshot of the complete state of an [`Engine`].
+///
+/// A snapshot can be serialized and later be used for resuming the
+/// game exactly where it was left off, via [`Engine::from_snapshot`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Snapshot {
+  /// The most recently simulated frame.
+  frame: Frame,
+  /// The actions the AI has yet to perform, if it is playing the
+  /// game.
+  ai: Option<Vec<ai::Action>>,
+  /// Whether the AI played at any point during the game.
+  ai_played: bool,
+  /// The weights the AI uses for evaluating its options.
+  #[serde(default)]
+  ai_weights: ai::Weights,
+  /// The state of the random number generator deciding on the AI's
+  /// mistakes.
+  #[serde(default)]
+  ai_rng: Option<Rng>,
+  /// The frame at which the AI performs its next action, if it reacted
+  /// to the active stone already.
+  #[serde(default)]
+  ai_next_action: Option<Frame>,
+  /// The engine's state.
+  state: State,
+  /// The configuration the game is played with.
+  config: Config,
+  /// The state of the Tetris field.
+  field: FieldSnapshot,
+  /// The state of the preview stones.
+  preview: PreviewSnapshot,
+  /// The current score.
+  score: ScoreSnapshot,
+  /// The progress towards the goal of the game mode being played.
+  session: SessionSnapshot,
+}
+
+impl Snapshot {
+  /// Retrieve the most recently simulated frame of the game.
+  #[inline]
+  pub fn frame(&self) -> Frame {
+    self.frame
+  }
+
+  /// Check whether the game is over.
+  #[inline]
+  pub fn is_over(&self) -> bool {
+    matches!(self.state, State::Over)
+  }
+}
+
+
+/// The placement the AI suggests for the active stone.
+#[derive(Debug)]
+struct Hint {
+  /// The field the suggestion was calculated for.
+  field: ai::Field,
+  /// The template of the stone the suggestion was calculated for.
+  template: Box<[Point<i8>]>,
+  /// The AI searching for the suggested placement, while the search is
+  /// still in progress.
+  search: Option<ai::AI>,
+  /// The locations of the pieces making up the suggested placement,
+  /// if any.
+  pieces: Option<Box<[Point<i16>]>>,
+}
+
+
+/// The rules engine of a game of Tetris.
+///
+/// The engine contains the entire game logic, but is agnostic to how
+/// (or whether) the game is rendered. As such, it can be used without
+/// any graphics context being present.
+#[derive(Debug)]
+pub struct Engine {
+  /// The game's state.
+  state: State,
+  /// The most recently simulated frame.
+  frame: Frame,
+  /// The Tetris field.
+  field: Field,
+  /// The preview stones.
+  preview: Rc<PreviewStones>,
+  /// The strategy used for selecting upcoming stones.
+  randomizer: Randomizer,
+  /// The set of stones being played with.
+  stone_set: StoneSet,
+  /// The custom stone definitions.
+  stones: Vec<StoneConfig>,
+  /// The speed at which stones fall.
+  speed: Speed,
+  /// Whether or not the player is able to put stones on hold.
+  enable_hold: bool,
+  /// The current score.
+  score: Score,
+  /// The progress towards the goal of the game mode being played.
+  session: Session,
+  /// The AI playing the game, if any.
+  ai: Option<ai::AI>,
+  /// Whether the AI played at any point during the current game.
+  ai_played: bool,
+  /// The settings the AI searches with.
+  ai_settings: AiSettings,
+  /// The random number generator deciding on the AI's mistakes.
+  ai_rng: Rng,
+  /// The frame at which the AI performs its next action, if it reacted
+  /// to the active stone already.
+  ai_next_action: Option<Frame>,
+  /// Whether the AI searches in the background, with the simulation
+  /// waiting for it without blocking.
+  async_ai: bool,
+  /// Whether to show the placement the AI suggests for the active
+  /// stone.
+  show_hint: bool,
+  /// The most recently calculated hint, if any.
+  hint: Option<Hint>,
+  /// Statistics about the searches the AI performed.
+  search_stats: SearchStats,
+}
+
+impl Engine {
+  /// The maximum width of a field the AI is able to play on.
+  pub const MAX_AI_FIELD_WIDTH: i16 = ai::Field::MAX_WIDTH;
+
+  /// Instantiate a new game engine with the given configuration.
+  ///
+  /// All randomness in the game is derived from `seed`. If none is
+  /// provided, a seed based on the current time is used.
+  pub fn with_config(config: &Config, seed: Option<u64>) -> Result<Self> {
+    let rng = seed.map(Rng::with_seed).unwrap_or_else(Rng::new);
+    let templates = load_templates(config).context("failed to load stone templates")?;
+    let factory = StoneFactory::new(templates, config.randomizer, rng.fork());
+
+    let preview = PreviewStones::new(config.preview_stone_count, factory);
+    let preview = Rc::new(preview);
+
+    if config.enable_ai {
+      ensure!(
+        config.field_width <= Self::MAX_AI_FIELD_WIDTH,
+        "field width ({}) exceeds maximum supported by the AI ({})",
+        config.field_width,
+        Self::MAX_AI_FIELD_WIDTH
+      );
+    }
+
+    if config.mode == Mode::Dig {
+      ensure!(
+        config.dig_rows > 0,
+        "number of garbage lines must be greater than zero"
+      );
+      ensure!(
+        config.dig_rows < u16::try_from(config.field_height).unwrap_or_default(),
+        "number of garbage lines ({}) must be less than field height ({})",
+        config.dig_rows,
+        config.field_height
+      );
+    }
+
+    let now = 0;
+    let mut field = Field::new(config, now, CLEAR_TIME, Rc::clone(&preview) as _);
+
+    let speed = Speed::new(
+      config.gravity,
+      config.gravity_table.clone(),
+      Curve {
+        base: config.gravity_base,
+        growth: config.gravity_growth,
+        exponent: config.gravity_exponent,
+      },
+    )?;
+
+    let mut session = Session::new(config, rng.fork());
+    ensure!(
+      Self::insert_initial_garbage(&mut field, &session),
+      "initial garbage ({} lines) leaves no room for stones",
+      session.initial_garbage()
+    );
+
+    let mut score = Score::new(config.start_level, config.lines_for_level, config.scoring);
+    let () = field.set_instant_gravity(speed.is_instant(score.level()));
+
+    let state = match field.state() {
+      FieldState::Moving { .. } | FieldState::Landed { .. } | FieldState::Clearing { .. } => {
+        let () = session.start(now);
+        let (next_tick, fall) = speed.next_tick(now, 0.0, score.level());
+        State::Running { next_tick, fall }
+      },
+      FieldState::Colliding { .. } => State::Over,
+    };
+    let _change = score.set_time(session.displayed_time(now));
+
+    let time_budget = (config.ai_time_budget_ms != 0)
+      .then(|| Duration::from_millis(config.ai_time_budget_ms.into()));
+    let mut ai_settings = AiSettings {
+      lookahead: config.ai_lookahead,
+      time_budget,
+      difficulty: config.ai_difficulty,
+      bot_path: config.ai_bot.clone(),
+      bot: None,
+      bot_timeout: Duration::from_millis(config.ai_bot_timeout_ms.into()),
+      weights: ai::Weights::default(),
+      worker: ai::Worker::new(),
+    };
+    let ai_rng = rng.fork();
+    let ai = if config.enable_ai {
+      let () = ai_settings.launch_bot(&field)?;
+      Self::create_ai(&field, &preview, &ai_settings, &ai_rng)
+    } else {
+      None
+    };
+
+    let slf = Self {
+      state,
+      frame: now,
+      field,
+      preview,
+      randomizer: config.randomizer,
+      stone_set: config.stone_set,
+      stones: config.stones.clone(),
+      speed,
+      enable_hold: config.enable_hold,
+      ai_played: ai.is_some(),
+      ai,
+      ai_settings,
+      ai_rng,
+      ai_next_action: None,
+      async_ai: false,
+      score,
+      session,
+      show_hint: false,
+      hint: None,
+      search_stats: SearchStats::default(),
+    };
+    Ok(slf)
+  }
+
+  /// Restore an engine from a snapshot of its state, continuing the
+  /// game exactly where it was left off.
+  pub fn from_snapshot(snapshot: &Snapshot) -> Result<Self> {
+    let Snapshot {
+      frame,
+      ai,
+      ai_played,
+      ai_weights,
+      ai_rng,
+      ai_next_action,
+      state,
+      config,
+      field,
+      preview,
+      score,
+      session,
+    } = snapshot;
+
+    // The seed is irrelevant, as all state depending on it is
+    // restored from the snapshot.
+    let mut slf = Self::with_config(config, Some(0))?;
+    let () = slf.field.set_frame(*frame);
+    let () = slf
+      .field
+      .restore(field)
+      .context("failed to restore field")?;
+    let () = slf
+      .preview
+      .restore(preview)
+      .context("failed to restore preview stones")?;
+    let _change = slf.score.restore(score);
+    let () = slf.session.restore(session);
+    let _change = slf.score.set_time(slf.session.displayed_time(*frame));
+
+    slf.frame = *frame;
+    slf.state = state.clone();
+    // The AI picks up exactly where it left off, as re-planning from
+    // the stone's current position may lead it to different decisions.
+    slf.ai = ai.clone().map(|actions| {
+      ai::AI::with_actions(&slf.ai_settings.worker, actions, slf.ai_settings.planner())
+    });
+    slf.ai_played = *ai_played;
+    slf.ai_settings.weights = *ai_weights;
+    if let Some(ai_rng) = ai_rng {
+      let () = slf.ai_rng.assume(ai_rng);
+    }
+    slf.ai_next_action = *ai_next_action;
+    Ok(slf)
+  }
+
+  /// Create a snapshot of the engine's complete state.
+  pub fn snapshot(&self) -> Snapshot {
+    Snapshot {
+      frame: self.frame,
+      ai: self.ai.as_ref().map(ai::AI::remaining),
+      ai_played: self.ai_played,
+      ai_weights: self.ai_settings.weights,
+      ai_rng: Some(self.ai_rng.clone()),
+      ai_next_action: self.ai_next_action,
+      state: self.state.clone(),
+      config: self.to_config(),
+      field: self.field.snapshot(),
+      preview: self.preview.snapshot(),
+      score: self.score.snapshot(),
+      session: self.session.snapshot(),
+    }
+  }
+
+  fn with_ai_data<F, R>(
+    field: &Field,
+    preview: &PreviewStones,
+    settings: &AiSettings,
+    f: F,
+  ) -> Option<R>
+  where
+    F: FnOnce(&ai::Field, &ai::Stone, &[ai::Stone], Option<&ai::Stone>) -> R,
+  {
+    let hold = field.held_stone().map(Stone::to_ai_stone);
+
+    if let Some((field, stone)) = field.to_ai_data() {
+      // TODO: Ideally we would not have to allocate here.
+      let stones = preview.with_stones(move |stones| {
+        stones
+          .take(usize::from(settings.lookahead))
+          .map(Stone::to_ai_stone)
+          .collect::<Vec<_>>()
+      });
+
+      let result = f(&field, &stone, &stones, hold.as_ref());
+      Some(result)
+    } else {
+      None
+    }
+  }
+
+  fn advance_ai(
+    ai: &mut ai::AI,
+    field: &Field,
+    preview: &PreviewStones,
+    settings: &AiSettings,
+    rng: &Rng,
+  ) {
+    let _result = Self::with_ai_data(
+      field,
+      preview,
+      settings,
+      |field, stone, next_stones, hold| {
+        let rank = settings.difficulty.pick_rank(rng);
+        ai.advance_stone(field, &settings.weights, stone, next_stones, hold, rank)
+      },
+    );
+  }
+
+  fn replan_ai(ai: &mut ai::AI, field: &Field, preview: &PreviewStones, settings: &AiSettings) {
+    let _result = Self::with_ai_data(
+      field,
+      preview,
+      settings,
+      |field, stone, _next_stones, _hold| ai.replan(field, &settings.weights, stone),
+    );
+  }
+
+  fn create_ai(
+    field: &Field,
+    preview: &PreviewStones,
+    settings: &AiSettings,
+    rng: &Rng,
+  ) -> Option<ai::AI> {
+    Self::with_ai_data(
+      field,
+      preview,
+      settings,
+      |field, stone, next_stones, hold| {
+        let rank = settings.difficulty.pick_rank(rng);
+        ai::AI::new(
+          &settings.worker,
+          settings.planner(),
+          field,
+          &settings.weights,
+          stone,
+          next_stones,
+          hold,
+          rank,
+        )
+      },
+    )
+  }
+
+  /// Wait for the AI, if any, to have planned its actions for the
+  /// active stone, accounting for the time it spent searching.
+  ///
+  /// # Returns
+  /// This function returns `false` if the AI searches in the
+  /// background and is not yet done.
+  fn await_ai(&mut self) -> bool {
+    if let Some(ai) = self.ai.as_mut() {
+      if self.async_ai && !ai.is_ready() {
+        return false
+      }
+
+      if let Some(time) = ai.wait() {
+        self.search_stats.searches += 1;
+        self.search_stats.time += time;
+      }
+    }
+    true
+  }
+
+  /// Perform the AI's actions other than moving the stone down, at
+  /// the pace its difficulty permits.
+  fn ai_handle_regular_move(
+    ai: &mut Option<ai::AI>,
+    field: &mut Field,
+    next_action: &mut Option<Frame>,
+    settings: &AiSettings,
+    now: Frame,
+  ) -> Change {
+    let mut change = Change::Unchanged;
+
+    if let Some(ai) = ai.as_mut() {
+      // The AI only starts acting on a new stone once it had time to
+      // react to it.
+      let reaction = duration_to_frames(settings.difficulty.reaction_time());
+      let next_action = next_action.get_or_insert(now + reaction);
+      let interval = duration_to_frames(settings.difficulty.action_interval());
+
+      // Peeking waits for a plan still being calculated, so we only do
+      // so once the AI is due to act.
+      while now >= *next_action
+        && let Some(action) = ai.peek()
+      {
+        change |= match action {
+          ai::Action::MoveLeft => field.move_stone_left(),
+          ai::Action::MoveRight => field.move_stone_right(),
+          ai::Action::RotateLeft => field.rotate_stone_left(),
+          ai::Action::RotateRight => field.rotate_stone_right(),
+          ai::Action::Merge | ai::Action::MoveDown => return change,
+        };
+
+        let _ = ai.next();
+        *next_action = now + interval;
+      }
+    }
+
+    change
+  }
+
+  /// Check whether the AI, if any, still has actions other than moving
+  /// the active stone down pending.
+  fn ai_is_acting(ai: &Option<ai::AI>) -> bool {
+    ai.as_ref().is_some_and(|ai| {
+      !matches!(
+        ai.peek(),
+        Some(ai::Action::Merge | ai::Action::MoveDown) | None
+      )
+    })
+  }
+
+  /// Account for the active stone having moved down.
+  ///
+  /// # Returns
+  /// This function returns `false` if the AI did not plan for the
+  /// stone to move down just yet.
+  fn ai_remove_down_move(ai: &mut Option<ai::AI>) -> bool {
+    if let Some(ai) = ai.as_mut() {
+      match ai.peek() {
+        Some(ai::Action::MoveDown) => {
+          let _ = ai.next();
+        },
+        // With only the merge left, the stone is meant to be dropped
+        // where it is, no matter whether the AI moves it down itself
+        // or leaves that to gravity.
+        Some(ai::Action::Merge) | None => (),
+        Some(..) => return false,
+      }
+    }
+    true
+  }
+
+  fn ai_remove_stone_merge(
+    ai: &mut Option<ai::AI>,
+    field: &Field,
+    preview: &PreviewStones,
+    settings: &AiSettings,
+    rng: &Rng,
+    next_action: &mut Option<Frame>,
+  ) {
+    if let Some(current) = ai.as_mut() {
+      if let Some(ai::Action::Merge) = current.peek() {
+        let _merge = current.next();
+        debug_assert_eq!(current.next(), None);
+
+        let () = Self::advance_ai(current, field, preview, settings, rng);
+      } else {
+        // The stone got merged before the AI was done with it, e.g.,
+        // because it did not act fast enough. It just continues with
+        // the next one.
+        *ai = Self::create_ai(field, preview, settings, rng);
+      }
+      *next_action = None;
+    }
+  }
+
+  /// Fill the field with the garbage the game mode starts out with.
+  ///
+  /// # Returns
+  /// This function returns `false` if the garbage could not be placed
+  /// without conflicts.
+  fn insert_initial_garbage(field: &mut Field, session: &Session) -> bool {
+    for _ in 0..session.initial_garbage() {
+      let hole = session.garbage_hole(field.width());
+      let (_change, result) = field.insert_garbage(hole);
+      if let MoveResult::Conflict = result {
+        return false
+      }
+    }
+    true
+  }
+
+  /// Advance the game simulation up to the given frame.
+  ///
+  /// The simulation is stepped one frame at a time, which makes its
+  /// outcome independent of how often this method is invoked.
+  ///
+  /// Besides an indication of whether anything changed, the frame at
+  /// which the game should be ticked next is returned.
+  ///
+  /// If the AI searches in the background (see
+  /// [`Engine::set_async_ai`]), the simulation is held back until it
+  /// is done and catches up on subsequent invocations.
+  pub fn tick(&mut self, frame: Frame) -> (Change, Tick<Frame>) {
+    let mut change = Change::Unchanged;
+
+    while self.frame < frame {
+      if !self.await_ai() {
+        return (change, Tick::At(frame + 1))
+      }
+
+      self.frame += 1;
+      change |= self.step(self.frame);
+    }
+
+    change |= self.update_hint();
+    (change, self.next_tick())
+  }
+
+  /// Simulate a single frame.
+  fn step(&mut self, now: Frame) -> Change {
+    let () = self.field.set_frame(now);
+
+    let mut change = self.step_impl(now);
+
+    if let State::Running { .. } = self.state {
+      if let FieldState::Moving { .. } | FieldState::Landed { .. } = self.field.state() {
+        let width = self.field.width();
+        if let Some(hole) = self.session.next_garbage(now, width) {
+          let (garbage_change, result) = self.field.insert_garbage(hole);
+          change |= garbage_change;
+
+          if let MoveResult::Conflict = result {
+            let () = self.session.stop(now);
+            let () = self.set_game_over();
+            return change
+          }
+
+          if let Some(ai) = self.ai.as_mut() {
+            // The field changed under the AI's feet, so it has to
+            // start over.
+            let () = Self::replan_ai(ai, &self.field, &self.preview, &self.ai_settings);
+          }
+        }
+      }
+
+      change |= self.score.set_time(self.session.displayed_time(now));
+
+      let garbage_lines = self.field.garbage_lines();
+      if self.session.check_goal(&self.score, garbage_lines, now) {
+        let () = self.session.stop(now);
+        let () = self.set_game_over();
+        change = Change::Changed;
+      }
+    }
+    change
+  }
+
+  fn step_impl(&mut self, now: Frame) -> Change {
+    let (next_tick, fall) = match &mut self.state {
+      State::Running { next_tick, fall } => (next_tick, fall),
+      State::Paused | State::Over => return Change::Unchanged,
+    };
+
+    let mut change = Change::Unchanged;
+    // The level may have changed since the last frame.
+    let instant = self.speed.is_instant(self.score.level());
+    let () = self.field.set_instant_gravity(instant);
+
+    // Under instant gravity stones touch down right away, before an AI
+    // acting at a human pace had a chance to move them. Hence, they
+    // are only locked once it is done with them.
+    let hold_lock = instant && Self::ai_is_acting(&self.ai);
+
+    if let FieldState::Landed { lock_at, .. } = self.field.state() {
+      if now >= *lock_at && !hold_lock {
+        let (lock_change, result) = self.field.lock_stone();
+        change |= lock_change;
+
+        match result {
+          MoveResult::None | MoveResult::Moved | MoveResult::Landed => (),
+          MoveResult::Merged(clear) => {
+            change |= Self::handle_merged_lines(&mut self.score, clear);
+            let () = Self::ai_remove_stone_merge(
+              &mut self.ai,
+              &self.field,
+              &self.preview,
+              &self.ai_settings,
+              &self.ai_rng,
+              &mut self.ai_next_action,
+            );
+          },
+          MoveResult::Conflict => {
+            let () = self.set_game_over();
+            return change
+          },
+        }
+      }
+    }
+
+    let clearing_until = if let FieldState::Clearing { until, .. } = self.field.state() {
+      Some(*until)
+    } else {
+      None
+    };
+    change |= self.field.tick(now);
+
+    match self.field.state() {
+      FieldState::Moving { .. } | FieldState::Landed { .. } => {
+        if let Some(until) = clearing_until {
+          (*next_tick, *fall) = self.speed.next_tick(until, 0.0, self.score.level());
+        }
+      },
+      FieldState::Clearing { .. } => return change,
+      FieldState::Colliding { .. } => {
+        let () = self.set_game_over();
+        return change
+      },
+    }
+
+    change |= Self::ai_handle_regular_move(
+      &mut self.ai,
+      &mut self.field,
+      &mut self.ai_next_action,
+      &self.ai_settings,
+      now,
+    );
+
+    let level = self.score.level();
+    let instant = self.speed.is_instant(level);
+    if now >= *next_tick || instant {
+      // Under instant gravity the stone does not travel row by row,
+      // but is moved to its landing position right away. Otherwise it
+      // may still fall by multiple rows in a single frame.
+      let units = if instant { 1 } else { fall.floor() as u16 };
+
+      let mut replan = false;
+      for _ in 0..units {
+        let mut result = if instant {
+          let mut change = Change::Unchanged;
+          for _ in 0..self.field.drop_distance() {
+            change |= self.field.move_stone_down().0;
+            // Just as when falling row by row, the AI has to plan anew
+            // if it did not expect the stone to sink.
+            replan |= !Self::ai_remove_down_move(&mut self.ai);
+          }
+
+          // An AI acting at a human pace may not have had a chance to
+          // move the stone yet, in which case it does not touch down
+          // before the AI is done with it.
+          if Self::ai_is_acting(&self.ai) {
+            (change, MoveResult::None)
+          } else {
+            let (down_change, result) = self.field.move_stone_down();
+            (change | down_change, result)
+          }
+        } else {
+          self.field.move_stone_down()
+        };
+        if let (MoveResult::Landed, Some(..)) = (&result.1, &self.ai) {
+          // The AI does not make use of the lock delay and expects
+          // stones to be merged as soon as they touched down.
+          let locked = self.field.lock_stone();
+          result = (result.0 | locked.0, locked.1);
+        }
+        change |= result.0;
+
+        match result.1 {
+          MoveResult::None | MoveResult::Landed => break,
+          MoveResult::Moved => {
+            // If the AI was not yet done with its other actions, e.g.,
+            // because it acts slowly, it has to plan anew from the
+            // stone's new location.
+            replan |= !Self::ai_remove_down_move(&mut self.ai);
+          },
+          MoveResult::Merged(clear) => {
+            change |= Self::handle_merged_lines(&mut self.score, clear);
+            let _expected = Self::ai_remove_down_move(&mut self.ai);
+            replan = false;
+            let () = Self::ai_remove_stone_merge(
+              &mut self.ai,
+              &self.field,
+              &self.preview,
+              &self.ai_settings,
+              &self.ai_rng,
+              &mut self.ai_next_action,
+            );
+            break
+          },
+          MoveResult::Conflict => {
+            let () = self.set_game_over();
+            return change
+          },
+        }
+      }
+
+      if let (true, Some(ai)) = (replan, self.ai.as_mut()) {
+        let () = Self::replan_ai(ai, &self.field, &self.preview, &self.ai_settings);
+      }
+
+      (*next_tick, *fall) = self.speed.next_tick(now, fall.fract(), level);
+    }
+    change
+  }
+
+  /// Determine the frame at which the game next needs to be ticked.
+  fn next_tick(&self) -> Tick<Frame> {
+    let State::Running { next_tick, .. } = &self.state else {
+      return Tick::None
+    };
+
+    let now = self.frame;
+    let level = self.score.level();
+    let game_tick = if self.speed.is_instant(level) {
+      now + 1
+    } else {
+      *next_tick
+    };
+    let field_tick = match self.field.state() {
+      FieldState::Landed { lock_at, .. } => Tick::At(*lock_at),
+      FieldState::Clearing { .. } => Tick::At(now + 1),
+      FieldState::Moving { .. } | FieldState::Colliding { .. } => Tick::None,
+    };
+    let session_tick = Tick::from(self.session.next_update(now));
+    let ai_tick = match (&self.ai, self.field.state()) {
+      (Some(ai), FieldState::Moving { .. } | FieldState::Landed { .. }) => {
+        if !ai.is_ready() {
+          Tick::At(now + 1)
+        } else if let Some(ai::Action::Merge | ai::Action::MoveDown) | None = ai.peek() {
+          Tick::None
+        } else {
+          Tick::At(max(self.ai_next_action.unwrap_or_default(), now + 1))
+        }
+      },
+      _ => Tick::None,
+    };
+    let hint_tick = match &self.hint {
+      Some(Hint {
+        search: Some(..), ..
+      }) => Tick::At(now + 1),
+      _ => Tick::None,
+    };
+    min(
+      min(min(Tick::At(game_tick), field_tick), session_tick),
+      min(ai_tick, hint_tick),
+    )
+  }
+
+  /// Restart the game.
+  pub fn restart(&mut self) -> Change {
+    let mut change = self.score.reset();
+    let () = self.session.reset();
+
+    let now = self.frame;
+    let () = if self.field.reset() && Self::insert_initial_garbage(&mut self.field, &self.session) {
+      let () = self
+        .field
+        .set_instant_gravity(self.speed.is_instant(self.score.level()));
+      if self.ai.is_some() {
+        self.ai = Self::create_ai(&self.field, &self.preview, &self.ai_settings, &self.ai_rng);
+        self.ai_next_action = None;
+      }
+      self.ai_played = self.ai.is_some() || self.show_hint;
+      let () = self.session.start(now);
+      let (next_tick, fall) = self.speed.next_tick(now, 0.0, self.score.level());
+      self.state = State::Running { next_tick, fall };
+    } else {
+      self.set_game_over()
+    };
+
+    change |= self.score.set_time(self.session.displayed_time(now));
+    change
+  }
+
+  /// End the current game, setting `state` to [`State::Over`].
+  fn set_game_over(&mut self) {
+    debug_assert!(!matches!(self.state, State::Over));
+    self.state = State::Over;
+    let () = self.session.stop(self.frame);
+  }
+
+  /// Pause or unpause the game.
+  #[inline]
+  pub fn pause(&mut self, pause: bool) {
+    match &self.state {
+      State::Running { .. } if pause => {
+        // Note that strictly speaking the field could change state here
+        // (if it was "clearing") and, conceptually, we should cause a
+        // redraw (i.e., by returning `Change::Changed`. Practically,
+        // though, we do *not* want to do that, because doing so could
+        // eagerly remove cleared lines and it just makes more sense to
+        // leave them there for the duration of the pause.
+        let () = self.field.on_pause();
+        let () = self.session.stop(self.frame);
+        self.state = State::Paused;
+      },
+      State::Paused if !pause => {
+        let () = self.field.on_resume();
+        let () = self.session.start(self.frame);
+        let (next_tick, fall) = self.speed.next_tick(self.frame, 0.0, self.score.level());
+        self.state = State::Running { next_tick, fall }
+      },
+      State::Over | State::Paused | State::Running { .. } => (),
+    }
+  }
+
+  /// Retrieve the most recently simulated frame.
+  ///
+  /// All time based state transitions performed in response to user
+  /// input are relative to this frame.
+  #[inline]
+  pub fn frame(&self) -> Frame {
+    self.frame
+  }
+
+  /// Inquire whether the game is currently paused.
+  ///
+  /// Note that this method truly only returns `true` when the game is
+  /// paused, and not just when it isn't running (e.g., because it is
+  /// over).
+  #[inline]
+  pub fn is_paused(&self) -> bool {
+    matches!(&self.state, State::Paused)
+  }
+
+  /// Enable or disable auto-playing of the game.
+  ///
+  /// The external bot, if configured, is launched once auto-playing is
+  /// first enabled.
+  pub fn auto_play(&mut self, auto_play: bool) -> Result<()> {
+    if auto_play {
+      if self.ai.is_none() {
+        let () = self.ai_settings.launch_bot(&self.field)?;
+        self.ai = Self::create_ai(&self.field, &self.preview, &self.ai_settings, &self.ai_rng);
+        self.ai_next_action = None;
+        self.ai_played |= self.ai.is_some();
+      }
+    } else {
+      self.ai = None;
+      let _change = self.update_hint();
+    }
+    Ok(())
+  }
+
+  /// Check whether the game is currently controlled by an auto-playing
+  /// AI.
+  #[inline]
+  pub fn is_auto_playing(&self) -> bool {
+    self.ai.is_some()
+  }
+
+  /// Set the weights the AI uses for evaluating its options.
+  ///
+  /// The weights take effect once the AI considers the next stone.
+  #[inline]
+  pub fn set_ai_weights(&mut self, weights: ai::Weights) {
+    self.ai_settings.weights = weights
+  }
+
+  /// Retrieve the weights the AI uses for evaluating its options.
+  #[inline]
+  pub fn ai_weights(&self) -> &ai::Weights {
+    &self.ai_settings.weights
+  }
+
+  /// Enable or disable showing of the placement the AI suggests for
+  /// the active stone, while the player stays in control.
+  ///
+  /// A game in which hints were shown counts as assisted by the AI.
+  pub fn show_hint(&mut self, show: bool) {
+    self.show_hint = show;
+    if show {
+      self.ai_played |= !self.is_over();
+      let _change = self.update_hint();
+    } else {
+      self.hint = None;
+    }
+  }
+
+  /// Check whether the placement the AI suggests for the active stone
+  /// is being shown.
+  #[inline]
+  pub fn is_showing_hint(&self) -> bool {
+    self.show_hint
+  }
+
+  /// Retrieve the locations of the pieces making up the placement the
+  /// AI suggests for the active stone, if hints are to be shown.
+  pub(super) fn hint(&self) -> Option<&[Point<i16>]> {
+    if !self.show_hint || self.ai.is_some() || self.is_over() {
+      return None
+    }
+
+    match self.field.state() {
+      FieldState::Moving { .. } | FieldState::Landed { .. } => (),
+      FieldState::Clearing { .. } | FieldState::Colliding { .. } => return None,
+    }
+    self.hint.as_ref()?.pieces.as_deref()
+  }
+
+  /// Update the placement the AI suggests for the active stone, if
+  /// hints are to be shown.
+  ///
+  /// The suggestion is only calculated anew once the active stone or
+  /// the field changed. Just like the AI's actions, it is searched for
+  /// in the background if so configured (see
+  /// [`Engine::set_async_ai`]), in which case it only becomes
+  /// available on a subsequent invocation.
+  fn update_hint(&mut self) -> Change {
+    if !self.show_hint || self.ai.is_some() || self.is_over() {
+      return Change::Unchanged
+    }
+
+    let template = match self.field.state() {
+      FieldState::Moving { stone } | FieldState::Landed { stone, .. } => stone.template(),
+      FieldState::Clearing { .. } | FieldState::Colliding { .. } => return Change::Unchanged,
+    };
+
+    let hint = &mut self.hint;
+    let settings = &self.ai_settings;
+    let _result = Self::with_ai_data(
+      &self.field,
+      &self.preview,
+      settings,
+      |field, stone, next_stones, hold| {
+        let stale = hint
+          .as_ref()
+          .is_none_or(|hint| hint.field != *field || *hint.template != *template);
+        if stale {
+          // A hint is always searched for by the builtin AI, even if a
+          // bot plays in its place.
+          let search = ai::AI::new(
+            &settings.worker,
+            ai::Planner::Search(settings.time_budget),
+            field,
+            &settings.weights,
+            stone,
+            next_stones,
+            hold,
+            0,
+          );
+          *hint = Some(Hint {
+            field: field.clone(),
+            template: template.into(),
+            search: Some(search),
+            pieces: None,
+          });
+        }
+      },
+    );
+
+    if let Some(hint) = &mut self.hint
+      && let Some(search) = &hint.search
+      && (!self.async_ai || search.is_ready())
+    {
+      hint.pieces = search
+        .placement()
+        .map(|placement| placement.pieces().collect());
+      hint.search = None;
+      Change::Changed
+    } else {
+      Change::Unchanged
+    }
+  }
+
+  fn handle_merged_lines(score: &mut Score, clear: Clear) -> Change {
+    score.add(clear)
+  }
+
+  /// Check whether the game in its current state accepts and reacts to
+  /// input.
+  ///
+  /// It won't accept input if it's currently paused or if the AI is
+  /// playing.
+  #[inline]
+  fn accepts_input(&self) -> bool {
+    matches!(self.state, State::Running { .. }) && !self.is_auto_playing()
+  }
+
+  /// Move the active stone down by one row, as a soft drop.
+  #[inline]
+  pub fn on_move_down(&mut self) -> Change {
+    if self.accepts_input() {
+      let (mut change, result) = self.field.move_stone_down();
+      match result {
+        MoveResult::None | MoveResult::Landed => (),
+        MoveResult::Moved => {
+          change |= self.score.add_drop(DropKind::Soft, 1);
+        },
+        MoveResult::Merged(clear) => {
+          change |= Self::handle_merged_lines(&mut self.score, clear);
+        },
+        MoveResult::Conflict => {
+          let () = self.set_game_over();
+        },
+      }
+
+      change
+    } else {
+      Change::Unchanged
+    }
+  }
+
+  /// Drop the active stone, as a hard drop.
+  #[inline]
+  pub fn on_drop(&mut self) -> Change {
+    if self.accepts_input() {
+      let units = self.field.drop_distance();
+      let (mut change, result) = self.field.drop_stone();
+      if units > 0 {
+        change |= self.score.add_drop(DropKind::Hard, units as u16);
+      }
+
+      match result {
+        MoveResult::None | MoveResult::Moved | MoveResult::Landed => (),
+        MoveResult::Merged(clear) => {
+          change |= Self::handle_merged_lines(&mut self.score, clear);
+        },
+        MoveResult::Conflict => {
+          let () = self.set_game_over();
+        },
+      }
+
+      change
+    } else {
+      Change::Unchanged
+    }
+  }
+
+  /// Move the active stone one column to the left.
+  #[inline]
+  pub fn on_move_left(&mut self) -> Change {
+    if self.accepts_input() {
+      self.field.move_stone_left()
+    } else {
+      Change::Unchanged
+    }
+  }
+
+  /// Move the active stone one column to the right.
+  #[inline]
+  pub fn on_move_right(&mut self) -> Change {
+    if self.accepts_input() {
+      self.field.move_stone_right()
+    } else {
+      Change::Unchanged
+    }
+  }
+
+  /// Rotate the active stone counter-clockwise.
+  #[inline]
+  pub fn on_rotate_left(&mut self) -> Change {
+    if self.accepts_input() {
+      self.field.rotate_stone_left()
+    } else {
+      Change::Unchanged
+    }
+  }
+
+  /// Rotate the active stone clockwise.
+  #[inline]
+  pub fn on_rotate_right(&mut self) -> Change {
+    if self.accepts_input() {
+      self.field.rotate_stone_right()
+    } else {
+      Change::Unchanged
+    }
+  }
+
+  /// Put the active stone on hold, if enabled.
+  #[inline]
+  pub fn on_hold(&mut self) -> Change {
+    if self.enable_hold && self.accepts_input() {
+      let change = self.field.hold_stone();
+      if let FieldState::Colliding { .. } = self.field.state() {
+        let () = self.set_game_over();
+      }
+      change
+    } else {
+      Change::Unchanged
+    }
+  }
+
+  /// Inquire whether the game is over.
+  #[inline]
+  pub fn is_over(&self) -> bool {
+    matches!(&self.state, State::Over)
+  }
+
+  /// Retrieve the outcome of the game, if it is over.
+  pub fn outcome(&self) -> Option<Outcome> {
+    if !self.is_over() {
+      return None
+    }
+
+    let outcome = Outcome {
+      mode: self.session.mode(),
+      field_width: self.field.width(),
+      field_height: self.field.height(),
+      start_level: self.score.start_level(),
+      scoring: self.score.scoring(),
+      level: self.score.level(),
+      points: self.score.points(),
+      lines: self.score.lines(),
+      time: self.session.played_time(self.frame),
+      completed: self.session.is_completed(),
+      sprint_lines: self.session.sprint_lines(),
+      ultra_time: self.session.ultra_time(),
+      dig_rows: self.session.dig_rows(),
+      dig_left: self.session.dig_left(),
+      ai: self.ai_played,
+    };
+    Some(outcome)
+  }
+
+  /// Retrieve the number of points earned so far.
+  #[inline]
+  pub fn points(&self) -> u64 {
+    self.score.points()
+  }
+
+  /// Retrieve the current level.
+  #[inline]
+  pub fn level(&self) -> u16 {
+    self.score.level()
+  }
+
+  /// Retrieve the total number of lines cleared.
+  #[inline]
+  pub fn lines(&self) -> u32 {
+    self.score.lines()
+  }
+
+  /// Retrieve the number of stones placed.
+  #[inline]
+  pub fn stones(&self) -> u64 {
+    self.score.stones()
+  }
+
+  /// Retrieve the height of the stack of locked pieces and the number
+  /// of holes in it.
+  #[inline]
+  pub fn stack(&self) -> (i16, u16) {
+    self.field.stack()
+  }
+
+  /// Retrieve statistics about the searches the AI performed.
+  #[inline]
+  pub fn search_stats(&self) -> SearchStats {
+    self.search_stats
+  }
+
+  /// Enable or disable searching by the AI in the background.
+  ///
+  /// When enabled, [`Engine::tick`] does not block while the AI is
+  /// searching, but rather holds back the simulation until the AI is
+  /// done. Where background work is unsupported, the AI always
+  /// searches synchronously.
+  #[inline]
+  pub fn set_async_ai(&mut self, async_ai: bool) {
+    self.async_ai = async_ai
+  }
+
+  /// Retrieve the Tetris field.
+  #[inline]
+  pub(super) fn field(&self) -> &Field {
+    &self.field
+  }
+
+  /// Retrieve the preview stones.
+  #[inline]
+  pub(super) fn preview(&self) -> &PreviewStones {
+    &self.preview
+  }
+
+  /// Retrieve the current score.
+  #[inline]
+  pub(super) fn score(&self) -> &Score {
+    &self.score
+  }
+
+  /// Retrieve the progress towards the goal of the game mode.
+  #[inline]
+  pub(super) fn session(&self) -> &Session {
+    &self.session
+  }
+
+  /// Check whether the player is able to put stones on hold.
+  #[inline]
+  pub(super) fn hold_enabled(&self) -> bool {
+    self.enable_hold
+  }
+
+  /// Convert the engine (back) into a [`Config`].
+  ///
+  /// Settings not concerning the game logic, such as
+  /// [`Config::enable_dark_mode`], are reported with their defaults.
+  #[inline]
+  pub fn into_config(self) -> Config {
+    self.to_config()
+  }
+
+  /// Retrieve the [`Config`] the engine is running with.
+  fn to_config(&self) -> Config {
+    Config {
+      start_level: self.score.start_level(),
+      lines_for_level: self.score.lines_for_level(),
+      field_width: self.field.width(),
+      field_height: self.field.height(),
+      preview_stone_count: self.preview.with_stones(|stones| stones.count()) as _,
+      lock_delay_ms: self.field.lock_delay_ms(),
+      lock_reset_limit: self.field.lock_reset_limit(),
+      scoring: self.score.scoring(),
+      randomizer: self.randomizer,
+      stone_set: self.stone_set,
+      stones: self.stones.clone(),
+      gravity: self.speed.gravity(),
+      gravity_table: self.speed.table().to_vec(),
+      gravity_base: self.speed.curve().base,
+      gravity_growth: self.speed.curve().growth,
+      gravity_exponent: self.speed.curve().exponent,
+      rotation_system: self.field.rotation_system(),
+      mode: self.session.mode(),
+      sprint_lines: self.session.sprint_lines(),
+      ultra_time_secs: self.session.ultra_time().as_secs() as u32,
+      dig_rows: self.session.dig_rows(),
+      dig_interval_ms: self.session.dig_interval().as_millis() as u32,
+      enable_ai: self.ai.is_some(),
+      ai_lookahead: self.ai_settings.lookahead,
+      ai_time_budget_ms: self
+        .ai_settings
+        .time_budget
+        .map(|budget| budget.as_millis() as u32)
+        .unwrap_or_default(),
+      ai_difficulty: self.ai_settings.difficulty,
+      ai_bot: self.ai_settings.bot_path.clone(),
+      ai_bot_timeout_ms: self.ai_settings.bot_timeout.as_millis() as u32,
+      enable_hold: self.enable_hold,
+      enable_ghost: self.field.show_ghost(),
+      ..Default::default()
+    }
+  }
+
+  #[cfg(feature = "debug")]
+  pub(crate) fn dump_state(&self) {
+    if let Some((field, stone)) = self.field.to_ai_data() {
+      if let Some(ai) = self.ai.as_ref() {
+        println!("{ai:#?}");
+      }
+      println!("{stone:?}");
+      println!("{field:?}");
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::fs::read_to_string;
+
+  use tempfile::TempDir;
+
+  use toml_edit::de::from_str as from_toml_str;
+  use toml_edit::ser::to_string as to_toml_string;
+
+  use super::super::Gravity;
+  use super::super::ai::util::write_bot;
+
+
+  /// Check that we fail to start a game if the initial garbage does
+  /// not leave room for stones.
+  #[test]
+  fn initial_garbage_conflict() {
+    let config = Config {
+      mode: Mode::Dig,
+      dig_rows: 19,
+      ..Default::default()
+    };
+    let err = Engine::with_config(&config, Some(42)).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "initial garbage (19 lines) leaves no room for stones"
+    );
+
+    // Without any garbage, there would be nothing to dig for.
+    let config = Config {
+      dig_rows: 0,
+      ..config
+    };
+    let err = Engine::with_config(&config, Some(42)).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "number of garbage lines must be greater than zero"
+    );
+
+    let config = Config {
+      dig_rows: 10,
+      ..config
+    };
+    let _engine = Engine::with_config(&config, Some(42)).unwrap();
+  }
+
+  /// Check that we fail to start a game with the AI enabled on a field
+  /// too wide for it.
+  #[test]
+  fn ai_field_too_wide() {
+    let config = Config {
+      field_width: Engine::MAX_AI_FIELD_WIDTH + 1,
+      enable_ai: true,
+      ..Default::default()
+    };
+    let err = Engine::with_config(&config, Some(42)).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "field width (33) exceeds maximum supported by the AI (32)"
+    );
+
+    let config = Config {
+      enable_ai: false,
+      ..config
+    };
+    let _engine = Engine::with_config(&config, Some(42)).unwrap();
+  }
+
+  /// Check that under instant gravity new stones appear at their
+  /// landing position right away.
+  #[test]
+  fn instant_gravity_spawn() {
+    let config = Config {
+      gravity: Gravity::Table,
+      gravity_table: vec![1200.0],
+      lock_delay_ms: 500,
+      ..Default::default()
+    };
+    let mut engine = Engine::with_config(&config, Some(42)).unwrap();
+    assert_eq!(engine.field.drop_distance(), 0);
+
+    for _ in 0..3 {
+      let _change = engine.on_drop();
+      assert!(!engine.is_over());
+      assert!(matches!(engine.field.state(), FieldState::Moving { .. }));
+      assert_eq!(engine.field.drop_distance(), 0);
+    }
+  }
+
+  /// Check that the AI can play a game without any graphics context
+  /// being present.
+  #[test]
+  fn headless_auto_play() {
+    let config = Config {
+      enable_ai: true,
+      ..Default::default()
+    };
+    let mut engine = Engine::with_config(&config, Some(42)).unwrap();
+    assert!(engine.is_auto_playing());
+
+    let (_change, tick) = engine.tick(120 * 60);
+    assert_eq!(engine.frame(), 120 * 60);
+    assert!(engine.lines() > 0);
+    assert!(engine.points() > 0);
+    assert!(!engine.is_over());
+    assert_eq!(engine.outcome(), None);
+    assert!(matches!(tick, Tick::At(frame) if frame > engine.frame()));
+  }
+
+  /// Check that searching in the background does not change how the
+  /// AI plays.
+  #[test]
+  fn async_auto_play() {
+    let config = Config {
+      enable_ai: true,
+      ..Default::default()
+    };
+    let mut engine1 = Engine::with_config(&config, Some(1337)).unwrap();
+    let mut engine2 = Engine::with_config(&config, Some(1337)).unwrap();
+    let () = engine2.set_async_ai(true);
+
+    let frame = 60 * 60;
+    let _result = engine1.tick(frame);
+    while engine2.frame() < frame {
+      let _result = engine2.tick(frame);
+    }
+
+    assert_eq!(engine1.lines(), engine2.lines());
+    assert_eq!(engine1.points(), engine2.points());
+    assert_eq!(engine1.stones(), engine2.stones());
+    assert_eq!(
+      format!("{:?}", engine1.field().to_ai_data()),
+      format!("{:?}", engine2.field().to_ai_data())
+    );
+  }
+
+  /// Check that the AI playing at a lower difficulty performs worse,
+  /// but equally deterministically.
+  #[test]
+  fn difficulty_auto_play() {
+    fn play(difficulty: Difficulty, step: Frame) -> Engine {
+      let config = Config {
+        enable_ai: true,
+        ai_difficulty: difficulty,
+        start_level: 8,
+        ..Default::default()
+      };
+      let mut engine = Engine::with_config(&config, Some(42)).unwrap();
+      let mut frame = 0;
+      while frame < 90 * 60 && !engine.is_over() {
+        frame += step;
+        let _result = engine.tick(frame);
+      }
+      engine
+    }
+
+    let perfect = play(Difficulty::Perfect, 60);
+    let easy1 = play(Difficulty::Easy, 60);
+    let easy2 = play(Difficulty::Easy, 1);
+    assert!(easy1.lines() < perfect.lines());
+    assert_eq!(easy1.lines(), easy2.lines());
+    assert_eq!(easy1.points(), easy2.points());
+    assert_eq!(easy1.stones(), easy2.stones());
+    assert_eq!(
+      format!("{:?}", easy1.field().to_ai_data()),
+      format!("{:?}", easy2.field().to_ai_data())
+    );
+  }
+
+  /// Check that an AI acting at a human pace still gets to move stones
+  /// under instant gravity.
+  #[test]
+  fn instant_gravity_difficulty_auto_play() {
+    let config = Config {
+      enable_ai: true,
+      ai_difficulty: Difficulty::Medium,
+      gravity: Gravity::Table,
+      gravity_table: vec![1200.0],
+      ..Default::default()
+    };
+    let mut engine = Engine::with_config(&config, Some(42)).unwrap();
+    let _result = engine.tick(30 * 60);
+    // Had stones been locked in the column they appeared in, not a
+    // single line would have been cleared.
+    assert!(engine.lines() > 0);
+  }
+
+  /// Check that an external bot can play in place of the builtin AI.
+  #[test]
+  fn bot_auto_play() {
+    let dir = TempDir::new().unwrap();
+    let path = write_bot(
+      dir.path(),
+      r#"echo '{"type":"suggestion","actions":["move_left","merge"]}'"#,
+    );
+    let config = Config {
+      enable_ai: true,
+      ai_bot: Some(path.clone()),
+      ai_bot_timeout_ms: 500,
+      ..Default::default()
+    };
+    let mut engine = Engine::with_config(&config, Some(42)).unwrap();
+    let mut frame = 0;
+    while frame < 600 * 60 && !engine.is_over() {
+      frame += 60;
+      let _result = engine.tick(frame);
+    }
+
+    // The bot stacks all stones at the left side, until the game is
+    // over.
+    assert!(engine.is_over());
+    assert!(engine.stones() > 1);
+    assert_eq!(engine.lines(), 0);
+    let config = engine.into_config();
+    assert_eq!(config.ai_bot, Some(path));
+    assert_eq!(config.ai_bot_timeout_ms, 500);
+  }
+
+  /// Check that the external bot is launched only once auto-playing
+  /// starts.
+  #[test]
+  fn bot_lazy_launch() {
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+      enable_ai: false,
+      ai_bot: Some(dir.path().join("does-not-exist")),
+      ..Default::default()
+    };
+    let mut engine = Engine::with_config(&config, Some(42)).unwrap();
+    assert!(engine.auto_play(true).is_err());
+    assert!(!engine.is_auto_playing());
+
+    let config = Config {
+      enable_ai: true,
+      ..config
+    };
+    assert!(Engine::with_config(&config, Some(42)).is_err());
+  }
+
+  /// Check that actions a bot sends after merging the stone are
+  /// ignored.
+  #[test]
+  fn bot_actions_after_merge() {
+    let dir = TempDir::new().unwrap();
+    let path = write_bot(
+      dir.path(),
+      r#"echo '{"type":"suggestion","actions":["move_right","merge","move_left","merge"]}'"#,
+    );
+    let config = Config {
+      enable_ai: true,
+      ai_bot: Some(path),
+      ..Default::default()
+    };
+    let mut engine = Engine::with_config(&config, Some(42)).unwrap();
+    let _result = engine.tick(120 * 60);
+    assert!(engine.stones() > 1);
+  }
+
+  /// Check that a bot is asked for actions only once for each stone,
+  /// even if it acts slowly and leaves moving the stone down to
+  /// gravity.
+  #[test]
+  fn bot_requests_per_stone() {
+    let dir = TempDir::new().unwrap();
+    let requests = dir.path().join("requests");
+    let respond = format!(
+      r#"echo >> '{}'; echo '{{"type":"suggestion","actions":["move_left","move_left","merge"]}}'"#,
+      requests.display()
+    );
+    let config = Config {
+      enable_ai: true,
+      ai_bot: Some(write_bot(dir.path(), &respond)),
+      ai_difficulty: Difficulty::Easy,
+      ..Default::default()
+    };
+    let mut engine = Engine::with_config(&config, Some(42)).unwrap();
+    let mut frame = 0;
+    while frame < 600 * 60 && !engine.is_over() {
+      frame += 60;
+      let _result = engine.tick(frame);
+    }
+    assert!(engine.is_over());
+
+    // The stone whose merge ended the game is not counted as placed.
+    let requests = read_to_string(&requests).unwrap().lines().count();
+    assert_eq!(requests as u64, engine.stones() + 1);
+  }
+
+  /// Check that the outcome of a finished game is reported correctly.
+  #[test]
+  fn game_over_outcome() {
+    let mut engine = Engine::with_config(&Config::default(), Some(42)).unwrap();
+    let mut frame = 0;
+
+    while !engine.is_over() {
+      frame += 1;
+      let _result = engine.tick(frame);
+      let _change = engine.on_drop();
+    }
+
+    let outcome = engine.outcome().unwrap();
+    assert_eq!(outcome.mode, Mode::Marathon);
+    assert_eq!(outcome.points, engine.points());
+    assert_eq!(outcome.lines, engine.lines());
+    assert!(!outcome.ai);
+
+    let summary = format!(
+      "{} points @ level {}; total {} lines cleared (game over)",
+      outcome.points, outcome.level, outcome.lines
+    );
+    assert_eq!(outcome.to_string(), summary);
+
+    let outcome = Outcome {
+      mode: Mode::Sprint,
+      ..outcome
+    };
+    assert_eq!(
+      outcome.to_string(),
+      format!(
+        "{summary}\nsprint: failed after {} of {} lines",
+        outcome.lines, outcome.sprint_lines
+      )
+    );
+
+    let outcome = Outcome {
+      completed: true,
+      ..outcome
+    };
+    let summary = format!(
+      "{} points @ level {}; total {} lines cleared (completed)\nsprint: {} lines in ",
+      outcome.points, outcome.level, outcome.lines, outcome.sprint_lines
+    );
+    assert!(outcome.to_string().starts_with(&summary));
+
+    // Having the AI take over at any point marks the game as played
+    // by it.
+    let _change = engine.restart();
+    let () = engine.auto_play(true).unwrap();
+    let () = engine.auto_play(false).unwrap();
+    while !engine.is_over() {
+      frame += 1;
+      let _result = engine.tick(frame);
+      let _change = engine.on_drop();
+    }
+    assert!(engine.outcome().unwrap().ai);
+
+    // The same is true for games in which hints were shown.
+    let _change = engine.restart();
+    let () = engine.show_hint(true);
+    let () = engine.show_hint(false);
+    while !engine.is_over() {
+      frame += 1;
+      let _result = engine.tick(frame);
+      let _change = engine.on_drop();
+    }
+    assert!(engine.outcome().unwrap().ai);
+  }
+
+  /// Make sure that games with the same seed and inputs play out the
+  /// same way, regardless of how often they are ticked.
+  #[test]
+  fn deterministic_play() {
+    fn play(step: Frame) -> Engine {
+      let mut engine = Engine::with_config(&Config::default(), Some(1337)).unwrap();
+      let mut frame = 0;
+
+      while frame < 30 * 60 {
+        frame += step;
+        let _result = engine.tick(frame);
+
+        if frame % 90 == 0 {
+          let _change = engine.on_move_left();
+          let _change = engine.on_drop();
+        }
+      }
+      engine
+    }
+
+    let engine1 = play(1);
+    let engine2 = play(5);
+    assert_eq!(engine1.frame(), engine2.frame());
+    assert_eq!(engine1.points(), engine2.points());
+    assert_eq!(engine1.lines(), engine2.lines());
+    assert_eq!(
+      format!("{:?}", engine1.field().to_ai_data()),
+      format!("{:?}", engine2.field().to_ai_data())
+    );
+  }
+
+  /// Check that a paused game does not advance.
+  #[test]
+  fn pause_resume() {
+    let mut engine = Engine::with_config(&Config::default(), Some(7)).unwrap();
+    let _result = engine.tick(10);
+    let () = engine.pause(true);
+    assert!(engine.is_paused());
+
+    let before = format!("{:?}", engine.field().to_ai_data());
+    let (change, tick) = engine.tick(10 * 60);
+    assert_eq!(change, Change::Unchanged);
+    assert_eq!(tick, Tick::None);
+    assert_eq!(format!("{:?}", engine.field().to_ai_data()), before);
+
+    let () = engine.pause(false);
+    assert!(!engine.is_paused());
+    let (_change, tick) = engine.tick(10 * 60);
+    assert_ne!(tick, Tick::None);
+  }
+
+  /// Check that pausing the game does not grant a landed stone a fresh
+  /// lock delay.
+  #[test]
+  fn pause_lock_delay() {
+    let config = Config {
+      gravity: Gravity::Table,
+      gravity_table: vec![1200.0],
+      lock_delay_ms: 500,
+      ..Default::default()
+    };
+    let mut engine = Engine::with_config(&config, Some(42)).unwrap();
+    let _change = engine.on_move_down();
+    assert!(matches!(engine.field.state(), FieldState::Landed { .. }));
+    let stones = engine.stones();
+
+    let _result = engine.tick(20);
+    let () = engine.pause(true);
+    let _result = engine.tick(20 + 10 * 60);
+    let () = engine.pause(false);
+
+    let _result = engine.tick(20 + 10 * 60 + 5);
+    assert!(matches!(engine.field.state(), FieldState::Landed { .. }));
+    assert_eq!(engine.stones(), stones);
+
+    let _result = engine.tick(20 + 10 * 60 + 15);
+    assert_ne!(engine.stones(), stones);
+  }
+
+  /// Check that garbage pushing up a landed stone does not reset its
+  /// lock delay.
+  #[test]
+  fn garbage_lock_delay() {
+    let config = Config {
+      gravity: Gravity::Table,
+      gravity_table: vec![1200.0],
+      lock_delay_ms: 500,
+      ..Default::default()
+    };
+    let mut engine = Engine::with_config(&config, Some(42)).unwrap();
+    let _change = engine.on_move_down();
+    let lock_at = match engine.field.state() {
+      FieldState::Landed { lock_at, .. } => *lock_at,
+      state => panic!("unexpected field state: {state:?}"),
+    };
+
+    let _result = engine.tick(10);
+    let (change, result) = engine.field.insert_garbage(0);
+    assert_eq!(change, Change::Changed);
+    assert!(matches!(result, MoveResult::None));
+    assert!(
+      matches!(engine.field.state(), FieldState::Landed { lock_at: at, .. } if *at == lock_at)
+    );
+  }
+
+  /// Check that the placement suggested by the AI is reported as
+  /// expected.
+  #[test]
+  fn hint_placement() {
+    let mut engine = Engine::with_config(&Config::default(), Some(3)).unwrap();
+    assert!(engine.hint().is_none());
+
+    let () = engine.show_hint(true);
+    assert!(engine.is_showing_hint());
+    let hint = engine.hint().unwrap().to_vec();
+    assert_eq!(hint.len(), 4);
+
+    // The suggested placement is where the AI ends up merging the
+    // stone.
+    let () = engine.auto_play(true).unwrap();
+    assert!(engine.hint().is_none());
+    let before = engine.field().to_ai_data().unwrap().0;
+    let mut frame = 0;
+    let field = loop {
+      frame += 1;
+      let _result = engine.tick(frame);
+      let field = engine.field().to_ai_data().unwrap().0;
+      if field != before {
+        break field
+      }
+    };
+    assert!(hint.iter().all(|location| field[*location].is_some()));
+    let () = engine.auto_play(false).unwrap();
+
+    // Moving the stone does not change the suggestion.
+    let hint = engine.hint().unwrap().to_vec();
+    let _change = engine.on_move_left();
+    assert_eq!(engine.hint().unwrap(), hint.as_slice());
+
+    let () = engine.show_hint(false);
+    assert!(engine.hint().is_none());
+  }
+
+  /// Check that a hint searched for in the background becomes
+  /// available on a later tick.
+  #[test]
+  fn async_hint_placement() {
+    let mut engine1 = Engine::with_config(&Config::default(), Some(3)).unwrap();
+    let mut engine2 = Engine::with_config(&Config::default(), Some(3)).unwrap();
+    let () = engine2.set_async_ai(true);
+
+    let () = engine1.show_hint(true);
+    let () = engine2.show_hint(true);
+
+    let hint = loop {
+      let (change, tick) = engine2.tick(0);
+      if let Some(hint) = engine2.hint() {
+        assert_eq!(change, Change::Changed);
+        break hint.to_vec()
+      }
+      assert_eq!(tick, Tick::At(1));
+    };
+    assert_eq!(engine1.hint().unwrap(), hint.as_slice());
+  }
+
+  /// Check that a game restored from a snapshot continues exactly like
+  /// the original one.
+  #[test]
+  fn snapshot_restore() {
+    let config = Config {
+      enable_ai: true,
+      ..Default::default()
+    };
+    let mut engine = Engine::with_config(&config, Some(99)).unwrap();
+    let _result = engine.tick(60 * 60);
+
+    let snapshot = to_toml_string(&engine.snapshot()).unwrap();
+    let snapshot = from_toml_str::<Snapshot>(&snapshot).unwrap();
+    assert_eq!(snapshot.frame(), engine.frame());
+    assert!(!snapshot.is_over());
+
+    let mut restored = Engine::from_snapshot(&snapshot).unwrap();
+    assert_eq!(restored.frame(), engine.frame());
+    assert_eq!(restored.points(), engine.points());
+    assert!(restored.is_auto_playing());
+
+    for engine in [&mut engine, &mut restored] {
+      let _result = engine.tick(120 * 60);
+      let () = engine.auto_play(false).unwrap();
+      let _change = engine.on_move_left();
+      let _change = engine.on_drop();
+      let _result = engine.tick(125 * 60);
+    }
+
+    assert_eq!(
+      to_toml_string(&restored.snapshot()).unwrap(),
+      to_toml_string(&engine.snapshot()).unwrap()
+    );
+  }
+}
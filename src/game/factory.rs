@@ -4,64 +4,74 @@
 use std::cmp::max;
 use std::cmp::min;
 use std::ops::Deref;
-use std::rc::Rc;
 
-use crate::Point;
+use anyhow::Result;
+
 use crate::Rng;
-use crate::Texture;
 
-use super::Piece;
+#[cfg(test)]
+use super::Config;
+use super::Randomizer;
+use super::Selector;
 use super::Stone;
 use super::StoneProducer;
-
-
-type StoneTemplate = Box<[Point<i8>]>;
+use super::randomizer::Snapshot as SelectorSnapshot;
+use super::template::StoneTemplate;
+#[cfg(test)]
+use super::template::load_templates;
 
 
 #[derive(Debug)]
 pub(super) struct StoneFactory {
-  /// The texture to use for each piece.
-  piece_texture: Rc<Texture>,
-  /// The known stone types.
-  templates: Box<[StoneTemplate]>,
-  /// The random number generator we use when creating new stones.
-  rng: Rng,
+  /// The known stone types, along with the index of their color.
+  templates: Box<[(StoneTemplate, u8)]>,
+  /// The selector we use for picking the template of the next stone.
+  selector: Selector,
 }
 
 impl StoneFactory {
-  pub fn with_default_stones(piece_texture: Rc<Texture>) -> Self {
-    #[rustfmt::skip]
-    let templates = Box::new([
-      vec![Point::new(0, 0), Point::new(1, 0), Point::new(1, 1), Point::new(0, 1)].into_boxed_slice(), // O
-      vec![Point::new(0, 0), Point::new(1, 0), Point::new(1, 1), Point::new(2, 1)].into_boxed_slice(), // S
-      vec![Point::new(0, 1), Point::new(1, 1), Point::new(1, 0), Point::new(2, 0)].into_boxed_slice(), // Z
-      vec![Point::new(0, 0), Point::new(0, 1), Point::new(0, 2), Point::new(0, 3)].into_boxed_slice(), // I
-      vec![Point::new(0, 0), Point::new(1, 0), Point::new(1, 1), Point::new(2, 0)].into_boxed_slice(), // T
-      vec![Point::new(0, 0), Point::new(1, 0), Point::new(0, 1), Point::new(0, 2)].into_boxed_slice(), // J
-      vec![Point::new(0, 0), Point::new(1, 0), Point::new(1, 1), Point::new(1, 2)].into_boxed_slice(), // L
-    ]);
-
+  pub fn new(templates: Box<[(StoneTemplate, u8)]>, randomizer: Randomizer, rng: Rng) -> Self {
     Self {
+      selector: Selector::new(randomizer, templates.len(), rng),
       templates,
-      piece_texture,
-      rng: Rng::new(),
     }
   }
+
+  /// Restore the state of the factory from a snapshot.
+  #[inline]
+  pub fn restore(&self, snapshot: &SelectorSnapshot) -> Result<()> {
+    self.selector.restore(snapshot)
+  }
+
+  /// Create a snapshot of the state of the factory.
+  #[inline]
+  pub fn snapshot(&self) -> SelectorSnapshot {
+    self.selector.snapshot()
+  }
+
+  #[cfg(test)]
+  pub fn with_default_stones(randomizer: Randomizer) -> Self {
+    // SANITY: The default configuration is known to be valid.
+    let templates = load_templates(&Config::default()).unwrap();
+    Self::new(templates, randomizer, Rng::new())
+  }
 }
 
 impl StoneProducer for StoneFactory {
   /// Create a new random stone, based on one of the known templates.
   fn create_stone(&self) -> Stone {
-    let index = self.rng.rand_u32() as usize % self.templates.len();
-    let template = &self.templates[index];
-    let color_idx = index % Piece::COLORS.len();
+    let index = self.selector.next();
+    let (template, color_idx) = &self.templates[index];
 
-    Stone::new(Rc::clone(&self.piece_texture), template, color_idx as u8)
+    Stone::new(template, *color_idx)
   }
 
   // TODO: Loose copy of logic from `Stone`. Should think about deduplicating.
   fn max_dimensions(&self) -> (i16, i16) {
-    let mut locations = self.templates.deref().iter().flat_map(Deref::deref);
+    let mut locations = self
+      .templates
+      .iter()
+      .flat_map(|(template, _color_idx)| template.deref());
     // SANITY: Our stone always has at least one template.
     let location = locations.next().unwrap();
     let mut x_min = location.x;
@@ -69,7 +79,7 @@ impl StoneProducer for StoneFactory {
     let mut y_min = location.y;
     let mut y_max = location.y;
 
-    for location in locations.skip(1) {
+    for location in locations {
       x_min = min(x_min, location.x);
       x_max = max(x_max, location.x);
       y_min = min(y_min, location.y);
@@ -87,24 +97,15 @@ impl StoneProducer for StoneFactory {
 mod tests {
   use super::*;
 
-  use test_fork::fork;
-
-  use crate::gl::empty_texture;
-  use crate::winit::with_opengl_context;
-
 
   /// Check that we report the correct upper bound dimensions for the
   /// default set of stones.
-  #[fork]
   #[test]
   fn stone_dimensions() {
-    with_opengl_context(|context| {
-      let texture = Rc::new(empty_texture(context).unwrap());
-      let factory = StoneFactory::with_default_stones(texture);
-
-      let (w, h) = factory.max_dimensions();
-      assert_eq!(w, 3);
-      assert_eq!(h, 4);
-    })
+    let factory = StoneFactory::with_default_stones(Randomizer::default());
+
+    let (w, h) = factory.max_dimensions();
+    assert_eq!(w, 3);
+    assert_eq!(h, 4);
   }
 }
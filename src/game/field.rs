@@ -8,27 +8,42 @@ use std::ops::Range;
 use std::rc::Rc;
 use std::time::Duration;
 
+use anyhow::Context as _;
+use anyhow::Result;
+use anyhow::ensure;
+
+use serde::Deserialize;
+use serde::Serialize;
+
 use crate::ActiveRenderer as Renderer;
 use crate::Change;
 use crate::Color;
-use crate::Instant;
+use crate::Frame;
 use crate::Point;
 use crate::Rect;
-use crate::Texture;
-use crate::Tick;
+use crate::clock::duration_to_frames;
 use crate::mode::ColorMode;
 use crate::mode::ColorSet;
 use crate::util::smoothstep;
 
+use super::Clear;
+use super::Config;
 use super::Fieldlike;
 use super::Matrix;
 use super::Piece;
+use super::RotationSystem;
+use super::Spin;
 use super::Stone;
 use super::StoneProducer;
 use super::Stonelike as _;
+use super::Textures;
 use super::ai;
+use super::stone::Snapshot as StoneSnapshot;
 
 
+/// The character representing an empty cell in a [`Snapshot`].
+const EMPTY_CELL: char = '.';
+
 /// The width of each wall.
 const WALL_WIDTH: i16 = 1;
 
@@ -44,6 +59,28 @@ const BACKGROUND_COLOR: ColorSet = ColorSet::new(
 
 const WALL_COLOR: ColorSet = ColorSet::new(Color::orange(), Color::gray());
 
+/// The overlay used for rendering the "ghost" of the active stone,
+/// making it appear faded into the background.
+const GHOST_OVERLAY: ColorSet = ColorSet::new(
+  Color {
+    r: 0xb0,
+    g: 0xb0,
+    b: 0xb0,
+    a: 0,
+  },
+  Color {
+    r: 0x80,
+    g: 0x80,
+    b: 0x80,
+    a: 0,
+  },
+);
+
+
+/// The color set used for the outline of the placement suggested for
+/// the active stone.
+const HINT_COLOR: ColorSet = ColorSet::new(Color::red(), Color::orange());
+
 
 /// The result of a stone downward movement.
 #[derive(Debug)]
@@ -52,9 +89,12 @@ pub(super) enum MoveResult {
   None,
   /// The stone was moved down successfully and without a collision.
   Moved,
+  /// The stone touched down on the stack or bottom of the field and
+  /// will be merged once the lock delay expired.
+  Landed,
   /// The stone got merged into the field. Reported are the number of
-  /// lines cleared.
-  Merged(u16),
+  /// lines cleared along with the spin the stone was merged with.
+  Merged(Clear),
   /// A conflict has occurred, i.e., a stone got merged, but the
   /// replacement stone immediately collided with previously merged
   /// pieces in the field.
@@ -69,12 +109,20 @@ pub(super) enum State {
     /// The currently active stone.
     stone: Stone,
   },
+  /// The active stone touched down and is waiting to be locked in
+  /// place.
+  Landed {
+    /// The currently active stone.
+    stone: Stone,
+    /// The frame at which the stone will get merged.
+    lock_at: Frame,
+  },
   /// Completed lines are currently being cleared.
   Clearing {
     /// The next stone to be controlled by the user.
     next_stone: Stone,
-    /// The instant at which we are done clearing completed lines.
-    until: Instant,
+    /// The frame at which we are done clearing completed lines.
+    until: Frame,
     /// The overlay color to use.
     overlay: Color,
     /// The y-range containing completed lines.
@@ -89,30 +137,104 @@ pub(super) enum State {
 }
 
 
+/// The serializable representation of a field's [`State`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum StateSnapshot {
+  Moving {
+    stone: StoneSnapshot,
+  },
+  Landed {
+    stone: StoneSnapshot,
+    lock_at: Frame,
+  },
+  Clearing {
+    next_stone: StoneSnapshot,
+    until: Frame,
+    y_range: Range<i16>,
+  },
+  Colliding {
+    stone: StoneSnapshot,
+  },
+}
+
+
+/// The serializable state of a [`Field`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(super) struct Snapshot {
+  /// The merged pieces, one string per line from top to bottom. Each
+  /// piece is represented by the index of its color, while empty
+  /// cells are represented by a dot.
+  pieces: Vec<String>,
+  /// Whether the hold functionality was already used for the active
+  /// stone.
+  hold_used: bool,
+  /// The number of lock delay resets performed for the active stone.
+  lock_resets: u8,
+  /// Whether the last action performed on the active stone was a
+  /// rotation.
+  last_rotated: bool,
+  /// The field's state.
+  state: StateSnapshot,
+  /// The stone currently put on hold, if any.
+  #[serde(default)]
+  held: Option<StoneSnapshot>,
+  /// The lock delay, in frames, that remained for the landed stone
+  /// when the game got paused.
+  #[serde(default)]
+  paused_lock: Option<Frame>,
+}
+
+
 #[derive(Debug)]
 pub(super) struct Field {
-  /// The time we take for clearing completed lines.
-  clear_time: Duration,
+  /// The most recently simulated frame, which serves as the reference
+  /// for all time based state transitions.
+  now: Frame,
+  /// The number of frames we take for clearing completed lines.
+  clear_time: Frame,
   /// The inner field area, containing dropped pieces.
   pieces: PieceField,
   /// The field's current state.
   state: State,
+  /// The stone currently put on hold, if any.
+  held: Option<Stone>,
+  /// Whether the hold functionality was already used for the currently
+  /// active stone. It can only be used once per stone.
+  hold_used: bool,
+  /// The time a stone may rest on the stack before getting merged.
+  lock_delay: Duration,
+  /// The maximum number of times the lock delay gets reset by moving
+  /// or rotating a stone that touched down.
+  lock_reset_limit: u8,
+  /// The number of lock delay resets performed for the active stone.
+  lock_resets: u8,
+  /// Whether the last successful action performed on the active stone
+  /// was a rotation.
+  last_rotated: bool,
+  /// The lock delay, in frames, that remained for the landed stone
+  /// when the game got paused.
+  paused_lock: Option<Frame>,
   /// The producer we use for creating new stones.
   producer: Rc<dyn StoneProducer>,
-  /// The texture to use for one unit of wall.
-  wall: Rc<Texture>,
+  /// The rotation system used for rotating stones.
+  rotation: RotationSystem,
+  /// Whether or not to render a "ghost" of the active stone at the
+  /// location it would be dropped to.
+  show_ghost: bool,
+  /// Whether gravity is instant, in which case new stones are moved to
+  /// their landing position as soon as they appear.
+  instant_gravity: bool,
 }
 
 impl Field {
   pub fn new(
-    width: i16,
-    height: i16,
+    config: &Config,
+    now: Frame,
     clear_time: Duration,
     producer: Rc<dyn StoneProducer>,
-    piece: Rc<Texture>,
-    back: Rc<Texture>,
   ) -> Self {
-    let pieces = PieceField::new(width, height, back, Rc::clone(&piece));
+    let pieces = PieceField::new(config.field_width, config.field_height);
     let mut stone = producer.create_stone();
     let state = if pieces.reset_stone(&mut stone) {
       State::Moving { stone }
@@ -121,12 +243,33 @@ impl Field {
     };
 
     Self {
-      clear_time,
+      now,
+      clear_time: duration_to_frames(clear_time),
       state,
+      held: None,
+      hold_used: false,
+      lock_delay: Duration::from_millis(config.lock_delay_ms.into()),
+      lock_reset_limit: config.lock_reset_limit,
+      lock_resets: 0,
+      last_rotated: false,
+      paused_lock: None,
       producer,
+      rotation: config.rotation_system,
+      show_ghost: config.enable_ghost,
+      instant_gravity: false,
       pieces,
-      // The walls just use the "piece" texture.
-      wall: piece,
+    }
+  }
+
+  /// Move a newly active stone to its landing position right away if
+  /// gravity is instant, instead of having it linger at the top.
+  ///
+  /// The stone is not locked in place, leaving that to the next
+  /// downward movement.
+  fn sink_new_stone(&mut self) {
+    if let (true, State::Moving { stone }) = (self.instant_gravity, &mut self.state) {
+      let distance = self.pieces.drop_distance(stone);
+      let () = stone.move_by(0, -distance);
     }
   }
 
@@ -139,13 +282,14 @@ impl Field {
         y_range,
         ..
       } => {
-        debug_assert!(Instant::now() > *until);
+        debug_assert!(self.now > *until);
         let _removed = self.pieces.remove_complete_lines(y_range.clone());
         self.state = State::Moving {
           stone: next_stone.take(),
         };
+        let () = self.sink_new_stone();
       },
-      State::Moving { .. } | State::Colliding { .. } => (),
+      State::Moving { .. } | State::Landed { .. } | State::Colliding { .. } => (),
     }
   }
 
@@ -153,9 +297,14 @@ impl Field {
   /// and a stone at its initial position.
   pub fn reset(&mut self) -> bool {
     let () = self.pieces.clear();
+    self.held = None;
+    self.hold_used = false;
+    self.lock_resets = 0;
+    self.last_rotated = false;
     let mut stone = self.producer.create_stone();
     if self.pieces.reset_stone(&mut stone) {
       self.state = State::Moving { stone };
+      let () = self.sink_new_stone();
       true
     } else {
       self.state = State::Colliding { stone };
@@ -163,6 +312,181 @@ impl Field {
     }
   }
 
+  /// Restore the field from a snapshot of its state.
+  ///
+  /// The field's dimensions have to match those of the snapshot.
+  pub fn restore(&mut self, snapshot: &Snapshot) -> Result<()> {
+    let Snapshot {
+      pieces,
+      hold_used,
+      lock_resets,
+      last_rotated,
+      state,
+      held,
+      paused_lock,
+    } = snapshot;
+
+    let (width, height) = (self.pieces.width(), self.pieces.height());
+    ensure!(
+      pieces.len() == usize::try_from(height).unwrap_or_default(),
+      "expected {height} lines of pieces but found {}",
+      pieces.len()
+    );
+
+    let mut matrix = PieceField::new(width, height);
+    for (y, line) in (0..height).rev().zip(pieces) {
+      ensure!(
+        line.chars().count() == usize::try_from(width).unwrap_or_default(),
+        "line `{line}` does not contain {width} pieces"
+      );
+
+      for (x, c) in (0..width).zip(line.chars()) {
+        matrix[Point::new(x, y)] = if c == EMPTY_CELL {
+          None
+        } else {
+          let color_idx = c
+            .to_digit(36)
+            .and_then(|idx| u8::try_from(idx).ok())
+            .filter(|idx| usize::from(*idx) < Piece::COLORS.len())
+            .with_context(|| format!("piece `{c}` is invalid"))?;
+          Some(Piece::new(color_idx))
+        };
+      }
+    }
+
+    let state = match state {
+      StateSnapshot::Moving { stone } => State::Moving {
+        stone: Stone::from_snapshot(stone)?,
+      },
+      StateSnapshot::Landed { stone, lock_at } => State::Landed {
+        stone: Stone::from_snapshot(stone)?,
+        lock_at: *lock_at,
+      },
+      StateSnapshot::Clearing {
+        next_stone,
+        until,
+        y_range,
+      } => State::Clearing {
+        next_stone: Stone::from_snapshot(next_stone)?,
+        until: *until,
+        overlay: Color::black(),
+        y_range: y_range.clone(),
+      },
+      StateSnapshot::Colliding { stone } => State::Colliding {
+        stone: Stone::from_snapshot(stone)?,
+      },
+    };
+
+    self.held = held.as_ref().map(Stone::from_snapshot).transpose()?;
+    self.pieces = matrix;
+    self.state = state;
+    self.hold_used = *hold_used;
+    self.lock_resets = *lock_resets;
+    self.last_rotated = *last_rotated;
+    self.paused_lock = *paused_lock;
+    Ok(())
+  }
+
+  /// Create a snapshot of the field's state.
+  pub fn snapshot(&self) -> Snapshot {
+    let pieces = (0..self.pieces.height())
+      .rev()
+      .map(|y| {
+        (0..self.pieces.width())
+          .map(|x| match self.pieces[Point::new(x, y)] {
+            // SANITY: Color indices are always small enough to be
+            //         represented by a single digit.
+            Some(piece) => char::from_digit(piece.color_idx().into(), 36).unwrap(),
+            None => EMPTY_CELL,
+          })
+          .collect()
+      })
+      .collect();
+
+    let state = match &self.state {
+      State::Moving { stone } => StateSnapshot::Moving {
+        stone: stone.snapshot(),
+      },
+      State::Landed { stone, lock_at } => StateSnapshot::Landed {
+        stone: stone.snapshot(),
+        lock_at: *lock_at,
+      },
+      State::Clearing {
+        next_stone,
+        until,
+        y_range,
+        ..
+      } => StateSnapshot::Clearing {
+        next_stone: next_stone.snapshot(),
+        until: *until,
+        y_range: y_range.clone(),
+      },
+      State::Colliding { stone } => StateSnapshot::Colliding {
+        stone: stone.snapshot(),
+      },
+    };
+
+    Snapshot {
+      pieces,
+      hold_used: self.hold_used,
+      lock_resets: self.lock_resets,
+      last_rotated: self.last_rotated,
+      state,
+      held: self.held.as_ref().map(Stone::snapshot),
+      paused_lock: self.paused_lock,
+    }
+  }
+
+  /// Merge the active stone into the field and replace it with a new
+  /// one.
+  fn merge_stone(&mut self) -> (Change, MoveResult) {
+    match &mut self.state {
+      State::Moving { stone } | State::Landed { stone, .. } => {
+        let new_stone = self.producer.create_stone();
+        let old_stone = replace(stone, new_stone);
+        let bounds = old_stone.bounds();
+        let y_range = bounds.y..bounds.y + bounds.h;
+        let spin = if self.last_rotated {
+          self.pieces.detect_spin(&old_stone)
+        } else {
+          Spin::None
+        };
+
+        let cleared = self.pieces.merge_stone(old_stone);
+        self.hold_used = false;
+        self.lock_resets = 0;
+        self.last_rotated = false;
+        if !self.pieces.reset_stone(stone) {
+          self.state = State::Colliding {
+            stone: stone.take(),
+          };
+          (Change::Changed, MoveResult::Conflict)
+        } else {
+          if cleared > 0 {
+            self.state = State::Clearing {
+              next_stone: stone.take(),
+              until: self.now + self.clear_time,
+              // The initial overlay is basically invisible.
+              overlay: Color::black(),
+              y_range,
+            };
+          } else {
+            self.state = State::Moving {
+              stone: stone.take(),
+            };
+            let () = self.sink_new_stone();
+          }
+          (
+            Change::Changed,
+            MoveResult::Merged(Clear::new(cleared, spin)),
+          )
+        }
+      },
+      State::Clearing { .. } => (Change::Unchanged, MoveResult::None),
+      State::Colliding { .. } => (Change::Unchanged, MoveResult::Conflict),
+    }
+  }
+
   /// Move the stone down.
   fn move_stone_down_impl(&mut self) -> (Change, MoveResult) {
     match &mut self.state {
@@ -173,46 +497,56 @@ impl Field {
         if self.pieces.collides(stone) {
           let () = stone.move_by(0, 1);
 
-          let new_stone = self.producer.create_stone();
-          let old_stone = replace(stone, new_stone);
-          let bounds = old_stone.bounds();
-          let y_range = bounds.y..bounds.y + bounds.h;
-
-          let cleared = self.pieces.merge_stone(old_stone);
-          if !self.pieces.reset_stone(stone) {
-            self.state = State::Colliding {
+          if self.lock_delay.is_zero() {
+            self.merge_stone()
+          } else {
+            self.state = State::Landed {
               stone: stone.take(),
+              lock_at: self.now + duration_to_frames(self.lock_delay),
             };
-            (Change::Changed, MoveResult::Conflict)
-          } else {
-            if cleared > 0 {
-              self.state = State::Clearing {
-                next_stone: stone.take(),
-                until: Instant::now() + self.clear_time,
-                // The initial overlay is basically invisible.
-                overlay: Color::black(),
-                y_range,
-              };
-            }
-            (Change::Changed, MoveResult::Merged(cleared))
+            (Change::Unchanged, MoveResult::Landed)
           }
         } else {
+          self.last_rotated = false;
           (Change::Changed, MoveResult::Moved)
         }
       },
+      State::Landed { stone, .. } => {
+        // A stone that touched down may only fall further if it got
+        // moved or rotated into an unsupported position, in which case
+        // we would already have transitioned back into "moving" state.
+        debug_assert!(self.pieces.drop_distance(stone) == 0);
+        (Change::Unchanged, MoveResult::None)
+      },
       State::Clearing { .. } => (Change::Unchanged, MoveResult::None),
       State::Colliding { .. } => (Change::Unchanged, MoveResult::Conflict),
     }
   }
 
+  /// Merge the active stone into the field immediately, if it has
+  /// touched down.
+  pub fn lock_stone(&mut self) -> (Change, MoveResult) {
+    match &self.state {
+      State::Landed { .. } => self.merge_stone(),
+      State::Moving { .. } | State::Clearing { .. } => (Change::Unchanged, MoveResult::None),
+      State::Colliding { .. } => (Change::Unchanged, MoveResult::Conflict),
+    }
+  }
+
   pub fn drop_stone(&mut self) -> (Change, MoveResult) {
     let mut change = Change::Unchanged;
     loop {
       let result = self.move_stone_down_impl();
       change |= result.0;
 
-      if !matches!(result.1, MoveResult::Moved) {
-        break (change, result.1)
+      match result.1 {
+        MoveResult::Moved => (),
+        // A dropped stone is locked in place right away.
+        MoveResult::Landed | MoveResult::None if matches!(self.state, State::Landed { .. }) => {
+          let result = self.lock_stone();
+          break (change | result.0, result.1)
+        },
+        result => break (change, result),
       }
     }
   }
@@ -221,10 +555,26 @@ impl Field {
     self.move_stone_down_impl()
   }
 
+  /// Update the lock state after the active stone got moved or
+  /// rotated.
+  fn on_stone_moved(&mut self) {
+    if let State::Landed { stone, lock_at } = &mut self.state {
+      if self.pieces.drop_distance(stone) > 0 {
+        self.state = State::Moving {
+          stone: stone.take(),
+        };
+      } else if self.lock_resets < self.lock_reset_limit {
+        self.lock_resets += 1;
+        *lock_at = self.now + duration_to_frames(self.lock_delay);
+      }
+    }
+  }
+
   /// Move the stone horizontally by the given amount.
   fn move_stone_by(&mut self, x: i16) -> Change {
     match &mut self.state {
       State::Moving { stone }
+      | State::Landed { stone, .. }
       | State::Clearing {
         next_stone: stone, ..
       } => {
@@ -234,6 +584,8 @@ impl Field {
           let () = stone.move_by(-x, 0);
           Change::Unchanged
         } else {
+          self.last_rotated = false;
+          let () = self.on_stone_moved();
           Change::Changed
         }
       },
@@ -252,16 +604,16 @@ impl Field {
   fn rotate_stone(&mut self, left: bool) -> Change {
     match &mut self.state {
       State::Moving { stone }
+      | State::Landed { stone, .. }
       | State::Clearing {
         next_stone: stone, ..
       } => {
-        let () = stone.rotate(left);
-
-        if self.pieces.collides(stone) {
-          let () = stone.rotate(!left);
-          Change::Unchanged
-        } else {
+        if self.rotation.rotate(&self.pieces, stone, left) {
+          self.last_rotated = true;
+          let () = self.on_stone_moved();
           Change::Changed
+        } else {
+          Change::Unchanged
         }
       },
       State::Colliding { .. } => Change::Unchanged,
@@ -276,6 +628,46 @@ impl Field {
     self.rotate_stone(false)
   }
 
+  /// Put the currently active stone on hold.
+  ///
+  /// The previously held stone becomes the active one. If no stone was
+  /// held, a new one is retrieved from the producer instead. Holding is
+  /// possible only once per stone.
+  pub fn hold_stone(&mut self) -> Change {
+    match &mut self.state {
+      State::Moving { stone } | State::Landed { stone, .. } if !self.hold_used => {
+        let next_stone = self
+          .held
+          .take()
+          .unwrap_or_else(|| self.producer.create_stone());
+        let mut held = replace(stone, next_stone);
+        let () = held.reset();
+        let bounds = held.bounds();
+        let () = held.move_to(Point::new(0, -bounds.h));
+        self.held = Some(held);
+        self.hold_used = true;
+        self.lock_resets = 0;
+        self.last_rotated = false;
+
+        if self.pieces.reset_stone(stone) {
+          self.state = State::Moving {
+            stone: stone.take(),
+          };
+          let () = self.sink_new_stone();
+        } else {
+          self.state = State::Colliding {
+            stone: stone.take(),
+          };
+        }
+        Change::Changed
+      },
+      State::Moving { .. }
+      | State::Landed { .. }
+      | State::Clearing { .. }
+      | State::Colliding { .. } => Change::Unchanged,
+    }
+  }
+
   /// "Event handler" for informing the field that the overall game has
   /// been paused.
   pub fn on_pause(&mut self) {
@@ -289,40 +681,125 @@ impl Field {
         self.state = State::Moving {
           stone: next_stone.take(),
         };
+        let () = self.sink_new_stone();
+      },
+      State::Landed { lock_at, .. } => {
+        self.paused_lock = Some(lock_at.saturating_sub(self.now));
       },
       State::Colliding { .. } => panic!("attempted to pause from collision state"),
       State::Moving { .. } => (),
     }
   }
 
-  pub fn tick(&mut self, now: Instant) -> (Change, Tick) {
+  /// "Event handler" for informing the field that the overall game has
+  /// been resumed after a pause.
+  pub fn on_resume(&mut self) {
+    let paused_lock = self.paused_lock.take();
+    if let State::Landed { lock_at, .. } = &mut self.state {
+      // Give the player the lock delay that was remaining when the
+      // game got paused, instead of merging the stone right away. It
+      // is never reset to the full delay, as pausing would otherwise
+      // allow for stalling indefinitely.
+      let remaining = paused_lock.unwrap_or_else(|| duration_to_frames(self.lock_delay));
+      *lock_at = self.now + remaining;
+    }
+  }
+
+  /// Insert a line of garbage at the bottom of the field, with a hole
+  /// at the given column, moving everything else up.
+  ///
+  /// Garbage can only be inserted while the active stone is moving (or
+  /// has landed). If pieces get pushed out at the top or the active
+  /// stone cannot be moved out of the way, the field transitions into
+  /// "colliding" state.
+  pub fn insert_garbage(&mut self, hole: i16) -> (Change, MoveResult) {
+    match &mut self.state {
+      State::Moving { stone } | State::Landed { stone, .. } => {
+        let top = self.pieces.height() - 1;
+        let overflow = self.pieces.matrix.iter_line(top).any(Option::is_some);
+        let () = self.pieces.insert_garbage(hole);
+
+        if self.pieces.collides(stone) {
+          let () = stone.move_by(0, 1);
+        }
+
+        if overflow || self.pieces.collides(stone) {
+          self.state = State::Colliding {
+            stone: stone.take(),
+          };
+          (Change::Changed, MoveResult::Conflict)
+        } else {
+          // Getting pushed up by garbage is not a player action and, hence,
+          // does not reset the lock delay. As everything moves up
+          // together, a landed stone stays landed.
+          (Change::Changed, MoveResult::None)
+        }
+      },
+      State::Clearing { .. } => (Change::Unchanged, MoveResult::None),
+      State::Colliding { .. } => (Change::Unchanged, MoveResult::Conflict),
+    }
+  }
+
+  /// Count the lines containing garbage.
+  pub fn garbage_lines(&self) -> u16 {
+    (0..self.pieces.height())
+      .filter(|line| {
+        self
+          .pieces
+          .matrix
+          .iter_line(*line)
+          .any(|piece| piece.is_some_and(|piece| piece.is_garbage()))
+      })
+      .count() as u16
+  }
+
+  /// Set whether gravity is instant.
+  ///
+  /// Under instant gravity, new stones are moved to their landing
+  /// position as soon as they appear, which also applies to a stone
+  /// that is currently moving.
+  pub fn set_instant_gravity(&mut self, instant: bool) {
+    self.instant_gravity = instant;
+    let () = self.sink_new_stone();
+  }
+
+  /// Set the frame currently being simulated.
+  ///
+  /// Time based state transitions triggered by subsequent operations
+  /// are scheduled relative to it.
+  #[inline]
+  pub fn set_frame(&mut self, now: Frame) {
+    self.now = now;
+  }
+
+  pub fn tick(&mut self, now: Frame) -> Change {
+    let () = self.set_frame(now);
+
     match &mut self.state {
       State::Clearing { until, overlay, .. } => {
         if now > *until {
           let () = self.clear_complete_lines();
-          // NB: The field itself doesn't know when the next tick is,
-          //     because it depends on the level etc.
-          (Change::Changed, Tick::None)
+          Change::Changed
         } else {
-          // SANITY: The resulting time will always be representable,
-          //         because we added `clear_time` to construct it.
-          let start = (*until).checked_sub(self.clear_time).unwrap();
-          let max = until.duration_since(start).as_secs_f32();
-          let cur = now.duration_since(start).as_secs_f32();
+          // SANITY: `until` was constructed by adding `clear_time`.
+          let start = *until - self.clear_time;
+          let max = self.clear_time.max(1) as f32;
+          let cur = (now - start) as f32;
           let val = smoothstep(0.0, 255.0, cur / max) as u8;
           overlay.r = val;
           overlay.g = val;
           overlay.b = val;
-          (Change::Changed, Tick::Now)
+          Change::Changed
         }
       },
-      State::Moving { .. } | State::Colliding { .. } => (Change::Unchanged, Tick::None),
+      State::Moving { .. } | State::Landed { .. } | State::Colliding { .. } => Change::Unchanged,
     }
   }
 
   /// Render the walls of the field.
-  fn render_walls(&self, renderer: &Renderer, color_mode: ColorMode) {
-    let _guard = renderer.set_texture(&self.wall);
+  fn render_walls(&self, renderer: &Renderer, textures: &Textures, color_mode: ColorMode) {
+    // The walls just use the "piece" texture.
+    let _guard = renderer.set_texture(&textures.piece);
     let _guard = renderer.set_color(WALL_COLOR.select(color_mode));
 
     let left = Rect::new(0, 0, WALL_WIDTH, self.display_height());
@@ -340,19 +817,75 @@ impl Field {
     let () = renderer.render_rect_with_tex_coords(right.into_other(), right);
   }
 
+  /// Render the "ghost" of the currently active stone, i.e., a faded
+  /// version of it at the location it would be dropped to.
+  fn render_ghost(&self, renderer: &Renderer, textures: &Textures, color_mode: ColorMode) {
+    if let State::Moving { stone } = &self.state {
+      let distance = self.pieces.drop_distance(stone);
+      if distance > 0 {
+        let _guard = renderer.set_origin(Point::new(0, -distance));
+        let overlay = GHOST_OVERLAY.select(color_mode);
+        let () = stone.render_with_overlay(renderer, textures, color_mode, overlay);
+      }
+    }
+  }
+
+  /// Render the outline of the placement suggested for the active
+  /// stone, as described by the locations of its pieces.
+  fn render_hint(&self, renderer: &Renderer, color_mode: ColorMode, hint: &[Point<i16>]) {
+    let _guard = renderer.set_no_texture();
+    let _guard = renderer.set_color(HINT_COLOR.select(color_mode));
+
+    for location in hint {
+      let Point { x, y } = *location;
+      // Only edges not shared with another piece of the placement
+      // make up its outline.
+      let edges = [
+        (Point::new(x - 1, y), Point::new(x, y), Point::new(x, y + 1)),
+        (
+          Point::new(x + 1, y),
+          Point::new(x + 1, y),
+          Point::new(x + 1, y + 1),
+        ),
+        (Point::new(x, y - 1), Point::new(x, y), Point::new(x + 1, y)),
+        (
+          Point::new(x, y + 1),
+          Point::new(x, y + 1),
+          Point::new(x + 1, y + 1),
+        ),
+      ];
+
+      for (neighbor, p1, p2) in edges {
+        if !hint.contains(&neighbor) {
+          let () = renderer.render_line(p1, p2);
+        }
+      }
+    }
+  }
+
   /// Render the currently active stone (if any).
-  fn render_stone(&self, renderer: &Renderer, color_mode: ColorMode) {
+  fn render_stone(&self, renderer: &Renderer, textures: &Textures, color_mode: ColorMode) {
     match &self.state {
       State::Moving { stone }
+      | State::Landed { stone, .. }
       | State::Clearing {
         next_stone: stone, ..
-      } => stone.render(renderer, color_mode),
-      State::Colliding { stone } => stone.render_with_overlay(renderer, color_mode, Color::white()),
+      } => stone.render(renderer, textures, color_mode),
+      State::Colliding { stone } => {
+        stone.render_with_overlay(renderer, textures, color_mode, Color::white())
+      },
     }
   }
 
-  /// Render the Tetris field.
-  pub fn render(&self, renderer: &Renderer, color_mode: ColorMode) {
+  /// Render the Tetris field, along with the outline of the placement
+  /// suggested for the active stone, if any.
+  pub fn render(
+    &self,
+    renderer: &Renderer,
+    textures: &Textures,
+    color_mode: ColorMode,
+    hint: Option<&[Point<i16>]>,
+  ) {
     {
       let complete_overlay = if let State::Clearing { overlay, .. } = &self.state {
         Some(*overlay)
@@ -361,22 +894,35 @@ impl Field {
       };
 
       let _guard = renderer.set_origin(Point::new(WALL_WIDTH, WALL_WIDTH));
-      let () = self.pieces.render(renderer, color_mode, complete_overlay);
-      let () = self.render_stone(renderer, color_mode);
+      let () = self
+        .pieces
+        .render(renderer, textures, color_mode, complete_overlay);
+      if self.show_ghost {
+        let () = self.render_ghost(renderer, textures, color_mode);
+      }
+      let () = self.render_stone(renderer, textures, color_mode);
+      if let Some(hint) = hint {
+        let () = self.render_hint(renderer, color_mode, hint);
+      }
     }
 
-    let () = self.render_walls(renderer, color_mode);
+    let () = self.render_walls(renderer, textures, color_mode);
   }
 
   /// Convert this `Field` into an `ai::Field` together with an
   /// `ai::Stone` representing the currently active stone.
   ///
-  /// This method returns `None` if there is a collision.
+  /// This method returns `None` if there is a collision or if the
+  /// field is too wide for the AI to handle.
   #[inline]
   pub fn to_ai_data(&self) -> Option<(ai::Field, ai::Stone)> {
+    if self.width() > ai::Field::MAX_WIDTH {
+      return None
+    }
+
     match &self.state {
-      State::Moving { stone } => {
-        let field = ai::Field::from_matrix(&self.pieces.matrix);
+      State::Moving { stone } | State::Landed { stone, .. } => {
+        let field = ai::Field::from_matrix(&self.pieces.matrix, self.rotation);
         let stone = stone.to_ai_stone();
         Some((field, stone))
       },
@@ -388,7 +934,7 @@ impl Field {
         // If we are still clearing completed lines we haven't yet
         // updated the piece data, but the AI certainly should only see
         // the state with cleared lines. So clear them after conversion.
-        let mut field = ai::Field::from_matrix(&self.pieces.matrix);
+        let mut field = ai::Field::from_matrix(&self.pieces.matrix, self.rotation);
         let _removed = field.remove_complete_lines(y_range.clone());
         let stone = stone.to_ai_stone();
         Some((field, stone))
@@ -402,6 +948,52 @@ impl Field {
     &self.state
   }
 
+  /// Retrieve the rotation system used for rotating stones.
+  #[inline]
+  pub fn rotation_system(&self) -> RotationSystem {
+    self.rotation
+  }
+
+  /// Calculate the number of units the active stone can be moved down
+  /// before touching down.
+  pub fn drop_distance(&self) -> i16 {
+    match &self.state {
+      State::Moving { stone } | State::Landed { stone, .. } => self.pieces.drop_distance(stone),
+      State::Clearing { .. } | State::Colliding { .. } => 0,
+    }
+  }
+
+  /// Calculate the height of the stack of locked pieces and the
+  /// number of holes in it.
+  #[inline]
+  pub fn stack(&self) -> (i16, u16) {
+    self.pieces.stack()
+  }
+
+  /// Retrieve the lock delay, in milliseconds.
+  #[inline]
+  pub fn lock_delay_ms(&self) -> u32 {
+    self.lock_delay.as_millis() as u32
+  }
+
+  /// Retrieve the maximum number of lock delay resets per stone.
+  #[inline]
+  pub fn lock_reset_limit(&self) -> u8 {
+    self.lock_reset_limit
+  }
+
+  /// Check whether the "ghost" of the active stone is being rendered.
+  #[inline]
+  pub fn show_ghost(&self) -> bool {
+    self.show_ghost
+  }
+
+  /// Retrieve the stone currently put on hold, if any.
+  #[inline]
+  pub fn held_stone(&self) -> Option<&Stone> {
+    self.held.as_ref()
+  }
+
   #[inline]
   pub fn width(&self) -> i16 {
     self.pieces.width()
@@ -438,18 +1030,12 @@ impl Field {
 struct PieceField {
   /// The matrix (2D array) of pieces.
   matrix: Matrix<Option<Piece>>,
-  /// The texture to use for the entire inner back area.
-  back: Rc<Texture>,
-  /// The texture to use for pieces.
-  piece: Rc<Texture>,
 }
 
 impl PieceField {
-  fn new(width: i16, height: i16, back: Rc<Texture>, piece: Rc<Texture>) -> Self {
+  fn new(width: i16, height: i16) -> Self {
     Self {
       matrix: Matrix::new(width, height),
-      back,
-      piece,
     }
   }
 
@@ -459,11 +1045,115 @@ impl PieceField {
     self.matrix.clear()
   }
 
+  /// Insert a line of garbage with a hole at the given column at the
+  /// bottom of the field, moving everything else up.
+  fn insert_garbage(&mut self, hole: i16) {
+    let () = self.matrix.insert_line(0);
+    for x in (0..self.width()).filter(|x| *x != hole) {
+      self[Point::new(x, 0)] = Some(Piece::garbage());
+    }
+  }
+
+  /// Calculate the height of the stack of pieces and the number of
+  /// holes in it, i.e., of free cells with a piece above them.
+  fn stack(&self) -> (i16, u16) {
+    let mut height = 0;
+    let mut holes = 0;
+
+    for x in 0..self.width() {
+      let top = (0..self.height())
+        .rev()
+        .find(|y| self[Point::new(x, *y)].is_some());
+      if let Some(top) = top {
+        height = height.max(top + 1);
+        holes += (0..top)
+          .filter(|y| self[Point::new(x, *y)].is_none())
+          .count() as u16;
+      }
+    }
+    (height, holes)
+  }
+
+  /// Check whether the given location is occupied by a piece or lies
+  /// to the side of or below the field.
+  fn is_occupied(&self, location: Point<i16>) -> bool {
+    if location.x < 0 || location.x >= self.width() || location.y < 0 {
+      true
+    } else if location.y >= self.height() {
+      false
+    } else {
+      self[location].is_some()
+    }
+  }
+
+  /// Determine the kind of T-spin, if any, the provided stone is placed
+  /// with, assuming that it got rotated into place.
+  ///
+  /// A T-spin requires at least three of the four corners diagonal to
+  /// the center of the T to be occupied. It is a full one if both
+  /// corners on the side the T points to are among them.
+  fn detect_spin(&self, stone: &Stone) -> Spin {
+    const DIRECTIONS: [Point<i16>; 4] = [
+      Point::new(1, 0),
+      Point::new(-1, 0),
+      Point::new(0, 1),
+      Point::new(0, -1),
+    ];
+    const CORNERS: [Point<i16>; 4] = [
+      Point::new(1, 1),
+      Point::new(1, -1),
+      Point::new(-1, 1),
+      Point::new(-1, -1),
+    ];
+
+    if stone.pieces().len() != 4 {
+      return Spin::None
+    }
+
+    let is_piece = |location: Point<i16>| stone.pieces().any(|piece| piece == location);
+    // The center of a T stone is the only piece with three direct
+    // neighbors.
+    let center = stone.pieces().find(|center| {
+      DIRECTIONS
+        .iter()
+        .filter(|direction| is_piece(*center + **direction))
+        .count()
+        == 3
+    });
+    let Some(center) = center else {
+      return Spin::None
+    };
+    // SANITY: A piece with three neighbors has exactly one direction
+    //         without one.
+    let back = DIRECTIONS
+      .into_iter()
+      .find(|direction| !is_piece(center + *direction))
+      .unwrap();
+
+    let occupied = CORNERS
+      .iter()
+      .filter(|corner| self.is_occupied(center + **corner))
+      .count();
+    if occupied < 3 {
+      return Spin::None
+    }
+
+    let front_occupied = CORNERS
+      .iter()
+      .filter(|corner| corner.x * back.x + corner.y * back.y < 0)
+      .all(|corner| self.is_occupied(center + *corner));
+    if front_occupied {
+      Spin::Full
+    } else {
+      Spin::Mini
+    }
+  }
+
   /// Render the background of the field and draw vertical lines.
-  fn render_back(&self, renderer: &Renderer, color_mode: ColorMode) {
+  fn render_back(&self, renderer: &Renderer, textures: &Textures, color_mode: ColorMode) {
     // Render background image.
     {
-      let _guard = renderer.set_texture(&self.back);
+      let _guard = renderer.set_texture(&textures.field_back);
       let _guard = renderer.set_color(BACKGROUND_COLOR.select(color_mode));
 
       let () = renderer.render_rect(Rect::new(0, 0, self.width(), self.height()));
@@ -484,10 +1174,11 @@ impl PieceField {
   fn render_pieces(
     &self,
     renderer: &Renderer,
+    textures: &Textures,
     color_mode: ColorMode,
     complete_overlay: Option<Color>,
   ) {
-    let _guard = renderer.set_texture(&self.piece);
+    let _guard = renderer.set_texture(&textures.piece);
 
     let mut complete = (-1, false);
 
@@ -512,9 +1203,15 @@ impl PieceField {
       })
   }
 
-  fn render(&self, renderer: &Renderer, color_mode: ColorMode, complete_overlay: Option<Color>) {
-    let () = self.render_back(renderer, color_mode);
-    let () = self.render_pieces(renderer, color_mode, complete_overlay);
+  fn render(
+    &self,
+    renderer: &Renderer,
+    textures: &Textures,
+    color_mode: ColorMode,
+    complete_overlay: Option<Color>,
+  ) {
+    let () = self.render_back(renderer, textures, color_mode);
+    let () = self.render_pieces(renderer, textures, color_mode, complete_overlay);
   }
 }
 
@@ -543,6 +1240,11 @@ impl Fieldlike<Stone> for PieceField {
     self.matrix.height()
   }
 
+  #[inline]
+  fn place(&mut self, location: Point<i16>, piece: Piece) -> Option<Piece> {
+    self[location].replace(piece)
+  }
+
   #[inline]
   fn line_complete(&self, line: i16) -> bool {
     self.matrix.iter_line(line).all(Option::is_some)
@@ -553,3 +1255,109 @@ impl Fieldlike<Stone> for PieceField {
     self.matrix.remove_line(line)
   }
 }
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use super::super::ai::util::matrix;
+
+
+  /// Create a `PieceField` from a matrix describing occupied cells.
+  fn piece_field(matrix: &Matrix<Option<()>>) -> PieceField {
+    let mut field = PieceField::new(matrix.width(), matrix.height());
+    for (piece, location) in matrix.iter() {
+      if piece.is_some() {
+        field[location] = Some(Piece::new(0));
+      }
+    }
+    field
+  }
+
+  /// Create a stone with pieces at the given locations.
+  fn stone(pieces: &[(i8, i8)]) -> Stone {
+    let template = pieces
+      .iter()
+      .map(|(x, y)| Point::new(*x, *y))
+      .collect::<Vec<_>>();
+    Stone::new(&template, 0)
+  }
+
+
+  /// Check that we measure the stack of pieces correctly.
+  #[test]
+  fn stack() {
+    let field = piece_field(&matrix! {"
+      .....
+      .....
+      .....
+    "});
+    assert_eq!(field.stack(), (0, 0));
+
+    let field = piece_field(&matrix! {"
+      ..#..
+      .#...
+      ##..#
+    "});
+    assert_eq!(field.stack(), (3, 2));
+
+    let field = piece_field(&matrix! {"
+      .....
+      #.#.#
+      .#..#
+      #.###
+    "});
+    assert_eq!(field.stack(), (3, 3));
+  }
+
+  /// Check that a T stone with both corners on the side it points to
+  /// and a third one occupied is detected as a full T-spin.
+  #[test]
+  fn spin_full() {
+    let field = piece_field(&matrix! {"
+      ##...
+      #...#
+      ##.##
+    "});
+    // A T pointing down, into the slot.
+    let t = stone(&[(1, 1), (2, 1), (3, 1), (2, 0)]);
+    assert_eq!(field.detect_spin(&t), Spin::Full);
+  }
+
+  /// Check that a T stone with three occupied corners, but only one on
+  /// the side it points to, is detected as a mini T-spin.
+  #[test]
+  fn spin_mini() {
+    let field = piece_field(&matrix! {"
+      ##...
+      #...#
+      ##.##
+    "});
+    // A T pointing up, away from the slot.
+    let t = stone(&[(1, 1), (2, 1), (3, 1), (2, 2)]);
+    assert_eq!(field.detect_spin(&t), Spin::Mini);
+  }
+
+  /// Check that stones not satisfying the three corner rule or not
+  /// being T stones are not detected as spinning.
+  #[test]
+  fn spin_none() {
+    let field = piece_field(&matrix! {"
+      .....
+      ##.##
+    "});
+    // Only the two bottom corners are occupied; the top ones are above
+    // the field.
+    let t = stone(&[(1, 1), (2, 1), (3, 1), (2, 0)]);
+    assert_eq!(field.detect_spin(&t), Spin::None);
+
+    let field = piece_field(&matrix! {"
+      ##...
+      #...#
+      ##.##
+    "});
+    let l = stone(&[(1, 1), (2, 1), (3, 1), (3, 2)]);
+    assert_eq!(field.detect_spin(&l), Spin::None);
+  }
+}
@@ -2,7 +2,6 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use std::ops::Index;
-use std::ops::IndexMut;
 use std::ops::Range;
 
 use crate::Point;
@@ -14,7 +13,6 @@ pub(super) trait Fieldlike<S>
 where
   S: Stonelike,
   Self: Index<Point<i16>, Output = Option<S::Piece>>,
-  Self: IndexMut<Point<i16>>,
 {
   fn width(&self) -> i16;
   fn height(&self) -> i16;
@@ -34,6 +32,22 @@ where
     })
   }
 
+  /// Calculate the number of units the provided stone can be moved
+  /// down before colliding with pieces or the bottom of the field.
+  fn drop_distance(&self, stone: &S) -> i16 {
+    stone
+      .pieces()
+      .map(|location| {
+        let mut y = location.y;
+        while y > 0 && self[Point::new(location.x, y - 1)].is_none() {
+          y -= 1;
+        }
+        location.y - y
+      })
+      .min()
+      .unwrap_or(0)
+  }
+
   /// Merge the provided stone into the field.
   fn merge_stone(&mut self, stone: S) -> u16 {
     // We should not have a current collision so that there is no
@@ -42,7 +56,7 @@ where
 
     let bounds = stone.bounds();
     let () = stone.into_pieces().for_each(|(piece, location)| {
-      let _prev = self[location].replace(piece);
+      let _prev = self.place(location, piece);
       debug_assert!(_prev.is_none(), "{location:?}");
     });
 
@@ -69,6 +83,10 @@ where
     !self.collides(stone)
   }
 
+  /// Put a piece at the given location, returning the one previously
+  /// located there, if any.
+  fn place(&mut self, location: Point<i16>, piece: S::Piece) -> Option<S::Piece>;
+
   /// Check whether the given line is complete.
   fn line_complete(&self, line: i16) -> bool;
 
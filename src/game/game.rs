@@ -2,12 +2,8 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use std::cmp::max;
-use std::cmp::min;
-use std::io::Cursor;
 use std::num::NonZeroU16;
 use std::num::NonZeroU32;
-use std::rc::Rc;
-use std::time::Duration;
 
 use anyhow::Context as _;
 use anyhow::Result;
@@ -17,28 +13,30 @@ use xgl::sys::Gl as _;
 
 use crate::ActiveRenderer;
 use crate::Change;
+use crate::Color;
 use crate::ColorMode;
 use crate::ColorSet;
-use crate::Instant;
+use crate::Font;
+use crate::Frame;
 use crate::Point;
-use crate::Texture;
-use crate::TextureBuilderExt as _;
 use crate::Tick;
 use crate::gl;
 use crate::gl::Renderer;
+use crate::highscore::HighScores;
 
+use super::AiWeights;
 use super::Blur;
 use super::Camera;
 use super::Config;
-use super::Field;
-use super::MoveResult;
-use super::PreviewStones;
-use super::Score;
-use super::Stone;
-use super::StoneFactory;
-use super::ai;
-use super::data;
-use super::field::State as FieldState;
+use super::Engine;
+use super::Outcome;
+use super::Scoreboard;
+use super::Snapshot;
+use super::StoneProducer as _;
+use super::Textures;
+use super::scoreboard::Line;
+use super::scoreboard::PROMPT_LINE_SIZE;
+use super::scoreboard::render_lines;
 
 
 /// The color set used when clearing the screen.
@@ -68,73 +66,184 @@ const TOP_SPACE: i16 = 1;
 const PREVIEW_FIELD_SPACE: i16 = 1;
 /// Space between the preview stones and the score board.
 const PREVIEW_SCORE_SPACE: i16 = 1;
-/// The time for which we highlight any completed lines while not
-/// responding to any input.
-// TODO: Make configurable.
-const CLEAR_TIME: Duration = Duration::from_millis(200);
-
-
-/// The state the [`Game`] is in.
+/// Space between the preview stones and the hold box.
+const PREVIEW_HOLD_SPACE: i16 = 1;
+/// Space between the hold box and the score board.
+const HOLD_SCORE_SPACE: i16 = 1;
+/// Space between the hold box' frame and the held stone.
+const HOLD_PADDING: i16 = 1;
+/// The color set used for the frame of the hold box.
+const HOLD_FRAME_COLOR: ColorSet<Color> = ColorSet::new(Color::black(), Color::gray());
+
+
+/// A prompt asking the player whether to continue a saved game or to
+/// start a new one.
 #[derive(Debug)]
-enum State {
-  Running {
-    /// The time of the next tick, i.e., the next downward movement.
-    next_tick: Instant,
-  },
-  Paused {
-    /// The state we use for blurring the background.
-    blur: Option<Blur>,
-  },
-  Over,
+struct ResumePrompt {
+  /// The name of the key continuing the saved game.
+  continue_key: String,
+  /// The name of the key starting a new game.
+  restart_key: String,
+  /// The font to render the prompt with.
+  font: Font,
 }
 
+impl ResumePrompt {
+  /// Render the prompt, centered inside an area of size `w` x `h`.
+  fn render(
+    &self,
+    renderer: &ActiveRenderer,
+    textures: &Textures,
+    color_mode: ColorMode,
+    w: i16,
+    h: i16,
+  ) {
+    let lines: [Line<'_>; 3] = [
+      (b"Resume game?", b""),
+      (self.continue_key.as_bytes(), b"continue"),
+      (self.restart_key.as_bytes(), b"new game"),
+    ];
+    render_lines(
+      &self.font,
+      renderer,
+      textures,
+      color_mode,
+      &lines,
+      None,
+      PROMPT_LINE_SIZE,
+      w,
+      h,
+    )
+  }
+}
 
-#[derive(Debug)]
-struct Inner {
-  /// The game's state.
-  state: State,
+
+/// Everything required for rendering a game, borrowed from a [`Game`].
+struct Scene<'game> {
+  /// The game's rules engine, providing the state to render.
+  engine: &'game Engine,
+  /// The textures to render with.
+  textures: &'game Textures,
+  /// The high score table, if any.
+  scoreboard: Option<&'game Scoreboard>,
+  /// The prompt asking whether to resume a saved game, if shown.
+  resume_prompt: Option<&'game ResumePrompt>,
   /// The color mode in use.
   color_mode: ColorMode,
-  /// The Tetris field.
-  field: Field,
-  /// The preview stones.
-  preview: Rc<PreviewStones>,
-  /// The current score.
-  score: Score,
-  /// The AI playing the game, if any.
-  ai: Option<ai::AI>,
 }
 
-impl Inner {
+impl Scene<'_> {
   fn render(&self, renderer: &ActiveRenderer) {
     let field_location = Point::new(LEFT_SPACE, BOTTOM_SPACE);
     {
       let _guard = renderer.set_origin(field_location);
-      let () = self.field.render(renderer, self.color_mode);
+      let hint = self.engine.hint();
+      let () = self
+        .engine
+        .field()
+        .render(renderer, self.textures, self.color_mode, hint);
+
+      let w = self.engine.field().display_width();
+      let h = self.engine.field().display_height();
+      if let Some(prompt) = self.resume_prompt {
+        let () = prompt.render(renderer, self.textures, self.color_mode, w, h);
+      } else if self.engine.is_over() {
+        match self.scoreboard {
+          Some(scoreboard) if scoreboard.is_active() => {
+            let () = scoreboard.render(renderer, self.textures, self.color_mode, w, h);
+          },
+          _ => {
+            let () = self.engine.session().render_results(
+              renderer,
+              self.textures,
+              self.color_mode,
+              self.engine.score(),
+              w,
+              h,
+            );
+          },
+        }
+      }
     }
 
     let preview_location = field_location
-      + Point::new(self.field.display_width(), self.field.display_height())
+      + Point::new(
+        self.engine.field().display_width(),
+        self.engine.field().display_height(),
+      )
       + Point::new(RIGHT_SPACE, 0);
     {
       let _guard = renderer.set_origin(preview_location);
-      let () = self.preview.render(renderer, self.color_mode);
+      let () = self
+        .engine
+        .preview()
+        .render(renderer, self.textures, self.color_mode);
     }
 
-    let score_location =
-      preview_location - Point::new(0, self.preview.height() + PREVIEW_SCORE_SPACE);
+    let score_location = if self.engine.hold_enabled() {
+      let hold_location =
+        preview_location - Point::new(0, self.engine.preview().height() + PREVIEW_HOLD_SPACE);
+      {
+        let _guard = renderer.set_origin(hold_location);
+        let () = self.render_hold(renderer);
+      }
+      hold_location - Point::new(0, self.hold_height() + HOLD_SCORE_SPACE)
+    } else {
+      preview_location - Point::new(0, self.engine.preview().height() + PREVIEW_SCORE_SPACE)
+    };
     {
       let _guard = renderer.set_origin(score_location);
-      let () = self.score.render(renderer);
+      let () = self.engine.score().render(renderer, self.textures);
+    }
+  }
+
+  /// Render the hold box, including the held stone, if any, with the
+  /// current origin marking its upper left corner.
+  fn render_hold(&self, renderer: &ActiveRenderer) {
+    let w = self.hold_width();
+    let h = self.hold_height();
+    {
+      let _guard = renderer.set_no_texture();
+      let _guard = renderer.set_color(HOLD_FRAME_COLOR.select(self.color_mode));
+
+      let () = renderer.render_line(Point::new(0, 0), Point::new(w, 0));
+      let () = renderer.render_line(Point::new(w, 0), Point::new(w, -h));
+      let () = renderer.render_line(Point::new(w, -h), Point::new(0, -h));
+      let () = renderer.render_line(Point::new(0, -h), Point::new(0, 0));
+    }
+
+    if let Some(stone) = self.engine.field().held_stone() {
+      let _guard = renderer.set_origin(Point::new(HOLD_PADDING, -HOLD_PADDING));
+      let () = stone.render(renderer, self.textures, self.color_mode);
     }
   }
 
+  /// Retrieve the width of the hold box.
+  #[inline]
+  fn hold_width(&self) -> i16 {
+    self.engine.preview().max_dimensions().0 + 2 * HOLD_PADDING
+  }
+
+  /// Retrieve the height of the hold box.
+  #[inline]
+  fn hold_height(&self) -> i16 {
+    self.engine.preview().max_dimensions().1 + 2 * HOLD_PADDING
+  }
+
   /// Retrieve the game surface's width.
   fn width(&self) -> NonZeroU16 {
+    let hold_width = if self.engine.hold_enabled() {
+      self.hold_width()
+    } else {
+      0
+    };
     let width = LEFT_SPACE
-      + self.field.display_width()
+      + self.engine.field().display_width()
       + PREVIEW_FIELD_SPACE
-      + max(self.preview.width(), self.score.width())
+      + max(
+        max(self.engine.preview().width(), hold_width),
+        self.engine.score().width(),
+      )
       + RIGHT_SPACE;
     // SAFETY: The provided width is guaranteed to be greater than zero.
     unsafe { NonZeroU16::new_unchecked(width as u16) }
@@ -142,7 +251,7 @@ impl Inner {
 
   /// Retrieve the game surface's height.
   fn height(&self) -> NonZeroU16 {
-    let height = BOTTOM_SPACE + self.field.display_height() + TOP_SPACE;
+    let height = BOTTOM_SPACE + self.engine.field().display_height() + TOP_SPACE;
     // SAFETY: The provided height is guaranteed to be greater than zero.
     unsafe { NonZeroU16::new_unchecked(height as u16) }
   }
@@ -150,6 +259,9 @@ impl Inner {
 
 
 /// A type representing a game of Tetris.
+///
+/// The game logic itself is provided by an [`Engine`], which a `Game`
+/// renders using OpenGL.
 #[derive(Debug)]
 pub struct Game {
   /// Our GL render state.
@@ -158,435 +270,352 @@ pub struct Game {
   camera: Camera,
   /// The renderer we use.
   renderer: Renderer,
-  /// Our inner game state.
-  inner: Inner,
+  /// The textures we render with.
+  textures: Textures,
+  /// The color mode in use.
+  color_mode: ColorMode,
+  /// The high score table, if high scores are recorded.
+  scoreboard: Option<Scoreboard>,
+  /// The state we use for blurring the background while the game is
+  /// paused.
+  blur: Option<Blur>,
+  /// The prompt asking whether to resume a saved game, while shown.
+  resume_prompt: Option<ResumePrompt>,
+  /// The game's rules engine.
+  engine: Engine,
 }
 
 impl Game {
   /// Instantiate a new game of Tetris with the given configuration.
+  ///
+  /// All randomness in the game is derived from `seed`. If none is
+  /// provided, a seed based on the current time is used.
   pub fn with_config(
     phys_w: NonZeroU32,
     phys_h: NonZeroU32,
     config: &Config,
+    seed: Option<u64>,
     context: &sys::Context,
   ) -> Result<Self> {
-    let reader = Cursor::new(data::TETRIS_FIELD_PIECE_TEXTURE);
-    let piece = image::ImageReader::with_format(reader, image::ImageFormat::Png).decode()?;
-    let piece = Texture::builder()
-      .set_context(context)
-      .from_dynamic_image(&piece)?;
-    let piece = Rc::new(piece);
-
-    let factory = Box::new(StoneFactory::with_default_stones(Rc::clone(&piece)));
-
-    let preview = PreviewStones::new(config.preview_stone_count, factory);
-    let preview = Rc::new(preview);
-
-    let reader = Cursor::new(data::TETRIS_FIELD_BACK_TEXTURE);
-    let field_back = image::ImageReader::with_format(reader, image::ImageFormat::Png).decode()?;
-    let field_back = Texture::builder()
-      .set_context(context)
-      .from_dynamic_image(&field_back)?;
-    let field_back = Rc::new(field_back);
-    let field = Field::new(
-      config.field_width,
-      config.field_height,
-      CLEAR_TIME,
-      Rc::clone(&preview) as _,
-      Rc::clone(&piece),
-      field_back,
-    );
-
-    let score = Score::new(config.start_level, config.lines_for_level, piece);
-
-    let state = match field.state() {
-      FieldState::Moving { .. } | FieldState::Clearing { .. } => State::Running {
-        next_tick: Self::next_tick(Instant::now(), score.level()),
-      },
-      FieldState::Colliding { .. } => State::Over,
-    };
+    let engine = Engine::with_config(config, seed)?;
+    Self::with_engine(phys_w, phys_h, config, engine, context)
+  }
 
-    let ai = if config.enable_ai {
-      Self::create_ai(&field, &preview)
-    } else {
-      None
-    };
+  /// Resume a game of Tetris from a snapshot of its state.
+  ///
+  /// Game logic related settings are taken from the snapshot, while
+  /// all others, such as [`Config::enable_dark_mode`], are taken from
+  /// `config`.
+  pub fn from_snapshot(
+    phys_w: NonZeroU32,
+    phys_h: NonZeroU32,
+    config: &Config,
+    snapshot: &Snapshot,
+    context: &sys::Context,
+  ) -> Result<Self> {
+    let engine = Engine::from_snapshot(snapshot)?;
+    Self::with_engine(phys_w, phys_h, config, engine, context)
+  }
 
-    let inner = Inner {
-      state,
-      color_mode: if config.enable_dark_mode {
-        ColorMode::Dark
-      } else {
-        ColorMode::Light
-      },
-      field,
-      preview,
-      ai,
-      score,
+  fn with_engine(
+    phys_w: NonZeroU32,
+    phys_h: NonZeroU32,
+    config: &Config,
+    mut engine: Engine,
+    context: &sys::Context,
+  ) -> Result<Self> {
+    // Searching in the background keeps the AI from stalling rendering
+    // and input handling.
+    let () = engine.set_async_ai(true);
+    let textures = Textures::new(context).context("failed to load textures")?;
+    let color_mode = if config.enable_dark_mode {
+      ColorMode::Dark
+    } else {
+      ColorMode::Light
     };
 
     let mut gl_state = gl::State::new(context).context("failed to initialize GL state")?;
     let object = gl_state.object();
-    let camera = Camera::new(phys_w, phys_h, inner.width(), inner.height());
+    let scene = Scene {
+      engine: &engine,
+      textures: &textures,
+      scoreboard: None,
+      resume_prompt: None,
+      color_mode,
+    };
+    let camera = Camera::new(phys_w, phys_h, scene.width(), scene.height());
     let renderer = Renderer::new(object).context("failed to create GL renderer")?;
 
     let slf = Self {
       gl_state,
       camera,
       renderer,
-      inner,
+      textures,
+      color_mode,
+      scoreboard: None,
+      blur: None,
+      resume_prompt: None,
+      engine,
     };
     Ok(slf)
   }
 
-  fn with_ai_data<F, R>(field: &Field, preview: &PreviewStones, f: F) -> Option<R>
-  where
-    F: FnOnce(&ai::Field, &ai::Stone, &[ai::Stone]) -> R,
-  {
-    if let Some((field, stone)) = field.to_ai_data() {
-      // TODO: Ideally we should use all preview stones (perhaps even
-      //       more). But right now our search algorithm is too compute
-      //       intensive to make that happen.
-      // TODO: Ideally we would not have to allocate here.
-      let stones = preview
-        .with_stones(move |stones| stones.take(1).map(Stone::to_ai_stone).collect::<Vec<_>>());
-
-      let result = f(&field, &stone, &stones);
-      Some(result)
-    } else {
-      None
+  /// Advance the game simulation up to the given frame.
+  ///
+  /// See [`Engine::tick`] for details.
+  pub fn tick(&mut self, frame: Frame) -> (Change, Tick<Frame>) {
+    let (mut change, tick) = self.engine.tick(frame);
+
+    if let Some(scoreboard) = &mut self.scoreboard {
+      if let Some(outcome) = self.engine.outcome() {
+        if scoreboard.on_game_over(outcome) {
+          change |= Change::Changed;
+        }
+      }
     }
+    (change, tick)
   }
 
-  fn advance_ai(ai: &mut ai::AI, field: &Field, preview: &PreviewStones) {
-    let _result = Self::with_ai_data(field, preview, |field, stone, next_stones| {
-      let () = ai.advance_stone(field, stone, next_stones);
-    });
-  }
-
-  fn create_ai(field: &Field, preview: &PreviewStones) -> Option<ai::AI> {
-    Self::with_ai_data(field, preview, |field, stone, next_stones| {
-      ai::AI::new(field, stone, next_stones)
-    })
+  /// Update the view after the containing window or contained logical
+  /// dimensions have changed.
+  pub fn update_view(&mut self, phys_w: Option<NonZeroU32>, phys_h: Option<NonZeroU32>) {
+    let scene = Scene {
+      engine: &self.engine,
+      textures: &self.textures,
+      scoreboard: self.scoreboard.as_ref(),
+      resume_prompt: self.resume_prompt.as_ref(),
+      color_mode: self.color_mode,
+    };
+    self
+      .camera
+      .update_view(phys_w, phys_h, scene.width(), scene.height())
   }
 
-  fn ai_handle_regular_move(ai: &mut Option<ai::AI>, field: &mut Field) -> Change {
-    let mut change = Change::Unchanged;
-
-    if let Some(ai) = ai.as_mut() {
-      while let Some(action) = ai.peek() {
-        change |= match action {
-          ai::Action::MoveLeft => field.move_stone_left(),
-          ai::Action::MoveRight => field.move_stone_right(),
-          ai::Action::RotateLeft => field.rotate_stone_left(),
-          ai::Action::RotateRight => field.rotate_stone_right(),
-          ai::Action::Merge | ai::Action::MoveDown => return change,
-        };
-
-        let _ = ai.next();
-      }
+  /// Restart the game.
+  pub fn restart(&mut self) -> Change {
+    // A restarted game is never paused.
+    self.blur = None;
+    self.resume_prompt = None;
+    if let Some(scoreboard) = &mut self.scoreboard {
+      let () = scoreboard.reset();
     }
-
-    change
+    self.engine.restart()
   }
 
-  fn ai_remove_down_move(ai: &mut Option<ai::AI>) {
-    if let Some(ai) = ai.as_mut() {
-      if let Some(ai::Action::MoveDown) = ai.peek() {
-        let _ = ai.next();
-      }
+  /// Pause or unpause the game.
+  pub(crate) fn pause(&mut self, pause: bool) {
+    let was_paused = self.engine.is_paused();
+    let () = self.engine.pause(pause);
+
+    if !self.engine.is_paused() {
+      self.blur = None;
+      self.resume_prompt = None;
+    } else if !was_paused {
+      self.blur = Blur::new(&self.gl_state).ok();
     }
   }
 
-  fn ai_remove_stone_merge(ai: &mut Option<ai::AI>, field: &Field, preview: &PreviewStones) {
-    if let Some(ai) = ai.as_mut() {
-      if let Some(ai::Action::Merge) = ai.peek() {
-        let _merge = ai.next();
-        debug_assert_eq!(ai.next(), None);
-
-        let () = Self::advance_ai(ai, field, preview);
-      }
-    }
+  /// Pause the game, asking the player whether to continue it, by
+  /// pressing the key named `continue_key`, or to start a new one,
+  /// using `restart_key`.
+  ///
+  /// The prompt is shown until the game is unpaused or restarted.
+  #[cfg_attr(target_arch = "wasm32", expect(dead_code))]
+  pub(crate) fn prompt_resume(&mut self, continue_key: String, restart_key: String) {
+    // The background is not blurred, so that the player can see the
+    // game they are asked about.
+    let () = self.engine.pause(true);
+    self.resume_prompt = Some(ResumePrompt {
+      continue_key,
+      restart_key,
+      font: Font::builtin(),
+    });
   }
 
-  /// Calculate the time of the next tick, given the current one.
-  fn next_tick(current_tick: Instant, level: u16) -> Instant {
-    // The current stone drop speed, in units per second.
-    let units_per_sec = 1.0 + 0.2 * level as f32;
-    current_tick + Duration::from_secs_f32(1.0 / units_per_sec)
+  /// Start recording the results of finished games in the provided
+  /// high score table.
+  #[cfg_attr(target_arch = "wasm32", expect(dead_code))]
+  pub(crate) fn set_high_scores(&mut self, scores: HighScores) {
+    self.scoreboard = Some(Scoreboard::new(scores));
   }
 
-  /// Fast-forward the game to the current time.
-  ///
-  /// This includes moving the currently active stone according to the
-  /// elapsed time since the last update.
-  pub fn tick(&mut self, now: Instant) -> (Change, Tick) {
-    let next_tick = match &mut self.inner.state {
-      State::Running { next_tick } => next_tick,
-      State::Paused { .. } | State::Over => return (Change::Unchanged, Tick::None),
-    };
-
-    let clearing_until = if let FieldState::Clearing { until, .. } = self.inner.field.state() {
-      Some(*until)
-    } else {
-      None
-    };
-    let (mut change, field_tick) = self.inner.field.tick(now);
-
-    match self.inner.field.state() {
-      FieldState::Moving { .. } => {
-        if let Some(until) = clearing_until {
-          *next_tick = Self::next_tick(until, self.inner.score.level());
-        }
-      },
-      FieldState::Clearing { .. } => return (change, field_tick),
-      FieldState::Colliding { .. } => {
-        let () = self.set_game_over();
-        return (Change::Unchanged, Tick::None)
-      },
-    }
-
-    loop {
-      change |= Self::ai_handle_regular_move(&mut self.inner.ai, &mut self.inner.field);
-
-      if now >= *next_tick {
-        let result = self.inner.field.move_stone_down();
-        change |= result.0;
+  /// Retrieve the high score table, if high scores are recorded.
+  #[cfg_attr(target_arch = "wasm32", expect(dead_code))]
+  pub(crate) fn high_scores(&self) -> Option<&HighScores> {
+    self.scoreboard.as_ref().map(Scoreboard::high_scores)
+  }
 
-        match result.1 {
-          MoveResult::None => (),
-          MoveResult::Moved => {
-            let () = Self::ai_remove_down_move(&mut self.inner.ai);
-          },
-          MoveResult::Merged(lines) => {
-            change |= Self::handle_merged_lines(&mut self.inner.score, lines);
-            let () = Self::ai_remove_down_move(&mut self.inner.ai);
-            let () = Self::ai_remove_stone_merge(
-              &mut self.inner.ai,
-              &self.inner.field,
-              &self.inner.preview,
-            );
-          },
-          MoveResult::Conflict => {
-            let () = self.set_game_over();
-            return (change, Tick::None)
-          },
-        }
+  /// Check whether the player is currently asked for a name to record
+  /// a new high score under.
+  #[inline]
+  pub(crate) fn is_prompting(&self) -> bool {
+    self
+      .scoreboard
+      .as_ref()
+      .is_some_and(Scoreboard::is_prompting)
+  }
 
-        *next_tick = Self::next_tick(*next_tick, self.inner.score.level());
-      } else {
-        break
-      }
+  /// Update the scoreboard, if any, using the provided function.
+  fn update_scoreboard<F>(&mut self, f: F) -> Change
+  where
+    F: FnOnce(&mut Scoreboard) -> bool,
+  {
+    match self.scoreboard.as_mut().map(f) {
+      Some(true) => Change::Changed,
+      Some(false) | None => Change::Unchanged,
     }
+  }
 
-    let game_tick = Tick::At(*next_tick);
-    (change, min(field_tick, game_tick))
+  /// Append a character to the name being entered for a new high
+  /// score.
+  pub(crate) fn on_name_char(&mut self, c: u8) -> Change {
+    self.update_scoreboard(|scoreboard| scoreboard.on_char(c))
   }
 
-  /// Update the view after the containing window or contained logical
-  /// dimensions have changed.
-  pub fn update_view(&mut self, phys_w: Option<NonZeroU32>, phys_h: Option<NonZeroU32>) {
-    self
-      .camera
-      .update_view(phys_w, phys_h, self.inner.width(), self.inner.height())
+  /// Remove the last character of the name being entered for a new
+  /// high score.
+  pub(crate) fn on_name_erase(&mut self) -> Change {
+    self.update_scoreboard(Scoreboard::on_erase)
   }
 
-  /// Restart the game.
-  pub fn restart(&mut self) -> Change {
-    let change = self.inner.score.reset();
+  /// Confirm the name entered for a new high score.
+  pub(crate) fn on_name_confirm(&mut self) -> Change {
+    self.update_scoreboard(Scoreboard::on_confirm)
+  }
 
-    let () = if self.inner.field.reset() {
-      if self.inner.ai.is_some() {
-        self.inner.ai = Self::create_ai(&self.inner.field, &self.inner.preview);
-      }
-      self.inner.state = State::Running {
-        next_tick: Self::next_tick(Instant::now(), self.inner.score.level()),
-      };
-    } else {
-      self.set_game_over()
-    };
+  /// Retrieve the most recently simulated frame.
+  #[inline]
+  pub(crate) fn frame(&self) -> Frame {
+    self.engine.frame()
+  }
 
-    change
+  /// Inquire whether the game is currently paused.
+  #[inline]
+  pub(crate) fn is_paused(&self) -> bool {
+    self.engine.is_paused()
   }
 
-  /// End the current game, setting `state` to [`State::Over`].
-  fn set_game_over(&mut self) {
-    debug_assert!(!matches!(self.inner.state, State::Over));
-    self.inner.state = State::Over;
+  /// Inquire whether the game is over.
+  #[cfg_attr(target_arch = "wasm32", expect(dead_code))]
+  #[inline]
+  pub(crate) fn is_over(&self) -> bool {
+    self.engine.is_over()
+  }
 
-    println!(
-      "{} points @ level {}; total {} lines cleared (game over)",
-      self.inner.score.points(),
-      self.inner.score.level(),
-      self.inner.score.lines()
-    );
+  /// Retrieve the outcome of the game, if it is over.
+  #[cfg_attr(target_arch = "wasm32", expect(dead_code))]
+  #[inline]
+  pub(crate) fn outcome(&self) -> Option<Outcome> {
+    self.engine.outcome()
   }
 
-  /// Pause or unpause the game.
+  /// Retrieve the current level.
+  #[cfg_attr(target_arch = "wasm32", expect(dead_code))]
   #[inline]
-  pub(crate) fn pause(&mut self, pause: bool) {
-    match &self.inner.state {
-      State::Running { .. } if pause => {
-        // Note that strictly speaking the field could change state here
-        // (if it was "clearing") and, conceptually, we should cause a
-        // redraw (i.e., by returning `Change::Changed`. Practically,
-        // though, we do *not* want to do that, because doing so could
-        // eagerly remove cleared lines and it just makes more sense to
-        // leave them there for the duration of the pause.
-        let () = self.inner.field.on_pause();
-        self.inner.state = State::Paused {
-          blur: Blur::new(&self.gl_state).ok(),
-        };
-      },
-      State::Paused { .. } if !pause => {
-        self.inner.state = State::Running {
-          next_tick: Self::next_tick(Instant::now(), self.inner.score.level()),
-        }
-      },
-      State::Over | State::Paused { .. } | State::Running { .. } => (),
-    }
+  pub(crate) fn level(&self) -> u16 {
+    self.engine.level()
   }
 
-  /// Inquire whether the game is currently paused.
-  ///
-  /// Note that this method truly only returns `true` when the game is
-  /// paused, and not just when it isn't running (e.g., because it is
-  /// over).
+  /// Retrieve the number of points earned so far.
+  #[cfg_attr(target_arch = "wasm32", expect(dead_code))]
   #[inline]
-  pub(crate) fn is_paused(&self) -> bool {
-    matches!(&self.inner.state, State::Paused { .. })
+  pub(crate) fn points(&self) -> u64 {
+    self.engine.points()
   }
 
   /// Enable or disable auto-playing of the game.
-  pub(crate) fn auto_play(&mut self, auto_play: bool) {
-    if auto_play {
-      if self.inner.ai.is_none() {
-        self.inner.ai = Self::create_ai(&self.inner.field, &self.inner.preview);
-      }
-    } else {
-      self.inner.ai = None;
-    }
+  #[inline]
+  pub(crate) fn auto_play(&mut self, auto_play: bool) -> Result<()> {
+    self.engine.auto_play(auto_play)
   }
 
   /// Check whether the game is currently controlled by an auto-playing
   /// AI.
   #[inline]
   pub(crate) fn is_auto_playing(&self) -> bool {
-    self.inner.ai.is_some()
+    self.engine.is_auto_playing()
+  }
+
+  /// Set the weights the AI uses for evaluating its options.
+  #[inline]
+  pub(crate) fn set_ai_weights(&mut self, weights: AiWeights) {
+    self.engine.set_ai_weights(weights)
   }
 
-  fn handle_merged_lines(score: &mut Score, lines: u16) -> Change {
-    let level = score.level();
-    let change = score.add(lines);
-    let new_level = score.level();
+  /// Retrieve the weights the AI uses for evaluating its options.
+  #[cfg_attr(target_arch = "wasm32", expect(dead_code))]
+  #[inline]
+  pub(crate) fn ai_weights(&self) -> &AiWeights {
+    self.engine.ai_weights()
+  }
 
-    // While we actually render the score in real-time, we also print to
-    // stdout on level up, just to have a history in a slightly more
-    // persistent location (still visible after the main window got
-    // closed).
-    if new_level != level {
-      println!("{} points @ level {}", score.points(), new_level);
-    }
-    change
+  /// Enable or disable showing of the placement the AI suggests for
+  /// the active stone.
+  #[inline]
+  pub(crate) fn show_hint(&mut self, show: bool) {
+    self.engine.show_hint(show)
   }
 
-  /// Check whether the game in its current state accepts and reacts to
-  /// input.
-  ///
-  /// It won't accept input if it's currently paused or if the AI is
-  /// playing.
+  /// Check whether the placement the AI suggests for the active stone
+  /// is being shown.
   #[inline]
-  fn accepts_input(&self) -> bool {
-    matches!(self.inner.state, State::Running { .. }) && !self.is_auto_playing()
+  pub(crate) fn is_showing_hint(&self) -> bool {
+    self.engine.is_showing_hint()
   }
 
   #[inline]
   pub(crate) fn on_move_down(&mut self) -> Change {
-    if self.accepts_input() {
-      let (mut change, result) = self.inner.field.move_stone_down();
-      match result {
-        MoveResult::None | MoveResult::Moved => (),
-        MoveResult::Merged(lines) => {
-          change |= Self::handle_merged_lines(&mut self.inner.score, lines);
-        },
-        MoveResult::Conflict => {
-          let () = self.set_game_over();
-        },
-      }
-
-      change
-    } else {
-      Change::Unchanged
-    }
+    self.engine.on_move_down()
   }
 
   #[inline]
   pub(crate) fn on_drop(&mut self) -> Change {
-    if self.accepts_input() {
-      let (mut change, result) = self.inner.field.drop_stone();
-      match result {
-        MoveResult::None | MoveResult::Moved => (),
-        MoveResult::Merged(lines) => {
-          change |= Self::handle_merged_lines(&mut self.inner.score, lines);
-        },
-        MoveResult::Conflict => {
-          let () = self.set_game_over();
-        },
-      }
-
-      change
-    } else {
-      Change::Unchanged
-    }
+    self.engine.on_drop()
   }
 
   #[inline]
   pub(crate) fn on_move_left(&mut self) -> Change {
-    if self.accepts_input() {
-      self.inner.field.move_stone_left()
-    } else {
-      Change::Unchanged
-    }
+    self.engine.on_move_left()
   }
 
   #[inline]
   pub(crate) fn on_move_right(&mut self) -> Change {
-    if self.accepts_input() {
-      self.inner.field.move_stone_right()
-    } else {
-      Change::Unchanged
-    }
+    self.engine.on_move_right()
   }
 
   #[inline]
   pub(crate) fn on_rotate_left(&mut self) -> Change {
-    if self.accepts_input() {
-      self.inner.field.rotate_stone_left()
-    } else {
-      Change::Unchanged
-    }
+    self.engine.on_rotate_left()
   }
 
   #[inline]
   pub(crate) fn on_rotate_right(&mut self) -> Change {
-    if self.accepts_input() {
-      self.inner.field.rotate_stone_right()
-    } else {
-      Change::Unchanged
-    }
+    self.engine.on_rotate_right()
+  }
+
+  #[inline]
+  pub(crate) fn on_hold(&mut self) -> Change {
+    self.engine.on_hold()
   }
 
   /// Render the game and its components.
   pub fn render(&mut self) {
-    let clear_color = SCREEN_CLEAR_COLOR.select(self.inner.color_mode);
+    let clear_color = SCREEN_CLEAR_COLOR.select(self.color_mode);
+    let scene = Scene {
+      engine: &self.engine,
+      textures: &self.textures,
+      scoreboard: self.scoreboard.as_ref(),
+      resume_prompt: self.resume_prompt.as_ref(),
+      color_mode: self.color_mode,
+    };
 
-    match &self.inner.state {
-      State::Paused { blur: Some(blur) } => {
+    match &self.blur {
+      Some(blur) => {
         let gl_state = self.gl_state.object();
         let () = blur.render_scene(gl_state, clear_color, |object| {
           let () = self.camera.render_scene(object, |object| {
             let renderer = self.renderer.on_pre_render(object);
-            let () = self.inner.render(&renderer);
+            let () = scene.render(&renderer);
           });
         });
 
@@ -594,7 +623,7 @@ impl Game {
         let () = self.camera.set_viewport(gl_state);
         let () = blur.render_blur(gl_state);
       },
-      State::Running { .. } | State::Paused { blur: None } | State::Over => {
+      None => {
         let gl_state = self.gl_state.object();
         let () = self.camera.set_viewport(gl_state);
         let () = self.camera.render_scene(gl_state, |object| {
@@ -602,39 +631,36 @@ impl Game {
           let () = object.set_clear_color(r, g, b, 1.0);
           let () = object.clear(sys::ClearMask::ColorBuffer);
           let renderer = self.renderer.on_pre_render(object);
-          let () = self.inner.render(&renderer);
+          let () = scene.render(&renderer);
         });
       },
     }
   }
 
+  /// Create a snapshot of the game's state, from which it can be
+  /// resumed later on.
+  #[inline]
+  pub fn snapshot(&self) -> Snapshot {
+    self.engine.snapshot()
+  }
+
   /// Convert the game (back) into a [`Config`].
   pub fn into_config(self) -> Config {
     Config {
-      start_level: self.inner.score.start_level(),
-      lines_for_level: self.inner.score.lines_for_level(),
-      field_width: self.inner.field.width(),
-      field_height: self.inner.field.height(),
-      preview_stone_count: self.inner.preview.with_stones(|stones| stones.count()) as _,
-      enable_ai: self.inner.ai.is_some(),
-      enable_dark_mode: matches!(self.inner.color_mode, ColorMode::Dark),
+      enable_dark_mode: matches!(self.color_mode, ColorMode::Dark),
+      ..self.engine.into_config()
     }
   }
 
   /// Toggle the color mode (light/dark) in use.
   pub(crate) fn toggle_color_mode(&mut self) {
-    let () = self.inner.color_mode.toggle();
+    let () = self.color_mode.toggle();
   }
 
   #[cfg(feature = "debug")]
+  #[inline]
   pub(crate) fn dump_state(&self) {
-    if let Some((stone, field)) = self.inner.field.to_ai_data() {
-      if let Some(ai) = self.inner.ai.as_ref() {
-        println!("{ai:#?}");
-      }
-      println!("{stone:?}");
-      println!("{field:?}");
-    }
+    self.engine.dump_state()
   }
 }
 
@@ -655,8 +681,6 @@ mod tests {
 
 
   /// Benchmark the performance of the rendering path.
-  // TODO: It would be good to use `with_opengl_context` in one form or
-  //       another instead of repeating a lot of what it does.
   #[allow(deprecated)]
   #[bench]
   fn bench_render(b: &mut Bencher) {
@@ -668,7 +692,7 @@ mod tests {
     let context = window.render_context().gl_context();
     let (phys_w, phys_h) = window.size();
     let config = Config::default();
-    let mut game = Game::with_config(phys_w, phys_h, &config, context).unwrap();
+    let mut game = Game::with_config(phys_w, phys_h, &config, Some(42), context).unwrap();
 
     let () = b.iter(|| {
       let () = game.render();
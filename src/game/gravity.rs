@@ -0,0 +1,342 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::str::FromStr;
+
+use anyhow::Error;
+use anyhow::Result;
+use anyhow::bail;
+use anyhow::ensure;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Frame;
+use crate::clock::FRAMES_PER_SEC;
+
+
+/// The drop speed, in units per frame, starting at which stones are
+/// moved to their landing position instantly ("20G").
+const INSTANT_UNITS_PER_FRAME: f64 = 20.0;
+/// The highest level the guideline formula is evaluated for. Past it
+/// the formula breaks down, but gravity is instant by then anyway.
+const GUIDELINE_MAX_LEVEL: u16 = 20;
+
+
+/// The curve mapping the current level to the speed at which stones
+/// fall.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gravity {
+  /// The speed increases linearly, by 0.2 units per second with every
+  /// level.
+  #[default]
+  Classic,
+  /// The speed follows the formula given by the Tetris guideline,
+  /// reaching instant gravity at level 20.
+  Guideline,
+  /// The speed for each level is looked up from a table, with the
+  /// last entry being used for all subsequent levels.
+  Table,
+  /// The speed follows a custom curve of the form `base + growth *
+  /// level^exponent`.
+  Curve,
+}
+
+impl Gravity {
+  /// Retrieve the textual representation of the gravity curve, as used
+  /// in configuration files.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Classic => "classic",
+      Self::Guideline => "guideline",
+      Self::Table => "table",
+      Self::Curve => "curve",
+    }
+  }
+}
+
+impl FromStr for Gravity {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "classic" => Ok(Self::Classic),
+      "guideline" => Ok(Self::Guideline),
+      "table" => Ok(Self::Table),
+      "curve" => Ok(Self::Curve),
+      _ => bail!("unsupported gravity `{s}`"),
+    }
+  }
+}
+
+
+/// The parameters of a custom gravity curve.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct Curve {
+  /// The speed at level zero, in units per second.
+  pub base: f64,
+  /// The factor by which the level-dependent term is scaled.
+  pub growth: f64,
+  /// The exponent the level is raised to.
+  pub exponent: f64,
+}
+
+
+/// A type determining the speed at which stones fall.
+#[derive(Clone, Debug)]
+pub(super) struct Speed {
+  /// The gravity curve in use.
+  gravity: Gravity,
+  /// The speeds used by [`Gravity::Table`], in units per second.
+  table: Vec<f64>,
+  /// The parameters used by [`Gravity::Curve`].
+  curve: Curve,
+}
+
+impl Speed {
+  pub fn new(gravity: Gravity, table: Vec<f64>, curve: Curve) -> Result<Self> {
+    if gravity == Gravity::Table {
+      ensure!(!table.is_empty(), "gravity table must not be empty");
+    }
+    ensure!(
+      table.iter().all(|speed| *speed > 0.0),
+      "gravity table entries must be positive"
+    );
+
+    if gravity == Gravity::Curve {
+      let Curve {
+        base,
+        growth,
+        exponent,
+      } = curve;
+      // With a positive base, a non-negative growth, and a non-negative
+      // exponent, the curve yields positive speeds for all levels.
+      ensure!(
+        base > 0.0 && base.is_finite(),
+        "gravity curve base must be positive"
+      );
+      ensure!(
+        growth >= 0.0 && growth.is_finite(),
+        "gravity curve growth must not be negative"
+      );
+      ensure!(
+        exponent >= 0.0 && exponent.is_finite(),
+        "gravity curve exponent must not be negative"
+      );
+    }
+
+    let slf = Self {
+      gravity,
+      table,
+      curve,
+    };
+    Ok(slf)
+  }
+
+  /// Calculate the drop speed at the given level, in units per second.
+  fn units_per_sec(&self, level: u16) -> f64 {
+    match self.gravity {
+      Gravity::Classic => 1.0 + 0.2 * f64::from(level),
+      Gravity::Guideline => {
+        let level = level.clamp(1, GUIDELINE_MAX_LEVEL);
+        let level = f64::from(level - 1);
+        let secs_per_unit = (0.8 - level * 0.007).powf(level);
+        1.0 / secs_per_unit
+      },
+      Gravity::Table => {
+        let index = usize::from(level.saturating_sub(1)).min(self.table.len() - 1);
+        self.table[index]
+      },
+      Gravity::Curve => {
+        let Curve {
+          base,
+          growth,
+          exponent,
+        } = self.curve;
+        base + growth * f64::from(level).powf(exponent)
+      },
+    }
+  }
+
+  /// Calculate the drop speed at the given level, in units per frame.
+  #[inline]
+  fn units_per_frame(&self, level: u16) -> f64 {
+    self.units_per_sec(level) / FRAMES_PER_SEC as f64
+  }
+
+  /// Check whether stones move to their landing position instantly at
+  /// the given level.
+  #[inline]
+  pub fn is_instant(&self, level: u16) -> bool {
+    self.units_per_frame(level) >= INSTANT_UNITS_PER_FRAME
+  }
+
+  /// Calculate the frame of the next tick, given the current one and
+  /// the fraction of a unit the stone has fallen already.
+  ///
+  /// Ticks happen at most once per frame; higher speeds are achieved
+  /// by moving stones by multiple units per tick. Alongside the frame,
+  /// the distance fallen by then is reported, in units. Its integral
+  /// part is the number of units to move the stone by at that tick
+  /// while the remainder is carried over to the next one, so that the
+  /// average rate of fall matches the configured speed.
+  pub fn next_tick(&self, current_tick: Frame, fall: f64, level: u16) -> (Frame, f64) {
+    let units_per_frame = self.units_per_frame(level);
+    // Allow for a bit of rounding error, lest we miss a unit by a frame.
+    let frames = ((1.0 - fall) / units_per_frame - 1e-9).ceil().max(1.0);
+    let fall = fall + frames * units_per_frame;
+    (current_tick + frames as Frame, fall)
+  }
+
+  /// Retrieve the gravity curve in use.
+  #[inline]
+  pub fn gravity(&self) -> Gravity {
+    self.gravity
+  }
+
+  /// Retrieve the speed table.
+  #[inline]
+  pub fn table(&self) -> &[f64] {
+    &self.table
+  }
+
+  /// Retrieve the parameters of the custom curve.
+  #[inline]
+  pub fn curve(&self) -> Curve {
+    self.curve
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// The curve parameters matching [`Gravity::Classic`].
+  const CLASSIC: Curve = Curve {
+    base: 1.0,
+    growth: 0.2,
+    exponent: 1.0,
+  };
+
+
+  /// Check that we can convert a [`Gravity`] to a string and back.
+  #[test]
+  fn gravity_str_conversion() {
+    for gravity in [
+      Gravity::Classic,
+      Gravity::Guideline,
+      Gravity::Table,
+      Gravity::Curve,
+    ] {
+      assert_eq!(Gravity::from_str(gravity.as_str()).unwrap(), gravity);
+    }
+
+    assert!(Gravity::from_str("foobar").is_err());
+  }
+
+  /// Make sure that invalid gravity settings are rejected.
+  #[test]
+  fn speed_validation() {
+    assert!(Speed::new(Gravity::Table, Vec::new(), CLASSIC).is_err());
+    assert!(Speed::new(Gravity::Table, vec![1.0, 0.0], CLASSIC).is_err());
+    assert!(Speed::new(Gravity::Classic, Vec::new(), CLASSIC).is_ok());
+
+    let curve = Curve {
+      base: 1.0,
+      growth: -1.0,
+      exponent: 1.0,
+    };
+    assert!(Speed::new(Gravity::Curve, Vec::new(), curve).is_err());
+    assert!(Speed::new(Gravity::Classic, Vec::new(), curve).is_ok());
+
+    for (base, growth, exponent) in [
+      (0.0, 1.0, 1.0),
+      (f64::NAN, 1.0, 1.0),
+      (1.0, f64::INFINITY, 1.0),
+      (1.0, 1.0, -1.0),
+      (1.0, 1.0, f64::NAN),
+    ] {
+      let curve = Curve {
+        base,
+        growth,
+        exponent,
+      };
+      assert!(
+        Speed::new(Gravity::Curve, Vec::new(), curve).is_err(),
+        "{curve:?}"
+      );
+    }
+  }
+
+  /// Check that the various gravity curves yield the expected speeds.
+  #[test]
+  fn speeds() {
+    let speed = Speed::new(Gravity::Classic, Vec::new(), CLASSIC).unwrap();
+    assert_eq!(speed.units_per_sec(10), 3.0);
+    assert!(!speed.is_instant(200));
+
+    let curve = Speed::new(Gravity::Curve, Vec::new(), CLASSIC).unwrap();
+    for level in [0, 1, 10, 200] {
+      assert_eq!(curve.units_per_sec(level), speed.units_per_sec(level));
+    }
+
+    let speed = Speed::new(Gravity::Table, vec![1.0, 2.0, 1200.0], CLASSIC).unwrap();
+    assert_eq!(speed.units_per_sec(0), 1.0);
+    assert_eq!(speed.units_per_sec(1), 1.0);
+    assert_eq!(speed.units_per_sec(2), 2.0);
+    assert!(!speed.is_instant(2));
+    assert!(speed.is_instant(3));
+    assert!(speed.is_instant(u16::MAX));
+
+    let speed = Speed::new(Gravity::Table, vec![1.0, 1.5, 30.0, 180.0], CLASSIC).unwrap();
+    for (level, frame, units) in [(1, 160, 1.0), (2, 140, 1.0), (3, 102, 1.0), (4, 101, 3.0)] {
+      let (next, fall) = speed.next_tick(100, 0.0, level);
+      assert_eq!(next, frame);
+      assert!((fall - units).abs() < 1e-9, "{fall}");
+    }
+
+    let speed = Speed::new(Gravity::Guideline, Vec::new(), CLASSIC).unwrap();
+    assert_eq!(speed.units_per_sec(1), 1.0);
+    assert!(speed.units_per_sec(2) > speed.units_per_sec(1));
+    assert!(!speed.is_instant(15));
+    assert!(speed.is_instant(20));
+    assert!(speed.is_instant(200));
+  }
+
+  /// Check that stones fall at the configured speed on average, even
+  /// if it does not correspond to an integral number of frames or
+  /// units per frame.
+  #[test]
+  fn average_fall_rate() {
+    fn fall_rate(speed: &Speed, level: u16) -> f64 {
+      let frames = 100 * FRAMES_PER_SEC;
+      let mut now = 0;
+      let mut fall = 0.0;
+      let mut units = 0;
+
+      loop {
+        let (next, next_fall) = speed.next_tick(now, fall, level);
+        if next > frames {
+          break
+        }
+        now = next;
+        units += next_fall.floor() as u64;
+        fall = next_fall.fract();
+      }
+      units as f64 / 100.0
+    }
+
+    // 0.4 and 90 units per second, i.e., 1/150 and 1.5 units per frame.
+    let speed = Speed::new(Gravity::Table, vec![0.4, 90.0], CLASSIC).unwrap();
+    assert_eq!(fall_rate(&speed, 1), 0.4);
+    assert_eq!(fall_rate(&speed, 2), 90.0);
+
+    let speed = Speed::new(Gravity::Guideline, Vec::new(), CLASSIC).unwrap();
+    let expected = speed.units_per_sec(10);
+    assert!(!speed.is_instant(10));
+    assert!((fall_rate(&speed, 10) - expected).abs() < 0.02);
+  }
+}
@@ -55,6 +55,21 @@ impl<T> Matrix<T> {
     let () = self.matrix.get_mut(src_range).unwrap().fill(T::default());
   }
 
+  /// Insert an empty line at the given index, moving it and all lines
+  /// above one line up. The very top line is discarded.
+  pub(super) fn insert_line(&mut self, line: i16)
+  where
+    T: Copy + Default,
+  {
+    let src_index = self.calculate_index((0, line));
+    let dst_index = self.calculate_index((0, line + 1));
+    let src_range = src_index..self.matrix.len() - self.width as usize;
+    let () = self.matrix.copy_within(src_range, dst_index);
+
+    let src_range = src_index..src_index + self.width as usize;
+    let () = self.matrix.get_mut(src_range).unwrap().fill(T::default());
+  }
+
   /// Clear the matrix, removing all elements from it.
   #[inline]
   pub(super) fn clear(&mut self)
@@ -81,19 +96,6 @@ impl<T> Matrix<T> {
     self.matrix[index..index + self.width as usize].iter()
   }
 
-  /// Convert the `Matrix` into one with a different `T`, using the
-  /// provided function, `f`, to to convert individual items.
-  pub fn to_other<U, F>(&self, f: F) -> Matrix<U>
-  where
-    F: Fn(&T) -> U,
-  {
-    Matrix {
-      width: self.width,
-      height: self.height,
-      matrix: self.matrix.iter().map(f).collect(),
-    }
-  }
-
   #[inline]
   pub fn width(&self) -> i16 {
     self.width
@@ -209,4 +211,45 @@ mod tests {
     ];
     assert_eq!(&*matrix.matrix, expected.as_slice());
   }
+
+  /// Make sure that we can insert a line into the matrix.
+  #[test]
+  fn line_insertion() {
+    let mut matrix = Matrix::<Option<usize>>::new(2, 4);
+    let mut x = 0;
+    let () = matrix.matrix.fill_with(|| {
+      x += 1;
+      Some(x)
+    });
+
+    let () = matrix.insert_line(0);
+    // |5,6|
+    // |3,4|
+    // |1,2|
+    // |   |
+    // -----
+    #[rustfmt::skip]
+    let expected = [
+      None, None,
+      Some(1), Some(2),
+      Some(3), Some(4),
+      Some(5), Some(6),
+    ];
+    assert_eq!(&*matrix.matrix, expected.as_slice());
+
+    let () = matrix.insert_line(2);
+    // |3,4|
+    // |   |
+    // |1,2|
+    // |   |
+    // -----
+    #[rustfmt::skip]
+    let expected = [
+      None, None,
+      Some(1), Some(2),
+      None, None,
+      Some(3), Some(4),
+    ];
+    assert_eq!(&*matrix.matrix, expected.as_slice());
+  }
 }
@@ -6,31 +6,67 @@ mod blur;
 mod camera;
 mod config;
 mod data;
+mod difficulty;
+mod engine;
 mod factory;
 mod field;
 mod fieldlike;
 mod game;
+mod gravity;
 mod matrix;
+mod mode;
 mod piece;
 mod preview;
 mod producer;
+mod randomizer;
+mod rotation;
 mod score;
+mod scoreboard;
+mod scoring;
 mod stone;
 mod stonelike;
+mod template;
+mod textures;
 
+use ai::Orientation;
 use blur::Blur;
 use camera::Camera;
 use factory::StoneFactory;
 use field::Field;
 use field::MoveResult;
 use fieldlike::Fieldlike;
+use gravity::Curve;
+use gravity::Speed;
 use matrix::Matrix;
+use mode::Session;
+use mode::write_time;
 use piece::Piece;
 use preview::PreviewStones;
 use producer::StoneProducer;
+use randomizer::Selector;
 use score::Score;
+use scoreboard::Scoreboard;
+use scoring::Clear;
+use scoring::DropKind;
+use scoring::ScoringRules;
+use scoring::Spin;
 use stone::Stone;
 use stonelike::Stonelike;
+use textures::Textures;
 
+pub use ai::Weights as AiWeights;
 pub use config::Config;
+pub use difficulty::Difficulty;
+pub use engine::Engine;
+pub use engine::Outcome;
+pub use engine::SearchStats;
+pub use engine::Snapshot;
 pub use game::Game;
+pub use gravity::Gravity;
+pub use mode::Mode;
+pub use randomizer::Randomizer;
+pub use rotation::RotationSystem;
+pub use scoring::Scoring;
+pub use template::PieceColor;
+pub use template::StoneConfig;
+pub use template::StoneSet;
@@ -0,0 +1,444 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::io::Result as IoResult;
+use std::io::Write;
+use std::mem::MaybeUninit;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::Error;
+use anyhow::bail;
+
+use bufio::Writer as StackWriter;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::ActiveRenderer as Renderer;
+use crate::Color;
+use crate::ColorMode;
+use crate::ColorSet;
+use crate::Font;
+use crate::Frame;
+use crate::Point;
+use crate::Rect;
+use crate::Rng;
+use crate::clock::duration_to_frames;
+use crate::clock::frames_to_duration;
+
+use super::Config;
+use super::Score;
+use super::Textures;
+
+
+/// The font size to use for the results, in game units.
+const FONT_SIZE: i16 = 2;
+/// The resolution at which the timer is displayed.
+const TIMER_RESOLUTION: Duration = Duration::from_millis(100);
+/// The color set used for the background of the results box.
+const RESULTS_BACK_COLOR: ColorSet<Color> = ColorSet::new(Color::white(), Color::black());
+
+
+/// Format a duration as `m:ss.d`.
+pub(super) fn write_time<W>(writer: &mut W, time: Duration) -> IoResult<()>
+where
+  W: Write,
+{
+  let tenths = time.as_millis() / TIMER_RESOLUTION.as_millis();
+  let secs = tenths / 10;
+  write!(writer, "{}:{:02}.{}", secs / 60, secs % 60, tenths % 10)
+}
+
+
+/// The mode a game is played in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+  /// Endless play, until the stones pile up to the top.
+  #[default]
+  Marathon,
+  /// Clear a given number of lines as fast as possible.
+  Sprint,
+  /// Earn as many points as possible within a given time.
+  Ultra,
+  /// Clear all lines of garbage the field starts out with.
+  Dig,
+}
+
+impl Mode {
+  /// Retrieve the textual representation of the mode, as used in
+  /// configuration files.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Marathon => "marathon",
+      Self::Sprint => "sprint",
+      Self::Ultra => "ultra",
+      Self::Dig => "dig",
+    }
+  }
+}
+
+impl FromStr for Mode {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "marathon" => Ok(Self::Marathon),
+      "sprint" => Ok(Self::Sprint),
+      "ultra" => Ok(Self::Ultra),
+      "dig" => Ok(Self::Dig),
+      _ => bail!("unsupported mode `{s}`"),
+    }
+  }
+}
+
+
+/// The serializable state of a [`Session`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(super) struct Snapshot {
+  /// The number of lines of garbage inserted periodically so far.
+  dig_inserted: u32,
+  /// The number of lines of garbage left, as of the last check.
+  dig_left: u16,
+  /// The random number generator used for placing holes in garbage.
+  rng: Rng,
+  /// The number of frames played up to `running_since`.
+  elapsed: Frame,
+  /// The frame at which the game was last started or resumed, if it
+  /// is currently running.
+  #[serde(default)]
+  running_since: Option<Frame>,
+  /// Whether the goal of the mode has been reached.
+  completed: bool,
+}
+
+
+/// A type keeping track of the progress towards the goal of a
+/// [`Mode`].
+#[derive(Debug)]
+pub(super) struct Session {
+  /// The mode being played.
+  mode: Mode,
+  /// The number of lines to clear in [`Mode::Sprint`].
+  sprint_lines: u16,
+  /// The time available in [`Mode::Ultra`].
+  ultra_time: Duration,
+  /// The number of lines of garbage to start with in [`Mode::Dig`].
+  dig_rows: u16,
+  /// The interval at which additional garbage is inserted in
+  /// [`Mode::Dig`]. Zero means that no garbage is inserted.
+  dig_interval: Duration,
+  /// The number of lines of garbage inserted periodically so far.
+  dig_inserted: u32,
+  /// The number of lines of garbage left, as of the last check.
+  dig_left: u16,
+  /// The random number generator used for placing holes in garbage.
+  rng: Rng,
+  /// The number of frames played up to `running_since`.
+  elapsed: Frame,
+  /// The frame at which the game was last started or resumed, if it
+  /// is currently running.
+  running_since: Option<Frame>,
+  /// Whether the goal of the mode has been reached.
+  completed: bool,
+  /// The font to use for rendering the results.
+  font: Font,
+}
+
+impl Session {
+  pub fn new(config: &Config, rng: Rng) -> Self {
+    Self {
+      mode: config.mode,
+      sprint_lines: config.sprint_lines,
+      ultra_time: Duration::from_secs(config.ultra_time_secs.into()),
+      dig_rows: config.dig_rows,
+      dig_interval: Duration::from_millis(config.dig_interval_ms.into()),
+      dig_inserted: 0,
+      dig_left: 0,
+      rng,
+      elapsed: 0,
+      running_since: None,
+      completed: false,
+      font: Font::builtin(),
+    }
+  }
+
+  /// Start (or resume) the timer.
+  pub fn start(&mut self, now: Frame) {
+    if self.running_since.is_none() {
+      self.running_since = Some(now);
+    }
+  }
+
+  /// Stop (or pause) the timer.
+  pub fn stop(&mut self, now: Frame) {
+    if let Some(since) = self.running_since.take() {
+      self.elapsed += now - since;
+    }
+  }
+
+  /// Reset the session to its initial state.
+  pub fn reset(&mut self) {
+    self.elapsed = 0;
+    self.running_since = None;
+    self.completed = false;
+    self.dig_inserted = 0;
+  }
+
+  /// Restore the session's state from a snapshot.
+  pub fn restore(&mut self, snapshot: &Snapshot) {
+    let Snapshot {
+      dig_inserted,
+      dig_left,
+      rng,
+      elapsed,
+      running_since,
+      completed,
+    } = snapshot;
+
+    self.dig_inserted = *dig_inserted;
+    self.dig_left = *dig_left;
+    let () = self.rng.assume(rng);
+    self.elapsed = *elapsed;
+    self.running_since = *running_since;
+    self.completed = *completed;
+  }
+
+  /// Create a snapshot of the session's state.
+  pub fn snapshot(&self) -> Snapshot {
+    Snapshot {
+      dig_inserted: self.dig_inserted,
+      dig_left: self.dig_left,
+      rng: self.rng.clone(),
+      elapsed: self.elapsed,
+      running_since: self.running_since,
+      completed: self.completed,
+    }
+  }
+
+  /// Retrieve the number of lines of garbage the field starts out with.
+  pub fn initial_garbage(&self) -> u16 {
+    match self.mode {
+      Mode::Marathon | Mode::Sprint | Mode::Ultra => 0,
+      Mode::Dig => self.dig_rows,
+    }
+  }
+
+  /// Pick the column of the hole in a line of garbage for a field of
+  /// the given width.
+  pub fn garbage_hole(&self, width: i16) -> i16 {
+    (self.rng.rand_u32() % width as u32) as i16
+  }
+
+  /// Check whether another line of garbage is due to be inserted,
+  /// returning the column of its hole if so.
+  pub fn next_garbage(&mut self, now: Frame, width: i16) -> Option<i16> {
+    if self.mode != Mode::Dig || self.dig_interval.is_zero() {
+      return None
+    }
+
+    let due = duration_to_frames(self.dig_interval) * Frame::from(self.dig_inserted + 1);
+    if self.elapsed(now) >= due {
+      self.dig_inserted += 1;
+      Some(self.garbage_hole(width))
+    } else {
+      None
+    }
+  }
+
+  /// Retrieve the number of frames played as of the given one.
+  fn elapsed(&self, now: Frame) -> Frame {
+    self.elapsed
+      + self
+        .running_since
+        .map(|since| now - since)
+        .unwrap_or_default()
+  }
+
+  /// Retrieve the time played as of the given frame.
+  #[inline]
+  pub fn played_time(&self, now: Frame) -> Duration {
+    frames_to_duration(self.elapsed(now))
+  }
+
+  /// Check whether the goal of the mode has been reached.
+  #[inline]
+  pub fn is_completed(&self) -> bool {
+    self.completed
+  }
+
+  /// Retrieve the time to display on the score board, if any.
+  ///
+  /// For [`Mode::Sprint`] that is the elapsed time, while for
+  /// [`Mode::Ultra`] it is the remaining one.
+  pub fn displayed_time(&self, now: Frame) -> Option<Duration> {
+    let elapsed = frames_to_duration(self.elapsed(now));
+    match self.mode {
+      Mode::Marathon => None,
+      Mode::Sprint | Mode::Dig => Some(elapsed),
+      Mode::Ultra => Some(self.ultra_time.saturating_sub(elapsed)),
+    }
+  }
+
+  /// Retrieve the frame at which the displayed time changes next, if
+  /// the timer is running.
+  pub fn next_update(&self, now: Frame) -> Option<Frame> {
+    if self.mode == Mode::Marathon || self.running_since.is_none() {
+      return None
+    }
+
+    let elapsed = self.elapsed(now);
+    let resolution = duration_to_frames(TIMER_RESOLUTION).max(1);
+    let wait = resolution - elapsed % resolution;
+    Some(now + wait)
+  }
+
+  /// Check whether the goal of the mode has been reached, in which case
+  /// the game is over.
+  pub fn check_goal(&mut self, score: &Score, garbage_lines: u16, now: Frame) -> bool {
+    self.dig_left = garbage_lines;
+    self.completed = match self.mode {
+      Mode::Marathon => false,
+      Mode::Sprint => score.lines() >= u32::from(self.sprint_lines),
+      Mode::Ultra => self.elapsed(now) >= duration_to_frames(self.ultra_time),
+      Mode::Dig => garbage_lines == 0,
+    };
+    self.completed
+  }
+
+  /// Render the results of a finished game, centered inside a box of
+  /// the given dimensions.
+  pub fn render_results(
+    &self,
+    renderer: &Renderer,
+    textures: &Textures,
+    color_mode: ColorMode,
+    score: &Score,
+    w: i16,
+    h: i16,
+  ) {
+    let mut buffer = [MaybeUninit::<u8>::uninit(); 64];
+    let mut writer = StackWriter::new(&mut buffer);
+
+    let (title, label): (&[u8], &[u8]) = match (self.mode, self.completed) {
+      (Mode::Marathon, _) => return,
+      (Mode::Sprint | Mode::Dig, true) => {
+        let () = write_time(&mut writer, frames_to_duration(self.elapsed)).unwrap();
+        (b"Finished!", b"Time:")
+      },
+      (Mode::Sprint, false) => {
+        let () = write!(writer, "{}/{}", score.lines(), self.sprint_lines).unwrap();
+        (b"Failed", b"Lines:")
+      },
+      (Mode::Ultra, completed) => {
+        let () = write!(writer, "{}", score.points()).unwrap();
+        let title: &[u8] = if completed { b"Time up!" } else { b"Failed" };
+        (title, b"Points:")
+      },
+      (Mode::Dig, false) => {
+        let () = write!(writer, "{}", self.dig_left).unwrap();
+        (b"Failed", b"Garbage:")
+      },
+    };
+    let value = writer.written();
+
+    let lines = [title, label, value];
+    let box_h = (lines.len() as i16 + 1) * FONT_SIZE;
+    let box_y = (h - box_h) / 2;
+    {
+      let _guard = renderer.set_no_texture();
+      let _guard = renderer.set_color(RESULTS_BACK_COLOR.select(color_mode));
+      let () = renderer.render_rect(Rect::new(0, box_y, w, box_h));
+    }
+
+    let factor = f32::from(FONT_SIZE) / f32::from(self.font.size());
+    let _guard = renderer.set_color(Color::orange());
+    let _guard = renderer.set_texture(&textures.piece);
+
+    for (i, line) in lines.into_iter().enumerate() {
+      let text_w = f32::from(self.font.str_width(line)) * factor;
+      let x = (f32::from(w) - text_w) / 2.0;
+      let y = f32::from(box_y + box_h - FONT_SIZE / 2 - (i as i16 + 1) * FONT_SIZE);
+
+      let () = self.font.render_str(line, |point: Point<i16>| {
+        let () = renderer.render_rect_f32(Rect::new(
+          x + f32::from(point.x) * factor,
+          y + f32::from(point.y) * factor,
+          factor,
+          factor,
+        ));
+      });
+    }
+  }
+
+  /// Retrieve the mode being played.
+  #[inline]
+  pub fn mode(&self) -> Mode {
+    self.mode
+  }
+
+  /// Retrieve the number of lines to clear in [`Mode::Sprint`].
+  #[inline]
+  pub fn sprint_lines(&self) -> u16 {
+    self.sprint_lines
+  }
+
+  /// Retrieve the time available in [`Mode::Ultra`].
+  #[inline]
+  pub fn ultra_time(&self) -> Duration {
+    self.ultra_time
+  }
+
+  /// Retrieve the number of lines of garbage to start with in
+  /// [`Mode::Dig`].
+  #[inline]
+  pub fn dig_rows(&self) -> u16 {
+    self.dig_rows
+  }
+
+  /// Retrieve the interval at which garbage is inserted in
+  /// [`Mode::Dig`].
+  #[inline]
+  pub fn dig_interval(&self) -> Duration {
+    self.dig_interval
+  }
+
+  /// Retrieve the number of lines of garbage left in [`Mode::Dig`].
+  #[inline]
+  pub fn dig_left(&self) -> u16 {
+    self.dig_left
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we can convert a [`Mode`] to a string and back.
+  #[test]
+  fn mode_str_conversion() {
+    for mode in [Mode::Marathon, Mode::Sprint, Mode::Ultra, Mode::Dig] {
+      assert_eq!(Mode::from_str(mode.as_str()).unwrap(), mode);
+    }
+
+    assert!(Mode::from_str("foobar").is_err());
+  }
+
+  /// Make sure that we format times as expected.
+  #[test]
+  fn time_formatting() {
+    let format = |time| {
+      let mut buffer = Vec::new();
+      let () = write_time(&mut buffer, time).unwrap();
+      String::from_utf8(buffer).unwrap()
+    };
+
+    assert_eq!(format(Duration::ZERO), "0:00.0");
+    assert_eq!(format(Duration::from_millis(1_999)), "0:01.9");
+    assert_eq!(format(Duration::from_secs(83)), "1:23.0");
+    assert_eq!(format(Duration::from_secs(3600)), "60:00.0");
+  }
+}
@@ -18,7 +18,8 @@ pub(crate) struct Piece {
 }
 
 impl Piece {
-  /// The set of colors we use for pieces.
+  /// The set of colors we use for pieces. The last one is reserved for
+  /// garbage.
   pub(super) const COLORS: &'static [ColorSet] = &[
     ColorSet::new(Color::red(), Color::red()),
     ColorSet::new(Color::green(), Color::green()),
@@ -27,13 +28,47 @@ impl Piece {
     ColorSet::new(Color::blue(), Color::blue()),
     ColorSet::new(Color::cyan(), Color::cyan()),
     ColorSet::new(Color::gray(), Color::orange()),
+    ColorSet::new(
+      Color {
+        r: 0x90,
+        g: 0x90,
+        b: 0x90,
+        a: u8::MAX,
+      },
+      Color {
+        r: 0x70,
+        g: 0x70,
+        b: 0x70,
+        a: u8::MAX,
+      },
+    ),
   ];
+  /// The index of the color used for garbage pieces.
+  pub(super) const GARBAGE_COLOR_IDX: u8 = (Self::COLORS.len() - 1) as u8;
 
   pub fn new(color_idx: u8) -> Self {
     debug_assert!(usize::from(color_idx) < Self::COLORS.len());
     Self { color_idx }
   }
 
+  /// Create a garbage piece.
+  #[inline]
+  pub fn garbage() -> Self {
+    Self::new(Self::GARBAGE_COLOR_IDX)
+  }
+
+  /// Retrieve the index of the color that the piece has.
+  #[inline]
+  pub fn color_idx(&self) -> u8 {
+    self.color_idx
+  }
+
+  /// Check whether the piece is a garbage piece.
+  #[inline]
+  pub fn is_garbage(&self) -> bool {
+    self.color_idx == Self::GARBAGE_COLOR_IDX
+  }
+
   /// # Notes
   /// This method assumes that the piece texture to use is already
   /// bound.
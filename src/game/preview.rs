@@ -7,24 +7,44 @@ use std::iter;
 use std::mem::replace;
 use std::slice;
 
+use anyhow::Result;
+use anyhow::ensure;
+
+use serde::Deserialize;
+use serde::Serialize;
+
 use crate::ActiveRenderer as Renderer;
 use crate::ColorMode;
 use crate::Point;
 
 use super::Stone;
+use super::StoneFactory;
 use super::StoneProducer;
 use super::Stonelike as _;
+use super::Textures;
+use super::randomizer::Snapshot as SelectorSnapshot;
+use super::stone::Snapshot as StoneSnapshot;
 
 
 /// The space between each stone in pieces.
 const SPACE: i16 = 1;
 
 
+/// The serializable state of [`PreviewStones`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(super) struct Snapshot {
+  /// The upcoming stones, in the order in which they will be yielded.
+  stones: Vec<StoneSnapshot>,
+  /// The state of the producer of new stones.
+  producer: SelectorSnapshot,
+}
+
+
 /// A type used for displaying a preview of upcoming stones.
 #[derive(Debug)]
 pub(super) struct PreviewStones {
   /// The producer we use for creating new stones.
-  producer: Box<dyn StoneProducer>,
+  producer: StoneFactory,
   /// The upcoming stones.
   stones: RefCell<Box<[Stone]>>,
   /// The index of the next stone to yield.
@@ -33,7 +53,7 @@ pub(super) struct PreviewStones {
 
 impl PreviewStones {
   /// Create a new `PreviewStones` object displaying `count` stones.
-  pub fn new(count: u8, producer: Box<dyn StoneProducer>) -> Self {
+  pub fn new(count: u8, producer: StoneFactory) -> Self {
     let stones = (0..count)
       .map(|_| producer.create_stone())
       .collect::<Vec<_>>()
@@ -49,6 +69,36 @@ impl PreviewStones {
     slf
   }
 
+  /// Restore the preview stones from a snapshot of their state.
+  pub fn restore(&self, snapshot: &Snapshot) -> Result<()> {
+    let Snapshot { stones, producer } = snapshot;
+
+    let count = self.stones.borrow().len();
+    ensure!(
+      stones.len() == count,
+      "expected {count} preview stones but found {}",
+      stones.len()
+    );
+
+    let stones = stones
+      .iter()
+      .map(Stone::from_snapshot)
+      .collect::<Result<Box<[_]>>>()?;
+    let () = self.producer.restore(producer)?;
+    *self.stones.borrow_mut() = stones;
+    let () = self.index.set(0);
+    let () = self.reposition_stones();
+    Ok(())
+  }
+
+  /// Create a snapshot of the state of the preview stones.
+  pub fn snapshot(&self) -> Snapshot {
+    Snapshot {
+      stones: self.with_stones(|stones| stones.map(Stone::snapshot).collect()),
+      producer: self.producer.snapshot(),
+    }
+  }
+
   /// Replace the currently "active" stone with a new one and adjust the
   /// "active" index by one to make this new stone the last one.
   fn add_new_stone(&self) -> Stone {
@@ -79,9 +129,9 @@ impl PreviewStones {
   }
 
   /// Render the object.
-  pub fn render(&self, renderer: &Renderer, color_mode: ColorMode) {
+  pub fn render(&self, renderer: &Renderer, textures: &Textures, color_mode: ColorMode) {
     for stone in self.stones.borrow().iter() {
-      let () = stone.render(renderer, color_mode);
+      let () = stone.render(renderer, textures, color_mode);
     }
   }
 
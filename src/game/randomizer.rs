@@ -0,0 +1,244 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use anyhow::Error;
+use anyhow::Result;
+use anyhow::bail;
+use anyhow::ensure;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Rng;
+
+
+/// The number of previously dealt stones remembered by the
+/// [`Randomizer::History`] strategy.
+const HISTORY_LEN: usize = 4;
+/// The number of times the [`Randomizer::History`] strategy rolls for a
+/// stone not in its history before accepting whatever it got.
+const HISTORY_ROLLS: usize = 4;
+
+
+/// The strategy used for selecting the next stone to create.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Randomizer {
+  /// Pick every stone uniformly at random.
+  #[default]
+  Uniform,
+  /// Put one stone of each type into a "bag", shuffle it, and deal
+  /// the stones out in order, refilling the bag once it is empty.
+  Bag,
+  /// Pick stones at random, but reroll a couple of times if the result
+  /// is one of the most recently dealt stones (as done by TGM).
+  History,
+}
+
+impl Randomizer {
+  /// Retrieve the textual representation of the randomizer, as used in
+  /// configuration files.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Uniform => "uniform",
+      Self::Bag => "bag",
+      Self::History => "history",
+    }
+  }
+}
+
+impl FromStr for Randomizer {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "uniform" => Ok(Self::Uniform),
+      "bag" => Ok(Self::Bag),
+      "history" => Ok(Self::History),
+      _ => bail!("unsupported randomizer `{s}`"),
+    }
+  }
+}
+
+
+/// The mutable state of a [`Selector`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct State {
+  /// The not-yet-dealt indices of the current bag.
+  #[serde(default)]
+  bag: Vec<usize>,
+  /// The most recently dealt indices, the latest one last.
+  #[serde(default)]
+  history: VecDeque<usize>,
+}
+
+
+/// The serializable state of a [`Selector`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(super) struct Snapshot {
+  /// The state of the random number generator.
+  rng: Rng,
+  /// Strategy dependent state.
+  #[serde(flatten)]
+  state: State,
+}
+
+
+/// A type selecting indices of stone templates according to a
+/// [`Randomizer`] strategy.
+#[derive(Debug)]
+pub(super) struct Selector {
+  /// The strategy in use.
+  randomizer: Randomizer,
+  /// The number of indices to pick from.
+  count: usize,
+  /// The random number generator we use.
+  rng: Rng,
+  /// Strategy dependent state.
+  state: RefCell<State>,
+}
+
+impl Selector {
+  pub fn new(randomizer: Randomizer, count: usize, rng: Rng) -> Self {
+    assert!(count > 0, "no indices to select from");
+
+    Self {
+      randomizer,
+      count,
+      rng,
+      state: RefCell::default(),
+    }
+  }
+
+  /// Restore the selector's state from a snapshot.
+  pub fn restore(&self, snapshot: &Snapshot) -> Result<()> {
+    let Snapshot { rng, state } = snapshot;
+
+    ensure!(
+      state
+        .bag
+        .iter()
+        .chain(state.history.iter())
+        .all(|index| *index < self.count),
+      "stone selector state references unknown stones"
+    );
+
+    let () = self.rng.assume(rng);
+    *self.state.borrow_mut() = state.clone();
+    Ok(())
+  }
+
+  /// Create a snapshot of the selector's state.
+  pub fn snapshot(&self) -> Snapshot {
+    Snapshot {
+      rng: self.rng.clone(),
+      state: self.state.borrow().clone(),
+    }
+  }
+
+  /// Pick a random index in the range `[0, count)`.
+  #[inline]
+  fn rand_index(&self) -> usize {
+    self.rng.rand_u32() as usize % self.count
+  }
+
+  /// Select the next index.
+  pub fn next(&self) -> usize {
+    match self.randomizer {
+      Randomizer::Uniform => self.rand_index(),
+      Randomizer::Bag => {
+        let mut state = self.state.borrow_mut();
+        if state.bag.is_empty() {
+          // Refill the bag with one of each index and shuffle it. The
+          // initial order is irrelevant for the distribution, but it
+          // determines the sequences dealt for a given seed, which
+          // recorded replays rely on.
+          let () = state.bag.extend((0..self.count).rev());
+          // Fisher-Yates shuffle.
+          for i in (1..self.count).rev() {
+            let j = self.rng.rand_u32() as usize % (i + 1);
+            let () = state.bag.swap(i, j);
+          }
+        }
+        // SANITY: The bag has just been refilled if it was empty.
+        state.bag.pop().unwrap()
+      },
+      Randomizer::History => {
+        let mut state = self.state.borrow_mut();
+        let mut index = self.rand_index();
+        for _ in 1..HISTORY_ROLLS {
+          if !state.history.contains(&index) {
+            break
+          }
+          index = self.rand_index();
+        }
+
+        if state.history.len() >= HISTORY_LEN {
+          let _oldest = state.history.pop_front();
+        }
+        let () = state.history.push_back(index);
+        index
+      },
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we can convert a [`Randomizer`] to a string and back.
+  #[test]
+  fn randomizer_str_conversion() {
+    for randomizer in [Randomizer::Uniform, Randomizer::Bag, Randomizer::History] {
+      assert_eq!(
+        Randomizer::from_str(randomizer.as_str()).unwrap(),
+        randomizer
+      );
+    }
+
+    assert!(Randomizer::from_str("foobar").is_err());
+  }
+
+  /// Make sure that the uniform randomizer stays within bounds.
+  #[test]
+  fn uniform_selection() {
+    let selector = Selector::new(Randomizer::Uniform, 7, Rng::with_seed(1337));
+    for _ in 0..100 {
+      assert!(selector.next() < 7);
+    }
+  }
+
+  /// Check that the bag randomizer deals out every index exactly once
+  /// per bag.
+  #[test]
+  fn bag_selection() {
+    let selector = Selector::new(Randomizer::Bag, 7, Rng::with_seed(42));
+    for _ in 0..10 {
+      let mut bag = (0..7).map(|_| selector.next()).collect::<Vec<_>>();
+      let () = bag.sort();
+      assert_eq!(bag, (0..7).collect::<Vec<_>>());
+    }
+  }
+
+  /// Check that the history randomizer hands out repeated indices
+  /// considerably less often than the uniform one.
+  #[test]
+  fn history_selection() {
+    fn repeats(randomizer: Randomizer) -> usize {
+      let selector = Selector::new(randomizer, 7, Rng::with_seed(3));
+      let indices = (0..1000).map(|_| selector.next()).collect::<Vec<_>>();
+      indices.windows(2).filter(|w| w[0] == w[1]).count()
+    }
+
+    let uniform = repeats(Randomizer::Uniform);
+    let history = repeats(Randomizer::History);
+    assert!(history * 4 < uniform, "{history} vs. {uniform}");
+  }
+}
@@ -0,0 +1,487 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cmp::max;
+use std::mem::swap;
+use std::str::FromStr;
+
+use anyhow::Error;
+use anyhow::bail;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Point;
+
+use super::Fieldlike;
+use super::Orientation;
+use super::Stonelike;
+
+
+/// A list of offsets to try, in order, when rotating a stone.
+type Kicks = [(i8, i8)];
+
+/// The offsets used when no wall kicks are to be performed.
+const NO_KICKS: &Kicks = &[(0, 0)];
+
+/// The kicks of the Arika Rotation System (ARS): one unit to the right,
+/// then one unit to the left.
+const ARS_KICKS: &Kicks = &[(0, 0), (1, 0), (-1, 0)];
+
+/// The kicks of the Super Rotation System (SRS) for all stones but the
+/// I stone, indexed by [`transition`].
+const SRS_KICKS: [&Kicks; 8] = [
+  &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 0 -> R
+  &[(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // R -> 0
+  &[(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // R -> 2
+  &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 2 -> R
+  &[(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 2 -> L
+  &[(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // L -> 2
+  &[(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // L -> 0
+  &[(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 0 -> L
+];
+
+/// The kicks of the Super Rotation System (SRS) for the I stone,
+/// indexed by [`transition`].
+const SRS_I_KICKS: [&Kicks; 8] = [
+  &[(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)], // 0 -> R
+  &[(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)], // R -> 0
+  &[(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)], // R -> 2
+  &[(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)], // 2 -> R
+  &[(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)], // 2 -> L
+  &[(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)], // L -> 2
+  &[(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)], // L -> 0
+  &[(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)], // 0 -> L
+];
+
+
+/// Map a rotation starting at the given orientation to an index into
+/// one of the SRS kick tables.
+#[inline]
+fn transition(from: Orientation, left: bool) -> usize {
+  match (from, left) {
+    (Orientation::Rotated0, false) => 0,
+    (Orientation::Rotated90, true) => 1,
+    (Orientation::Rotated90, false) => 2,
+    (Orientation::Rotated180, true) => 3,
+    (Orientation::Rotated180, false) => 4,
+    (Orientation::Rotated270, true) => 5,
+    (Orientation::Rotated270, false) => 6,
+    (Orientation::Rotated0, true) => 7,
+  }
+}
+
+
+/// A coarse classification of stones, as relevant for looking up wall
+/// kicks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Shape {
+  /// A stone with all pieces in a single line.
+  I,
+  /// A stone filling a two by two square.
+  O,
+  /// Any other stone.
+  Other,
+}
+
+impl Shape {
+  fn classify<S>(stone: &S) -> Self
+  where
+    S: ?Sized + Stonelike,
+  {
+    let bounds = stone.bounds();
+    let count = stone.pieces().len() as i16;
+
+    if bounds.w * bounds.h != count {
+      Self::Other
+    } else if bounds.w == 1 || bounds.h == 1 {
+      Self::I
+    } else if bounds.w == 2 && bounds.h == 2 {
+      Self::O
+    } else {
+      // Larger filled rectangles, e.g., of custom stones, get kicked
+      // just like any other stone.
+      Self::Other
+    }
+  }
+}
+
+
+/// The rotation system in use, governing which alternative positions
+/// are tried when a rotated stone would collide ("wall kicks").
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationSystem {
+  /// The Nintendo Rotation System, which does not perform any kicks and
+  /// rejects colliding rotations outright.
+  #[default]
+  Nrs,
+  /// The Super Rotation System as used by most modern Tetris games.
+  Srs,
+  /// The Arika Rotation System, which tries to kick a stone by one unit
+  /// to the right and then to the left.
+  Ars,
+}
+
+impl RotationSystem {
+  /// Retrieve the textual representation of the rotation system, as
+  /// used in configuration files.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Nrs => "nrs",
+      Self::Srs => "srs",
+      Self::Ars => "ars",
+    }
+  }
+
+  /// Retrieve the kicks to try for the given stone when rotating it
+  /// starting at the given orientation.
+  fn kicks<S>(&self, stone: &S, from: Orientation, left: bool) -> &'static Kicks
+  where
+    S: ?Sized + Stonelike,
+  {
+    match (self, Shape::classify(stone)) {
+      (Self::Nrs, _) | (_, Shape::O) | (Self::Ars, Shape::I) => NO_KICKS,
+      (Self::Ars, Shape::Other) => ARS_KICKS,
+      (Self::Srs, Shape::I) => SRS_I_KICKS[transition(from, left)],
+      (Self::Srs, Shape::Other) => SRS_KICKS[transition(from, left)],
+    }
+  }
+
+  /// Determine the point about which the given stone rotates, if
+  /// mandated by the rotation system, in doubled coordinates.
+  ///
+  /// In SRS, a stone rotates about the center of the smallest square
+  /// box containing it in its spawn orientation, in which it occupies
+  /// the upper rows (or, for the I stone, the second row from the top).
+  /// For the I and O stones this center lies between cells.
+  pub(super) fn pivot<S>(&self, stone: &S) -> Option<Point<i16>>
+  where
+    S: ?Sized + Stonelike,
+  {
+    if *self != Self::Srs {
+      return None
+    }
+
+    let bounds = stone.bounds();
+    let turns = match stone.rotation_state() {
+      Orientation::Rotated0 => 0,
+      Orientation::Rotated90 => 1,
+      Orientation::Rotated180 => 2,
+      Orientation::Rotated270 => 3,
+    };
+    let (mut w, mut h) = if turns % 2 == 0 {
+      (bounds.w, bounds.h)
+    } else {
+      (bounds.h, bounds.w)
+    };
+    let n = max(w, h);
+
+    // The pivot relative to the lower left corner of the stone's bounds
+    // in spawn orientation.
+    let mut pivot = Point::new(n - 2 * ((n - w) / 2), 2 * h + 2 * ((n - h) / 2) - n);
+    // Each clockwise rotation maps the stone's `w` x `h` bounds onto
+    // `h` x `w` ones, carrying the pivot along.
+    for _ in 0..turns {
+      pivot = Point::new(pivot.y, 2 * w - pivot.x);
+      let () = swap(&mut w, &mut h);
+    }
+    Some(Point::new(2 * bounds.x, 2 * bounds.y) + pivot)
+  }
+
+  /// Rotate the provided stone by 90° inside the given field, trying
+  /// all kicks until one results in a collision free position.
+  ///
+  /// # Returns
+  /// This method returns `true` if the stone could be rotated and
+  /// `false` otherwise, in which case it is left unchanged.
+  pub(super) fn rotate<F, S>(&self, field: &F, stone: &mut S, left: bool) -> bool
+  where
+    F: ?Sized + Fieldlike<S>,
+    S: Stonelike,
+  {
+    let kicks = self.kicks(stone, stone.rotation_state(), left);
+    let () = stone.rotate(*self, left);
+
+    for (x, y) in kicks {
+      let (x, y) = (i16::from(*x), i16::from(*y));
+      let () = stone.move_by(x, y);
+      if !field.collides(stone) {
+        return true
+      }
+      let () = stone.move_by(-x, -y);
+    }
+
+    let () = stone.rotate(*self, !left);
+    false
+  }
+}
+
+impl FromStr for RotationSystem {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "nrs" => Ok(Self::Nrs),
+      "srs" => Ok(Self::Srs),
+      "ars" => Ok(Self::Ars),
+      _ => bail!("unsupported rotation system `{s}`"),
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use super::super::ai::Stone;
+  use super::super::ai::util::assert_stones_eq;
+  use super::super::ai::util::field;
+  use super::super::ai::util::stone;
+
+
+  /// Retrieve the sorted locations of the pieces of a stone.
+  fn sorted_pieces(stone: &Stone) -> Vec<Point<i16>> {
+    let mut pieces = stone.pieces().collect::<Vec<_>>();
+    let () = pieces.sort();
+    pieces
+  }
+
+  /// Create a vector of points from a list of coordinates.
+  fn points(coords: &[(i16, i16)]) -> Vec<Point<i16>> {
+    let mut points = coords
+      .iter()
+      .map(|(x, y)| Point::new(*x, *y))
+      .collect::<Vec<_>>();
+    let () = points.sort();
+    points
+  }
+
+
+  /// Check that we can convert a [`RotationSystem`] to a string and
+  /// back.
+  #[test]
+  fn rotation_system_str_conversion() {
+    for system in [
+      RotationSystem::Nrs,
+      RotationSystem::Srs,
+      RotationSystem::Ars,
+    ] {
+      assert_eq!(RotationSystem::from_str(system.as_str()).unwrap(), system);
+    }
+
+    assert!(RotationSystem::from_str("foobar").is_err());
+  }
+
+  /// Make sure that we classify stones correctly.
+  #[test]
+  fn shape_classification() {
+    let stone = stone! {"
+      ####
+    "};
+    assert_eq!(Shape::classify(&stone), Shape::I);
+
+    let stone = stone! {"
+      ##
+      ##
+    "};
+    assert_eq!(Shape::classify(&stone), Shape::O);
+
+    let stone = stone! {"
+      ###
+      ###
+    "};
+    assert_eq!(Shape::classify(&stone), Shape::Other);
+
+    let stone = stone! {"
+      .#.
+      ###
+    "};
+    assert_eq!(Shape::classify(&stone), Shape::Other);
+  }
+
+  /// Check that a rotation against a wall is rejected without kicks and
+  /// succeeds with them.
+  #[test]
+  fn wall_kick() {
+    let field = field! {"
+      ....
+      ....
+      ....
+    "};
+    let stone = stone! {"
+      #...
+      #...
+      #...
+    "};
+
+    let mut rotated = stone.clone();
+    assert!(!RotationSystem::Nrs.rotate(&field, &mut rotated, false));
+    assert_stones_eq(&rotated, &stone);
+
+    for system in [RotationSystem::Srs, RotationSystem::Ars] {
+      let stone = stone! {"
+        .#..
+        .##.
+        .#..
+      "};
+      let mut rotated = stone.clone();
+      let () = rotated.move_left();
+      assert!(system.rotate(&field, &mut rotated, true), "{system:?}");
+      assert!(!field.collides(&rotated));
+    }
+
+    let mut rotated = stone.clone();
+    assert!(RotationSystem::Srs.rotate(&field, &mut rotated, false));
+    assert!(!field.collides(&rotated));
+  }
+
+  /// Check that SRS rotates stones about their proper pivots, with the
+  /// I and O stones not wobbling.
+  #[test]
+  fn srs_pivots() {
+    let field = field! {"
+      ......
+      ......
+      ......
+      ......
+      ......
+      ......
+    "};
+    let mut stone = Stone::from_pieces(
+      [(0, 3), (1, 3), (2, 3), (3, 3)]
+        .into_iter()
+        .map(|(x, y)| Point::new(x, y)),
+      Orientation::Rotated0,
+    );
+    let system = RotationSystem::Srs;
+
+    // The I stone cycles through the columns and rows of its 4x4 box,
+    // which spans x = 0..4 and y = 1..5 here.
+    assert!(system.rotate(&field, &mut stone, false));
+    assert_eq!(
+      sorted_pieces(&stone),
+      points(&[(2, 1), (2, 2), (2, 3), (2, 4)])
+    );
+    assert!(system.rotate(&field, &mut stone, false));
+    assert_eq!(
+      sorted_pieces(&stone),
+      points(&[(0, 2), (1, 2), (2, 2), (3, 2)])
+    );
+    assert!(system.rotate(&field, &mut stone, false));
+    assert_eq!(
+      sorted_pieces(&stone),
+      points(&[(1, 1), (1, 2), (1, 3), (1, 4)])
+    );
+    assert!(system.rotate(&field, &mut stone, false));
+    assert_eq!(
+      sorted_pieces(&stone),
+      points(&[(0, 3), (1, 3), (2, 3), (3, 3)])
+    );
+
+    let o = stone! {"
+      ##
+      ##
+    "};
+    let mut stone = o.clone();
+    assert!(system.rotate(&field, &mut stone, true));
+    assert_stones_eq(&stone, &o);
+
+    // The T stone rotates about its center piece.
+    let mut stone = stone! {"
+      .#.
+      ###
+    "};
+    let () = stone.move_by(2, 2);
+    assert!(system.rotate(&field, &mut stone, true));
+    assert_eq!(
+      sorted_pieces(&stone),
+      points(&[(2, 2), (3, 1), (3, 2), (3, 3)])
+    );
+  }
+
+  /// Check that a T-spin triple, which requires the last of the SRS
+  /// kicks, works as expected.
+  #[test]
+  fn srs_t_spin_triple() {
+    let field = field! {"
+      .#...
+      .....
+      #.###
+      #..##
+      #.###
+      ####.
+    "};
+    let mut stone = Stone::from_pieces(
+      [(1, 4), (2, 4), (3, 4), (2, 5)]
+        .into_iter()
+        .map(|(x, y)| Point::new(x, y)),
+      Orientation::Rotated0,
+    );
+    assert!(!field.collides(&stone));
+
+    // Rotating clockwise from spawn orientation kicks the stone by
+    // (-1, -2), into the slot.
+    assert!(RotationSystem::Srs.rotate(&field, &mut stone, false));
+    assert_eq!(stone.rotation_state(), Orientation::Rotated90);
+    assert_eq!(
+      sorted_pieces(&stone),
+      points(&[(1, 1), (1, 2), (1, 3), (2, 2)])
+    );
+
+    // Without kicks, the rotation is rejected.
+    let mut stone = Stone::from_pieces(
+      [(1, 4), (2, 4), (3, 4), (2, 5)]
+        .into_iter()
+        .map(|(x, y)| Point::new(x, y)),
+      Orientation::Rotated0,
+    );
+    assert!(!RotationSystem::Nrs.rotate(&field, &mut stone, false));
+  }
+
+  /// Check that the I stone gets kicked off walls as mandated by SRS.
+  #[test]
+  fn srs_i_wall_kicks() {
+    let field = field! {"
+      ......
+      ......
+      ......
+      ......
+      ......
+      ......
+    "};
+
+    // The I stone in left orientation at the left wall. Rotating it
+    // clockwise tries the offsets (0, 0), which collides with the wall,
+    // and (1, 0), which does not.
+    let mut stone = Stone::from_pieces(
+      [(0, 0), (0, 1), (0, 2), (0, 3)]
+        .into_iter()
+        .map(|(x, y)| Point::new(x, y)),
+      Orientation::Rotated270,
+    );
+    assert!(RotationSystem::Srs.rotate(&field, &mut stone, false));
+    assert_eq!(stone.rotation_state(), Orientation::Rotated0);
+    assert_eq!(
+      sorted_pieces(&stone),
+      points(&[(0, 2), (1, 2), (2, 2), (3, 2)])
+    );
+
+    // The I stone in right orientation at the right wall. Rotating it
+    // clockwise tries the offset (0, 0), which collides with the wall,
+    // and (-1, 0), which does not.
+    let mut stone = Stone::from_pieces(
+      [(5, 0), (5, 1), (5, 2), (5, 3)]
+        .into_iter()
+        .map(|(x, y)| Point::new(x, y)),
+      Orientation::Rotated90,
+    );
+    assert!(RotationSystem::Srs.rotate(&field, &mut stone, false));
+    assert_eq!(stone.rotation_state(), Orientation::Rotated180);
+    assert_eq!(
+      sorted_pieces(&stone),
+      points(&[(2, 1), (3, 1), (4, 1), (5, 1)])
+    );
+  }
+}
@@ -3,17 +3,26 @@
 
 use std::io::Write as _;
 use std::mem::MaybeUninit;
-use std::rc::Rc;
+use std::time::Duration;
 
 use bufio::Writer as StackWriter;
 
+use serde::Deserialize;
+use serde::Serialize;
+
 use crate::ActiveRenderer as Renderer;
 use crate::Change;
 use crate::Color;
 use crate::Font;
 use crate::Point;
 use crate::Rect;
-use crate::Texture;
+
+use super::Clear;
+use super::DropKind;
+use super::Scoring;
+use super::ScoringRules;
+use super::Textures;
+use super::write_time;
 
 /// The font size to use, in game units.
 const FONT_SIZE: i16 = 2;
@@ -21,11 +30,16 @@ const FONT_SIZE: i16 = 2;
 static LEVEL_STR: &[u8] = b"Level:";
 static POINTS_STR: &[u8] = b"Points:";
 static LINES_STR: &[u8] = b"Lines:";
-static FIXED_STRS: [&[u8]; 3] = [LEVEL_STR, POINTS_STR, LINES_STR];
+static COMBO_STR: &[u8] = b"Combo:";
+static B2B_STR: &[u8] = b"B2B:";
+static TIME_STR: &[u8] = b"Time:";
+static FIXED_STRS: [&[u8]; 6] = [
+  LEVEL_STR, POINTS_STR, LINES_STR, COMBO_STR, B2B_STR, TIME_STR,
+];
 
 /// The pre-calculated maximum width of the strings above when rendered
 /// using `Font::builtin`.
-const MAX_FIXED_STR_WIDTH: i16 = 33;
+const MAX_FIXED_STR_WIDTH: i16 = 37;
 /// The pre-calculated maximum width of any single digit (0-9) when
 /// rendered using `Font::builtin`.
 const MAX_DIGIT_WIDTH: i16 = 6;
@@ -42,6 +56,29 @@ fn digits(x: u64) -> i16 {
 }
 
 
+/// The serializable state of a [`Score`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(super) struct Snapshot {
+  /// The current level.
+  level: u16,
+  /// The number of points earned so far.
+  points: u64,
+  /// The total number of lines cleared.
+  lines: u32,
+  /// The number of lines cleared since the last level up.
+  lines_since_up: u16,
+  /// The number of stones merged.
+  #[serde(default)]
+  stones: u64,
+  /// The current combo count, if any.
+  #[serde(default)]
+  combo: Option<u32>,
+  /// The current back-to-back count, if any.
+  #[serde(default)]
+  back_to_back: Option<u32>,
+}
+
+
 /// A type helping with keeping track of score in a Tetris game.
 #[derive(Debug)]
 pub(super) struct Score {
@@ -57,15 +94,26 @@ pub(super) struct Score {
   lines_for_level: u16,
   /// The number of lines cleared since the last level up.
   lines_since_up: u16,
+  /// The number of stones merged.
+  stones: u64,
+  /// The number of consecutive line clearing merges, minus one, if the
+  /// last merge cleared any lines.
+  combo: Option<u32>,
+  /// The number of consecutive difficult clears, minus one, if the
+  /// last line clear was a difficult one.
+  back_to_back: Option<u32>,
+  /// The kind of scoring rules in use.
+  scoring: Scoring,
+  /// The rules used for awarding points.
+  rules: Box<dyn ScoringRules>,
+  /// The time to display, if any.
+  time: Option<Duration>,
   /// The font to use for rendering the current score.
   font: Font,
-  /// The texture to use for each and every "pixel" of each rendered
-  /// glyph.
-  texture: Rc<Texture>,
 }
 
 impl Score {
-  pub fn new(start_level: u16, lines_for_level: u16, texture: Rc<Texture>) -> Self {
+  pub fn new(start_level: u16, lines_for_level: u16, scoring: Scoring) -> Self {
     Self {
       start_level,
       level: start_level,
@@ -73,13 +121,18 @@ impl Score {
       lines: 0,
       lines_for_level,
       lines_since_up: 0,
+      stones: 0,
+      combo: None,
+      back_to_back: None,
+      scoring,
+      rules: scoring.rules(),
+      time: None,
       font: Font::builtin(),
-      texture,
     }
   }
 
   /// Render the object.
-  pub fn render(&self, renderer: &Renderer) {
+  pub fn render(&self, renderer: &Renderer, textures: &Textures) {
     let factor = f32::from(FONT_SIZE) / f32::from(self.font.size());
     let render_pixel = |point: Point<i16>| {
       let () = renderer.render_rect_f32(Rect::new(
@@ -91,7 +144,7 @@ impl Score {
     };
 
     let _guard = renderer.set_color(Color::orange());
-    let _guard = renderer.set_texture(&self.texture);
+    let _guard = renderer.set_texture(&textures.piece);
 
     {
       let () = self.font.render_str(LEVEL_STR, render_pixel);
@@ -101,6 +154,19 @@ impl Score {
 
       let _guard = renderer.set_origin(Point::new(0, -FONT_SIZE));
       let () = self.font.render_str(LINES_STR, render_pixel);
+
+      if self.show_bonuses() {
+        let _guard = renderer.set_origin(Point::new(0, -FONT_SIZE));
+        let () = self.font.render_str(COMBO_STR, render_pixel);
+
+        let _guard = renderer.set_origin(Point::new(0, -FONT_SIZE));
+        let () = self.font.render_str(B2B_STR, render_pixel);
+      }
+
+      if self.time.is_some() {
+        let _guard = renderer.set_origin(Point::new(0, -FONT_SIZE));
+        let () = self.font.render_str(TIME_STR, render_pixel);
+      }
     }
 
     // 256 bytes of stack buffer ought to be enough to format all the
@@ -127,6 +193,35 @@ impl Score {
     let () = write!(writer, "{}", self.lines).unwrap();
     let string = writer.written();
     let () = self.font.render_str(string, render_pixel);
+
+    if self.show_bonuses() {
+      let _guard = renderer.set_origin(Point::new(0, -FONT_SIZE));
+      let () = writer.reset();
+      let () = write!(writer, "{}", self.combo()).unwrap();
+      let string = writer.written();
+      let () = self.font.render_str(string, render_pixel);
+
+      let _guard = renderer.set_origin(Point::new(0, -FONT_SIZE));
+      let () = writer.reset();
+      let () = write!(writer, "{}", self.back_to_back()).unwrap();
+      let string = writer.written();
+      let () = self.font.render_str(string, render_pixel);
+    }
+
+    if let Some(time) = self.time {
+      let _guard = renderer.set_origin(Point::new(0, -FONT_SIZE));
+      let () = writer.reset();
+      let () = write_time(&mut writer, time).unwrap();
+      let string = writer.written();
+      let () = self.font.render_str(string, render_pixel);
+    }
+  }
+
+  /// Check whether combo and back-to-back counters are shown on the
+  /// score board.
+  #[inline]
+  fn show_bonuses(&self) -> bool {
+    !matches!(self.scoring, Scoring::Legacy)
   }
 
   fn with_dyn_str_change<F>(score: &mut Score, mut f: F) -> Change
@@ -144,9 +239,33 @@ impl Score {
     }
   }
 
-  /// Add the given number of lines to the score.
-  pub fn add(&mut self, lines: u16) -> Change {
+  /// Add the result of merging a stone to the score.
+  pub fn add(&mut self, clear: Clear) -> Change {
     Self::with_dyn_str_change(self, |slf| {
+      let lines = clear.lines;
+      let level = slf.level;
+      let mut points = slf.rules.clear_points(&clear, level);
+
+      if lines > 0 {
+        let combo = slf.combo.map_or(0, |combo| combo + 1);
+        slf.combo = Some(combo);
+
+        if clear.is_difficult() {
+          if let Some(back_to_back) = slf.back_to_back {
+            points += slf.rules.back_to_back_points(points);
+            slf.back_to_back = Some(back_to_back + 1);
+          } else {
+            slf.back_to_back = Some(0);
+          }
+        } else {
+          slf.back_to_back = None;
+        }
+
+        points += slf.rules.combo_points(combo, level);
+      } else {
+        slf.combo = None;
+      }
+
       // Strictly speaking the point calculation is wrong: if
       // `lines_for_level_` is a low value (e.g. 1) then the points will
       // be calculated based only on the current level -- though the level
@@ -155,19 +274,40 @@ impl Score {
       // impossible to change this because the points depend on the number
       // of lines cleared and is not limited so we cannot split the
       // calculation, increase the level and continue.
-      slf.points += slf.calculate_points(lines);
+      slf.points += points;
       slf.lines += u32::from(lines);
       slf.lines_since_up += lines;
       slf.level += slf.lines_since_up / slf.lines_for_level;
       slf.lines_since_up %= slf.lines_for_level;
+      slf.stones += 1;
     })
   }
 
-  /// This method is used to calculate the number of points for the
-  /// given number of cleared lines based on the current level.
-  #[inline]
-  fn calculate_points(&self, lines: u16) -> u64 {
-    (5 * (lines * lines) * self.level).into()
+  /// Add points for dropping a stone by the given number of units.
+  pub fn add_drop(&mut self, drop: DropKind, units: u16) -> Change {
+    let points = self.rules.drop_points(drop, units);
+    if points > 0 {
+      Self::with_dyn_str_change(self, |slf| {
+        slf.points += points;
+      })
+    } else {
+      Change::Unchanged
+    }
+  }
+
+  /// Set the time to display, if any.
+  pub fn set_time(&mut self, time: Option<Duration>) -> Change {
+    // We only display tenths of a second, so only those matter for
+    // whether anything changed.
+    let tenths = |time: Option<Duration>| time.map(|time| time.as_millis() / 100);
+    if tenths(self.time) == tenths(time) {
+      self.time = time;
+      Change::Unchanged
+    } else {
+      Self::with_dyn_str_change(self, |slf| {
+        slf.time = time;
+      })
+    }
   }
 
   /// Reset the `Score`'s state to its initial value.
@@ -177,19 +317,63 @@ impl Score {
       slf.lines = 0;
       slf.points = 0;
       slf.lines_since_up = 0;
+      slf.stones = 0;
+      slf.combo = None;
+      slf.back_to_back = None;
     })
   }
 
+  /// Restore the `Score`'s state from a snapshot.
+  pub fn restore(&mut self, snapshot: &Snapshot) -> Change {
+    Self::with_dyn_str_change(self, |slf| {
+      slf.level = snapshot.level;
+      slf.points = snapshot.points;
+      slf.lines = snapshot.lines;
+      slf.lines_since_up = snapshot.lines_since_up;
+      slf.stones = snapshot.stones;
+      slf.combo = snapshot.combo;
+      slf.back_to_back = snapshot.back_to_back;
+    })
+  }
+
+  /// Create a snapshot of the `Score`'s state.
+  pub fn snapshot(&self) -> Snapshot {
+    Snapshot {
+      level: self.level,
+      points: self.points,
+      lines: self.lines,
+      lines_since_up: self.lines_since_up,
+      stones: self.stones,
+      combo: self.combo,
+      back_to_back: self.back_to_back,
+    }
+  }
+
   fn dyn_str_len(&self) -> i16 {
     // If the fixed strings are changed the calculation below will
     // likely also need to be adjusted.
-    debug_assert_eq!(FIXED_STRS.len(), 3);
-
-    [u64::from(self.level), self.points, u64::from(self.lines)]
-      .into_iter()
-      .map(digits)
-      .max()
-      .unwrap_or_default()
+    debug_assert_eq!(FIXED_STRS.len(), 6);
+
+    // A time is displayed as `m:ss.d`, i.e., it is five characters
+    // longer than the number of minutes.
+    let time_len = self
+      .time
+      .map(|time| digits(time.as_secs() / 60) + 5)
+      .unwrap_or_default();
+
+    let len = [
+      u64::from(self.level),
+      self.points,
+      u64::from(self.lines),
+      u64::from(self.combo()),
+      u64::from(self.back_to_back()),
+    ]
+    .into_iter()
+    .map(digits)
+    .max()
+    .unwrap_or_default();
+
+    len.max(time_len)
   }
 
   /// Calculate the width of the score board.
@@ -231,6 +415,30 @@ impl Score {
   pub fn lines(&self) -> u32 {
     self.lines
   }
+
+  /// Retrieve the number of stones merged so far.
+  #[inline]
+  pub fn stones(&self) -> u64 {
+    self.stones
+  }
+
+  /// Retrieve the current combo count.
+  #[inline]
+  pub fn combo(&self) -> u32 {
+    self.combo.unwrap_or_default()
+  }
+
+  /// Retrieve the current back-to-back count.
+  #[inline]
+  pub fn back_to_back(&self) -> u32 {
+    self.back_to_back.unwrap_or_default()
+  }
+
+  /// Retrieve the kind of scoring rules in use.
+  #[inline]
+  pub fn scoring(&self) -> Scoring {
+    self.scoring
+  }
 }
 
 
@@ -238,12 +446,7 @@ impl Score {
 mod tests {
   use super::*;
 
-  use std::rc::Rc;
-
-  use test_fork::fork;
-
-  use crate::gl::empty_texture;
-  use crate::winit::with_opengl_context;
+  use super::super::Spin;
 
 
   /// Verify that our [`digit`] functions work correctly on a bunch of
@@ -286,37 +489,33 @@ mod tests {
   }
 
   /// Check that we can keep track of scores correctly.
-  #[fork]
   #[test]
   fn score_counting() {
-    with_opengl_context(|context| {
-      let texture = Rc::new(empty_texture(context).unwrap());
-      let mut score = Score::new(1, 10, texture);
-      assert_eq!(score.level, 1);
-      assert_eq!(score.points, 0);
-      assert_eq!(score.lines, 0);
-      assert_eq!(score.lines_for_level, 10);
-
-      let change = score.add(5);
-      assert_eq!(change, Change::Resize);
-      assert_eq!(score.level, 1);
-      assert_eq!(score.points, 125);
-      assert_eq!(score.lines, 5);
-      assert_eq!(score.lines_for_level, 10);
-
-      let change = score.add(1);
-      assert_eq!(change, Change::Changed);
-      assert_eq!(score.level, 1);
-      assert_eq!(score.points, 130);
-      assert_eq!(score.lines, 6);
-      assert_eq!(score.lines_for_level, 10);
-
-      let _change = score.add(4);
-      assert_eq!(change, Change::Changed);
-      assert_eq!(score.level, 2);
-      assert_eq!(score.points, 210);
-      assert_eq!(score.lines, 10);
-      assert_eq!(score.lines_for_level, 10);
-    })
+    let mut score = Score::new(1, 10, Scoring::Legacy);
+    assert_eq!(score.level, 1);
+    assert_eq!(score.points, 0);
+    assert_eq!(score.lines, 0);
+    assert_eq!(score.lines_for_level, 10);
+
+    let change = score.add(Clear::new(5, Spin::None));
+    assert_eq!(change, Change::Resize);
+    assert_eq!(score.level, 1);
+    assert_eq!(score.points, 125);
+    assert_eq!(score.lines, 5);
+    assert_eq!(score.lines_for_level, 10);
+
+    let change = score.add(Clear::new(1, Spin::None));
+    assert_eq!(change, Change::Changed);
+    assert_eq!(score.level, 1);
+    assert_eq!(score.points, 130);
+    assert_eq!(score.lines, 6);
+    assert_eq!(score.lines_for_level, 10);
+
+    let _change = score.add(Clear::new(4, Spin::None));
+    assert_eq!(change, Change::Changed);
+    assert_eq!(score.level, 2);
+    assert_eq!(score.points, 210);
+    assert_eq!(score.lines, 10);
+    assert_eq!(score.lines_for_level, 10);
   }
 }
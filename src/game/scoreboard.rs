@@ -0,0 +1,355 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::io::Write as _;
+use std::mem::MaybeUninit;
+
+use bufio::Writer as StackWriter;
+
+use crate::ActiveRenderer as Renderer;
+use crate::Color;
+use crate::ColorMode;
+use crate::ColorSet;
+use crate::Font;
+use crate::Point;
+use crate::Rect;
+use crate::highscore::Category;
+use crate::highscore::Entry;
+use crate::highscore::HighScores;
+use crate::highscore::MAX_NAME_LEN;
+
+use super::Mode;
+use super::Outcome;
+use super::Textures;
+use super::write_time;
+
+
+/// The height of a line of text in the ranking, in game units.
+const LINE_SIZE: f32 = 1.0;
+/// The height of a line of text in the name prompt, in game units.
+pub(super) const PROMPT_LINE_SIZE: f32 = 2.0;
+/// The horizontal space between the box' edges and the text.
+const PADDING: f32 = 0.5;
+/// The color set used for the background of the box.
+const BACK_COLOR: ColorSet<Color> = ColorSet::new(Color::white(), Color::black());
+
+
+/// A line of text, with one part aligned to the left and the other to
+/// the right.
+pub(super) type Line<'text> = (&'text [u8], &'text [u8]);
+
+
+/// The state the [`Scoreboard`] is in.
+#[derive(Debug)]
+enum State {
+  /// The game is still running.
+  Idle,
+  /// The player is asked for a name to record a new high score under.
+  Prompt {
+    /// The outcome of the game to record.
+    outcome: Outcome,
+    /// The name entered so far.
+    name: String,
+  },
+  /// The ranking of a category is being shown.
+  Ranking {
+    /// The category being shown.
+    category: Category,
+    /// The rank of the entry to highlight, if any.
+    highlight: Option<usize>,
+  },
+}
+
+
+/// The view of the high score table, taking care of recording and
+/// displaying the results of finished games.
+#[derive(Debug)]
+pub(super) struct Scoreboard {
+  /// The high scores achieved so far.
+  scores: HighScores,
+  /// The state we are in.
+  state: State,
+  /// The font to use for rendering.
+  font: Font,
+}
+
+impl Scoreboard {
+  pub fn new(scores: HighScores) -> Self {
+    Self {
+      scores,
+      state: State::Idle,
+      font: Font::builtin(),
+    }
+  }
+
+  /// Handle the end of a game.
+  ///
+  /// Returns `true` if anything changed.
+  pub fn on_game_over(&mut self, outcome: Outcome) -> bool {
+    if !matches!(self.state, State::Idle) {
+      return false
+    }
+
+    self.state = if self.scores.needs_name(&outcome) {
+      State::Prompt {
+        outcome,
+        name: String::new(),
+      }
+    } else {
+      State::Ranking {
+        category: Category::from(&outcome),
+        // Games played by the AI are recorded right away.
+        highlight: self.scores.insert("", &outcome),
+      }
+    };
+    true
+  }
+
+  /// Append a character to the name being entered.
+  pub fn on_char(&mut self, c: u8) -> bool {
+    match &mut self.state {
+      State::Prompt { name, .. } if name.len() < MAX_NAME_LEN => {
+        let () = name.push(char::from(c));
+        true
+      },
+      _ => false,
+    }
+  }
+
+  /// Remove the last character of the name being entered.
+  pub fn on_erase(&mut self) -> bool {
+    match &mut self.state {
+      State::Prompt { name, .. } => name.pop().is_some(),
+      _ => false,
+    }
+  }
+
+  /// Confirm the name entered and record the high score.
+  pub fn on_confirm(&mut self) -> bool {
+    match &self.state {
+      State::Prompt { outcome, name } if !name.is_empty() => {
+        let highlight = self.scores.insert(name, outcome);
+        self.state = State::Ranking {
+          category: Category::from(outcome),
+          highlight,
+        };
+        true
+      },
+      _ => false,
+    }
+  }
+
+  /// Reset the scoreboard for a new game.
+  #[inline]
+  pub fn reset(&mut self) {
+    self.state = State::Idle
+  }
+
+  /// Check whether we are currently asking the player for a name.
+  #[inline]
+  pub fn is_prompting(&self) -> bool {
+    matches!(self.state, State::Prompt { .. })
+  }
+
+  /// Check whether we are currently showing anything.
+  #[inline]
+  pub fn is_active(&self) -> bool {
+    !matches!(self.state, State::Idle)
+  }
+
+  /// Retrieve the high scores achieved so far.
+  #[inline]
+  pub fn high_scores(&self) -> &HighScores {
+    &self.scores
+  }
+
+  /// Render the name prompt.
+  fn render_prompt(
+    &self,
+    renderer: &Renderer,
+    textures: &Textures,
+    color_mode: ColorMode,
+    name: &str,
+    w: i16,
+    h: i16,
+  ) {
+    let mut buffer = [MaybeUninit::<u8>::uninit(); MAX_NAME_LEN + 1];
+    let mut writer = StackWriter::new(&mut buffer);
+    let () = write!(writer, "{name}").unwrap();
+    if name.len() < MAX_NAME_LEN {
+      let () = write!(writer, "_").unwrap();
+    }
+
+    let lines: [Line<'_>; 3] = [
+      (b"High score!", b""),
+      (b"Name:", b""),
+      (writer.written(), b""),
+    ];
+    render_lines(
+      &self.font,
+      renderer,
+      textures,
+      color_mode,
+      &lines,
+      None,
+      PROMPT_LINE_SIZE,
+      w,
+      h,
+    )
+  }
+
+  /// Render the ranking of the given category.
+  #[expect(clippy::too_many_arguments)]
+  fn render_ranking(
+    &self,
+    renderer: &Renderer,
+    textures: &Textures,
+    color_mode: ColorMode,
+    category: &Category,
+    highlight: Option<usize>,
+    w: i16,
+    h: i16,
+  ) {
+    let entries = self.scores.entries(category);
+    let texts = entries
+      .iter()
+      .enumerate()
+      .map(|(rank, entry)| format_entry(category.mode, rank, entry))
+      .collect::<Vec<_>>();
+    let mut lines = Vec::with_capacity(entries.len() + 2);
+    let title: &[u8] = if category.ai {
+      b"AI high scores"
+    } else {
+      b"High scores"
+    };
+    let () = lines.push((title, &b""[..]));
+    let () = lines.push((&b""[..], &b""[..]));
+
+    for (text, split) in &texts {
+      let () = lines.push(text.split_at(*split));
+    }
+
+    if entries.is_empty() {
+      let () = lines.push((b"No entries", b""));
+    }
+
+    render_lines(
+      &self.font,
+      renderer,
+      textures,
+      color_mode,
+      &lines,
+      highlight.map(|rank| rank + 2),
+      LINE_SIZE,
+      w,
+      h,
+    )
+  }
+
+  /// Render the scoreboard, if active, in an area of size `w` x `h`.
+  pub fn render(
+    &self,
+    renderer: &Renderer,
+    textures: &Textures,
+    color_mode: ColorMode,
+    w: i16,
+    h: i16,
+  ) {
+    match &self.state {
+      State::Idle => (),
+      State::Prompt { name, .. } => self.render_prompt(renderer, textures, color_mode, name, w, h),
+      State::Ranking {
+        category,
+        highlight,
+      } => self.render_ranking(renderer, textures, color_mode, category, *highlight, w, h),
+    }
+  }
+}
+
+
+/// Render a box containing the provided lines of text, vertically
+/// centered within an area of size `w` x `h`.
+///
+/// The line at index `highlight`, if any, is rendered in a
+/// different color.
+#[expect(clippy::too_many_arguments)]
+pub(super) fn render_lines(
+  font: &Font,
+  renderer: &Renderer,
+  textures: &Textures,
+  color_mode: ColorMode,
+  lines: &[Line<'_>],
+  highlight: Option<usize>,
+  line_size: f32,
+  w: i16,
+  h: i16,
+) {
+  let w = f32::from(w);
+  let h = f32::from(h);
+  let space = font.str_width(b" ");
+  let text_w = lines
+    .iter()
+    .map(|(left, right)| font.str_width(left) + space + font.str_width(right))
+    .max()
+    .unwrap_or_default();
+  // Shrink the text if it would not fit otherwise.
+  let factor =
+    (line_size / f32::from(font.size())).min((w - 2.0 * PADDING) / f32::from(text_w.max(1)));
+  let line_h = factor * f32::from(font.size());
+
+  let box_h = (lines.len() as f32 + 1.0) * line_h;
+  let box_y = (h - box_h) / 2.0;
+  {
+    let _guard = renderer.set_no_texture();
+    let _guard = renderer.set_color(BACK_COLOR.select(color_mode));
+    let () = renderer.render_rect_f32(Rect::new(0.0, box_y, w, box_h));
+  }
+
+  let _guard = renderer.set_texture(&textures.piece);
+
+  for (i, (left, right)) in lines.iter().enumerate() {
+    let color = if Some(i) == highlight {
+      Color::red()
+    } else {
+      Color::orange()
+    };
+    let _guard = renderer.set_color(color);
+
+    let y = box_y + box_h - line_h / 2.0 - (i as f32 + 1.0) * line_h;
+    let right_w = f32::from(font.str_width(right)) * factor;
+    let (left_x, right_x) = if left.is_empty() || right.is_empty() {
+      // A line without any alignment requirements is centered.
+      let text_w = f32::from(font.str_width(left)) * factor + right_w;
+      let x = (w - text_w) / 2.0;
+      (x, x + text_w - right_w)
+    } else {
+      (PADDING, w - PADDING - right_w)
+    };
+
+    for (x, text) in [(left_x, left), (right_x, right)] {
+      let () = font.render_str(text, |point: Point<i16>| {
+        let () = renderer.render_rect_f32(Rect::new(
+          x + f32::from(point.x) * factor,
+          y + f32::from(point.y) * factor,
+          factor,
+          factor,
+        ));
+      });
+    }
+  }
+}
+
+
+/// Format a ranking entry, returning the resulting text along with the
+/// index at which it is split into its left and right aligned parts.
+fn format_entry(mode: Mode, rank: usize, entry: &Entry) -> (Vec<u8>, usize) {
+  let mut text = Vec::new();
+  let () = write!(text, "{}. {}", rank + 1, entry.name).unwrap();
+  let split = text.len();
+
+  let () = match mode {
+    Mode::Marathon | Mode::Ultra => write!(text, "{}", entry.points).unwrap(),
+    Mode::Sprint | Mode::Dig => write_time(&mut text, entry.time()).unwrap(),
+  };
+  (text, split)
+}
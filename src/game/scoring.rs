@@ -0,0 +1,225 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use anyhow::Error;
+use anyhow::bail;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+
+/// The kind of "spin" a stone was merged with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Spin {
+  /// The stone was not spun into place.
+  #[default]
+  None,
+  /// The stone was spun into place, but did not fully qualify as a
+  /// T-spin.
+  Mini,
+  /// The stone was spun into place as a full T-spin.
+  Full,
+}
+
+
+/// A description of what happened when a stone got merged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Clear {
+  /// The number of lines cleared.
+  pub lines: u16,
+  /// The spin the stone was merged with.
+  pub spin: Spin,
+}
+
+impl Clear {
+  #[inline]
+  pub fn new(lines: u16, spin: Spin) -> Self {
+    Self { lines, spin }
+  }
+
+  /// Check whether the clear is considered "difficult", making it
+  /// eligible for a back-to-back bonus.
+  #[inline]
+  pub fn is_difficult(&self) -> bool {
+    self.lines >= 4 || (self.lines > 0 && self.spin != Spin::None)
+  }
+}
+
+
+/// The way a stone was dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DropKind {
+  /// The stone was moved down explicitly by the player.
+  Soft,
+  /// The stone was dropped all the way down.
+  Hard,
+}
+
+
+/// A trait representing a set of rules for awarding points.
+pub(super) trait ScoringRules: Debug {
+  /// Calculate the points awarded for the given clear, excluding any
+  /// bonuses.
+  fn clear_points(&self, clear: &Clear, level: u16) -> u64;
+
+  /// Calculate the points awarded for the given combo count, i.e., the
+  /// number of consecutive line clearing merges, minus one.
+  fn combo_points(&self, _combo: u32, _level: u16) -> u64 {
+    0
+  }
+
+  /// Calculate the bonus awarded on top of `points` for a difficult
+  /// clear following another one.
+  fn back_to_back_points(&self, _points: u64) -> u64 {
+    0
+  }
+
+  /// Calculate the points awarded for dropping a stone by the given
+  /// number of units.
+  fn drop_points(&self, _drop: DropKind, _units: u16) -> u64 {
+    0
+  }
+}
+
+
+/// The scoring rules the game originally shipped with.
+#[derive(Debug)]
+struct LegacyRules;
+
+impl ScoringRules for LegacyRules {
+  fn clear_points(&self, clear: &Clear, level: u16) -> u64 {
+    let lines = u64::from(clear.lines);
+    5 * (lines * lines) * u64::from(level)
+  }
+}
+
+
+/// Scoring rules modeled after the Tetris guideline.
+#[derive(Debug)]
+struct GuidelineRules;
+
+impl ScoringRules for GuidelineRules {
+  fn clear_points(&self, clear: &Clear, level: u16) -> u64 {
+    let points = match (clear.spin, clear.lines) {
+      (Spin::None, 0) => 0,
+      (Spin::None, 1) => 100,
+      (Spin::None, 2) => 300,
+      (Spin::None, 3) => 500,
+      (Spin::None, _) => 800,
+      (Spin::Mini, 0) => 100,
+      (Spin::Mini, 1) => 200,
+      (Spin::Mini, _) => 400,
+      (Spin::Full, 0) => 400,
+      (Spin::Full, 1) => 800,
+      (Spin::Full, 2) => 1200,
+      (Spin::Full, _) => 1600,
+    };
+    points * u64::from(level)
+  }
+
+  fn combo_points(&self, combo: u32, level: u16) -> u64 {
+    50 * u64::from(combo) * u64::from(level)
+  }
+
+  fn back_to_back_points(&self, points: u64) -> u64 {
+    points / 2
+  }
+
+  fn drop_points(&self, drop: DropKind, units: u16) -> u64 {
+    match drop {
+      DropKind::Soft => u64::from(units),
+      DropKind::Hard => 2 * u64::from(units),
+    }
+  }
+}
+
+
+/// The rules used for awarding points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scoring {
+  /// Points are awarded solely for cleared lines, based on their
+  /// number and the current level.
+  #[default]
+  Legacy,
+  /// Points are awarded according to the Tetris guideline, including
+  /// drops, combos, back-to-back clears, and T-spins.
+  Guideline,
+}
+
+impl Scoring {
+  /// Retrieve the textual representation of the scoring rules, as used
+  /// in configuration files.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Legacy => "legacy",
+      Self::Guideline => "guideline",
+    }
+  }
+
+  /// Retrieve the rules object corresponding to this variant.
+  pub(super) fn rules(&self) -> Box<dyn ScoringRules> {
+    match self {
+      Self::Legacy => Box::new(LegacyRules),
+      Self::Guideline => Box::new(GuidelineRules),
+    }
+  }
+}
+
+impl FromStr for Scoring {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "legacy" => Ok(Self::Legacy),
+      "guideline" => Ok(Self::Guideline),
+      _ => bail!("unsupported scoring rules `{s}`"),
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we can convert a [`Scoring`] to a string and back.
+  #[test]
+  fn scoring_str_conversion() {
+    for scoring in [Scoring::Legacy, Scoring::Guideline] {
+      assert_eq!(Scoring::from_str(scoring.as_str()).unwrap(), scoring);
+    }
+
+    assert!(Scoring::from_str("foobar").is_err());
+  }
+
+  /// Make sure that we classify clears as difficult correctly.
+  #[test]
+  fn difficult_clears() {
+    assert!(!Clear::new(0, Spin::None).is_difficult());
+    assert!(!Clear::new(3, Spin::None).is_difficult());
+    assert!(Clear::new(4, Spin::None).is_difficult());
+    assert!(!Clear::new(0, Spin::Full).is_difficult());
+    assert!(Clear::new(1, Spin::Mini).is_difficult());
+    assert!(Clear::new(2, Spin::Full).is_difficult());
+  }
+
+  /// Check that guideline rules award points as expected.
+  #[test]
+  fn guideline_points() {
+    let rules = Scoring::Guideline.rules();
+    assert_eq!(rules.clear_points(&Clear::new(1, Spin::None), 1), 100);
+    assert_eq!(rules.clear_points(&Clear::new(4, Spin::None), 2), 1600);
+    assert_eq!(rules.clear_points(&Clear::new(0, Spin::Full), 1), 400);
+    assert_eq!(rules.clear_points(&Clear::new(2, Spin::Full), 3), 3600);
+    assert_eq!(rules.clear_points(&Clear::new(1, Spin::Mini), 1), 200);
+    assert_eq!(rules.combo_points(2, 3), 300);
+    assert_eq!(rules.back_to_back_points(800), 400);
+    assert_eq!(rules.drop_points(DropKind::Soft, 3), 3);
+    assert_eq!(rules.drop_points(DropKind::Hard, 3), 6);
+  }
+}
@@ -3,50 +3,142 @@
 
 use std::iter;
 use std::mem::take;
-use std::rc::Rc;
 use std::slice;
 use std::vec;
 
+use anyhow::Result;
+use anyhow::ensure;
+
+use serde::Deserialize;
+use serde::Serialize;
+
 use crate::ActiveRenderer as Renderer;
 use crate::Color;
 use crate::ColorMode;
 use crate::Point;
-use crate::Texture;
 
+use super::Orientation;
 use super::Piece;
+use super::RotationSystem;
 use super::Stonelike;
+use super::Textures;
 use super::ai;
+use super::stonelike::rotate;
+
+
+/// The serializable state of a [`Stone`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(super) struct Snapshot {
+  /// The index of the color of the stone's pieces.
+  color_idx: u8,
+  /// The locations of the stone's pieces.
+  pieces: Box<[Point<i16>]>,
+  /// The template the stone was created from.
+  template: Box<[Point<i8>]>,
+  /// The stone's orientation relative to the template.
+  rotation_state: Orientation,
+}
 
 
 /// The representation of a Tetris stone.
 #[derive(Debug)]
 pub(super) struct Stone {
-  /// The texture to use for an individual piece.
-  piece_texture: Rc<Texture>,
   /// The individual pieces making up the stone and their locations.
   /// Typically a stone has four pieces, but that's not set in stone.
   pieces: Box<[(Piece, Point<i16>)]>,
+  /// The template the stone was created from.
+  template: Box<[Point<i8>]>,
+  /// The stone's orientation relative to the template.
+  rotation_state: Orientation,
 }
 
 impl Stone {
-  pub fn new(piece_texture: Rc<Texture>, template: &[Point<i8>], color_idx: u8) -> Self {
+  pub fn new(template: &[Point<i8>], color_idx: u8) -> Self {
     assert!(!template.is_empty(), "provided stone template is empty");
 
     Self {
-      piece_texture,
       pieces: template
         .iter()
         .map(|p| (Piece::new(color_idx), p.into_other()))
         .collect(),
+      template: template.into(),
+      rotation_state: Orientation::Rotated0,
+    }
+  }
+
+  /// Restore a stone from a snapshot of its state.
+  pub fn from_snapshot(snapshot: &Snapshot) -> Result<Self> {
+    let Snapshot {
+      color_idx,
+      pieces,
+      template,
+      rotation_state,
+    } = snapshot;
+
+    ensure!(!template.is_empty(), "stone template is empty");
+    ensure!(
+      pieces.len() == template.len(),
+      "stone has {} pieces but its template has {}",
+      pieces.len(),
+      template.len()
+    );
+    ensure!(
+      usize::from(*color_idx) < Piece::COLORS.len(),
+      "stone color index {color_idx} is invalid"
+    );
+
+    let slf = Self {
+      pieces: pieces
+        .iter()
+        .map(|location| (Piece::new(*color_idx), *location))
+        .collect(),
+      template: template.clone(),
+      rotation_state: *rotation_state,
+    };
+    Ok(slf)
+  }
+
+  /// Create a snapshot of the stone's state.
+  pub fn snapshot(&self) -> Snapshot {
+    Snapshot {
+      // SANITY: A stone always has at least one piece, all of the
+      //         same color.
+      color_idx: self.pieces[0].0.color_idx(),
+      pieces: self.pieces().collect(),
+      template: self.template.clone(),
+      rotation_state: self.rotation_state,
     }
   }
 
-  pub fn render(&self, renderer: &Renderer, color_mode: ColorMode) {
-    self.render_with_overlay(renderer, color_mode, Color::black())
+  /// Reset the stone to the orientation and location described by the
+  /// template it was created from.
+  pub fn reset(&mut self) {
+    let () = self
+      .pieces
+      .iter_mut()
+      .zip(self.template.iter())
+      .for_each(|((_piece, location), template)| *location = template.into_other());
+    self.rotation_state = Orientation::Rotated0;
+  }
+
+  /// Retrieve the template the stone was created from.
+  #[inline]
+  pub fn template(&self) -> &[Point<i8>] {
+    &self.template
+  }
+
+  pub fn render(&self, renderer: &Renderer, textures: &Textures, color_mode: ColorMode) {
+    self.render_with_overlay(renderer, textures, color_mode, Color::black())
   }
 
-  pub fn render_with_overlay(&self, renderer: &Renderer, color_mode: ColorMode, overlay: Color) {
-    let _guard = renderer.set_texture(&self.piece_texture);
+  pub fn render_with_overlay(
+    &self,
+    renderer: &Renderer,
+    textures: &Textures,
+    color_mode: ColorMode,
+    overlay: Color,
+  ) {
+    let _guard = renderer.set_texture(&textures.piece);
 
     let () = self.pieces.iter().for_each(|(piece, location)| {
       piece.render_with_overlay(renderer, *location, color_mode, overlay)
@@ -59,15 +151,16 @@ impl Stone {
   // allowing us to omit unnecessary clones due to limitations of Rust.
   pub fn take(&mut self) -> Self {
     Self {
-      piece_texture: Rc::clone(&self.piece_texture),
       pieces: take(&mut self.pieces),
+      template: take(&mut self.template),
+      rotation_state: self.rotation_state,
     }
   }
 
   /// Convert this stone into an [`ai::Stone`].
   #[inline]
   pub(super) fn to_ai_stone(&self) -> ai::Stone {
-    ai::Stone::from_pieces(self.pieces())
+    ai::Stone::from_pieces(self.pieces(), self.rotation_state)
   }
 }
 
@@ -95,6 +188,22 @@ impl Stonelike for Stone {
   fn into_pieces(self) -> Self::IntoPiecesIter {
     Vec::from(self.pieces).into_iter()
   }
+
+  #[inline]
+  fn rotation_state(&self) -> Orientation {
+    self.rotation_state
+  }
+
+  fn rotate(&mut self, system: RotationSystem, left: bool) {
+    // The pivot depends on the rotation state prior to the rotation.
+    let () = rotate(self, system, left);
+
+    if left {
+      self.rotation_state = self.rotation_state.rotate_left();
+    } else {
+      self.rotation_state = self.rotation_state.rotate_right();
+    }
+  }
 }
 
 
@@ -102,106 +211,111 @@ impl Stonelike for Stone {
 mod tests {
   use super::*;
 
-  use test_fork::fork;
-
-  use xgl::sys;
-
-  use crate::gl::empty_texture;
-  use crate::winit::with_opengl_context;
-
 
-  fn new_stone(template: &[Point<i8>], context: &sys::Context) -> Stone {
-    let texture = Rc::new(empty_texture(context).unwrap());
-    Stone::new(texture, template, 0)
+  fn new_stone(template: &[Point<i8>]) -> Stone {
+    Stone::new(template, 0)
   }
 
 
   /// Check that the bounds of a `Stone` are calculated correctly.
-  #[fork]
   #[test]
   fn stone_bounds() {
-    with_opengl_context(|context| {
-      let template = [Point::new(1, 2)];
-      let bounds = new_stone(&template, context).bounds();
-      assert_eq!(bounds.x, 1);
-      assert_eq!(bounds.y, 2);
-      assert_eq!(bounds.w, 1);
-      assert_eq!(bounds.h, 1);
-
-      let template = [Point::new(1, 2), Point::new(3, 2)];
-      let bounds = new_stone(&template, context).bounds();
-      assert_eq!(bounds.x, 1);
-      assert_eq!(bounds.y, 2);
-      assert_eq!(bounds.w, 3);
-      assert_eq!(bounds.h, 1);
-
-      let template = [Point::new(1, 2), Point::new(0, 1)];
-      let bounds = new_stone(&template, context).bounds();
-      assert_eq!(bounds.x, 0);
-      assert_eq!(bounds.y, 1);
-      assert_eq!(bounds.w, 2);
-      assert_eq!(bounds.h, 2);
-
-      let template = [
-        Point::new(0, 0),
-        Point::new(0, 1),
-        Point::new(1, 0),
-        Point::new(1, 1),
-      ];
-      let bounds = new_stone(&template, context).bounds();
-      assert_eq!(bounds.x, 0);
-      assert_eq!(bounds.y, 0);
-      assert_eq!(bounds.w, 2);
-      assert_eq!(bounds.h, 2);
-    })
+    let template = [Point::new(1, 2)];
+    let bounds = new_stone(&template).bounds();
+    assert_eq!(bounds.x, 1);
+    assert_eq!(bounds.y, 2);
+    assert_eq!(bounds.w, 1);
+    assert_eq!(bounds.h, 1);
+
+    let template = [Point::new(1, 2), Point::new(3, 2)];
+    let bounds = new_stone(&template).bounds();
+    assert_eq!(bounds.x, 1);
+    assert_eq!(bounds.y, 2);
+    assert_eq!(bounds.w, 3);
+    assert_eq!(bounds.h, 1);
+
+    let template = [Point::new(1, 2), Point::new(0, 1)];
+    let bounds = new_stone(&template).bounds();
+    assert_eq!(bounds.x, 0);
+    assert_eq!(bounds.y, 1);
+    assert_eq!(bounds.w, 2);
+    assert_eq!(bounds.h, 2);
+
+    let template = [
+      Point::new(0, 0),
+      Point::new(0, 1),
+      Point::new(1, 0),
+      Point::new(1, 1),
+    ];
+    let bounds = new_stone(&template).bounds();
+    assert_eq!(bounds.x, 0);
+    assert_eq!(bounds.y, 0);
+    assert_eq!(bounds.w, 2);
+    assert_eq!(bounds.h, 2);
   }
 
   /// Check that we can move a `Stone` object as expected.
-  #[fork]
   #[test]
   fn stone_movement() {
-    with_opengl_context(|context| {
-      let template = [Point::new(1, 2), Point::new(0, 1)];
-      let mut stone = new_stone(&template, context);
-      let bounds = stone.bounds();
-      assert_eq!(bounds.x, 0);
-      assert_eq!(bounds.y, 1);
-      assert_eq!(bounds.w, 2);
-      assert_eq!(bounds.h, 2);
-
-      let () = stone.move_to(Point::new(3, 4));
-      let bounds = stone.bounds();
-      assert_eq!(bounds.x, 3);
-      assert_eq!(bounds.y, 4);
-
-      let () = stone.move_to(Point::new(0, 0));
-      let bounds = stone.bounds();
-      assert_eq!(bounds.x, 0);
-      assert_eq!(bounds.y, 0);
-      assert_eq!(bounds.w, 2);
-      assert_eq!(bounds.h, 2);
-    })
+    let template = [Point::new(1, 2), Point::new(0, 1)];
+    let mut stone = new_stone(&template);
+    let bounds = stone.bounds();
+    assert_eq!(bounds.x, 0);
+    assert_eq!(bounds.y, 1);
+    assert_eq!(bounds.w, 2);
+    assert_eq!(bounds.h, 2);
+
+    let () = stone.move_to(Point::new(3, 4));
+    let bounds = stone.bounds();
+    assert_eq!(bounds.x, 3);
+    assert_eq!(bounds.y, 4);
+
+    let () = stone.move_to(Point::new(0, 0));
+    let bounds = stone.bounds();
+    assert_eq!(bounds.x, 0);
+    assert_eq!(bounds.y, 0);
+    assert_eq!(bounds.w, 2);
+    assert_eq!(bounds.h, 2);
   }
 
   /// Check that we can rotate a `Stone` object as expected.
-  #[fork]
   #[test]
   fn stone_rotation() {
-    with_opengl_context(|context| {
-      // T stone
-      let template = [
-        Point::new(0, 0),
-        Point::new(1, 0),
-        Point::new(1, 1),
-        Point::new(2, 0),
-      ];
-      let mut stone = new_stone(&template, context);
-      let () = stone.move_to(Point::new(6, 4));
-      let before = stone.pieces().collect::<Vec<_>>();
-      let () = stone.rotate_left();
-      let after = stone.pieces().collect::<Vec<_>>();
-
-      assert_ne!(before, after);
-    })
+    // T stone
+    let template = [
+      Point::new(0, 0),
+      Point::new(1, 0),
+      Point::new(1, 1),
+      Point::new(2, 0),
+    ];
+    let mut stone = new_stone(&template);
+    let () = stone.move_to(Point::new(6, 4));
+    let before = stone.pieces().collect::<Vec<_>>();
+    let () = stone.rotate_left();
+    let after = stone.pieces().collect::<Vec<_>>();
+
+    assert_ne!(before, after);
+  }
+
+  /// Check that we can reset a `Stone` object to its initial
+  /// orientation and location.
+  #[test]
+  fn stone_reset() {
+    // L stone
+    let template = [
+      Point::new(0, 0),
+      Point::new(1, 0),
+      Point::new(2, 0),
+      Point::new(2, 1),
+    ];
+    let mut stone = new_stone(&template);
+    let before = stone.pieces().collect::<Vec<_>>();
+    let () = stone.move_to(Point::new(5, 7));
+    let () = stone.rotate_right();
+    assert_ne!(stone.pieces().collect::<Vec<_>>(), before);
+
+    let () = stone.reset();
+    let after = stone.pieces().collect::<Vec<_>>();
+    assert_eq!(after, before);
   }
 }
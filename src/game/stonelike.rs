@@ -8,6 +8,9 @@ use std::f32::consts::PI;
 use crate::Point;
 use crate::Rect;
 
+use super::Orientation;
+use super::RotationSystem;
+
 
 #[inline]
 fn deg_to_rad(x: f32) -> f32 {
@@ -52,10 +55,40 @@ fn rotate_point(point: Point<i16>, center: Point<f32>, left: bool) -> Point<i16>
 }
 
 
-pub(super) fn rotate<S>(stone: &mut S, left: bool)
+/// Rotate all pieces of a stone by 90° about the given pivot, provided
+/// in doubled coordinates so that points between cells can be
+/// represented.
+fn rotate_about<S>(stone: &mut S, pivot: Point<i16>, left: bool)
 where
   S: ?Sized + Stonelike,
 {
+  let () = stone.pieces_mut().for_each(|location| {
+    // Work with the doubled center of the piece.
+    let d = Point::new(2 * location.x + 1, 2 * location.y + 1) - pivot;
+    let d = if left {
+      Point::new(-d.y, d.x)
+    } else {
+      Point::new(d.y, -d.x)
+    };
+    let center = pivot + d;
+    *location = Point::new((center.x - 1) / 2, (center.y - 1) / 2);
+  });
+}
+
+
+/// Rotate a stone by 90°, about the pivot mandated by the given
+/// rotation system.
+///
+/// This function has to be invoked before the stone's rotation state
+/// is updated.
+pub(super) fn rotate<S>(stone: &mut S, system: RotationSystem, left: bool)
+where
+  S: ?Sized + Stonelike,
+{
+  if let Some(pivot) = system.pivot(stone) {
+    return rotate_about(stone, pivot, left)
+  }
+
   let center_x;
   let center_y;
 
@@ -108,6 +141,10 @@ pub(super) trait Stonelike {
   fn pieces_mut(&mut self) -> Self::PieceIterMut<'_>;
   fn into_pieces(self) -> Self::IntoPiecesIter;
 
+  /// Retrieve the stone's rotation state, i.e., its orientation
+  /// relative to the one it was created in.
+  fn rotation_state(&self) -> Orientation;
+
   /// Retrieve the stone's bounds.
   fn bounds(&self) -> Rect<i16> {
     let mut pieces = self.pieces();
@@ -168,21 +205,24 @@ pub(super) trait Stonelike {
     self.move_by(1, 0)
   }
 
-  /// Rotate the stone either left or right by 90°.
+  /// Rotate the stone either left or right by 90°, as mandated by the
+  /// given rotation system.
   #[inline]
-  fn rotate(&mut self, left: bool) {
-    rotate(self, left)
+  fn rotate(&mut self, system: RotationSystem, left: bool) {
+    rotate(self, system, left)
   }
 
-  /// Rotate the stone left by 90°.
+  /// Rotate the stone left by 90°, using the default rotation system.
+  #[cfg(test)]
   #[inline]
   fn rotate_left(&mut self) {
-    self.rotate(true)
+    self.rotate(RotationSystem::default(), true)
   }
 
-  /// Rotate the stone right by 90°.
+  /// Rotate the stone right by 90°, using the default rotation system.
+  #[cfg(test)]
   #[inline]
   fn rotate_right(&mut self) {
-    self.rotate(false)
+    self.rotate(RotationSystem::default(), false)
   }
 }
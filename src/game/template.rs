@@ -0,0 +1,396 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::str::FromStr;
+
+use anyhow::Context as _;
+use anyhow::Error;
+use anyhow::Result;
+use anyhow::bail;
+use anyhow::ensure;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Point;
+
+use super::Config;
+use super::Piece;
+use super::RotationSystem;
+
+
+/// The one-sided trominoes.
+const TROMINOES: &[&[&str]] = &[&["###"], &["#.", "##"]];
+
+/// The one-sided pentominoes.
+#[rustfmt::skip]
+const PENTOMINOES: &[&[&str]] = &[
+  &[".##", "##.", ".#."],   // F
+  &["##.", ".##", ".#."],   // F'
+  &["#####"],               // I
+  &["#...", "####"],        // L
+  &["...#", "####"],        // L'
+  &["##..", ".###"],        // N
+  &["..##", "###."],        // N'
+  &["##", "##", "#."],      // P
+  &["##", "##", ".#"],      // P'
+  &["###", ".#.", ".#."],   // T
+  &["#.#", "###"],          // U
+  &["#..", "#..", "###"],   // V
+  &["#..", "##.", ".##"],   // W
+  &[".#.", "###", ".#."],   // X
+  &["..#.", "####"],        // Y
+  &[".#..", "####"],        // Y'
+  &["##.", ".#.", ".##"],   // Z
+  &[".##", ".#.", "##."],   // Z'
+];
+
+
+/// The set of stones to play with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoneSet {
+  /// The seven classic stones made up of four pieces each.
+  #[default]
+  Tetrominoes,
+  /// The two stones made up of three pieces each.
+  Trominoes,
+  /// The eighteen stones made up of five pieces each.
+  Pentominoes,
+  /// The stones defined by the `stones` option.
+  Custom,
+}
+
+impl StoneSet {
+  /// Retrieve the textual representation of the stone set, as used in
+  /// configuration files.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Tetrominoes => "tetrominoes",
+      Self::Trominoes => "trominoes",
+      Self::Pentominoes => "pentominoes",
+      Self::Custom => "custom",
+    }
+  }
+}
+
+impl FromStr for StoneSet {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "tetrominoes" => Ok(Self::Tetrominoes),
+      "trominoes" => Ok(Self::Trominoes),
+      "pentominoes" => Ok(Self::Pentominoes),
+      "custom" => Ok(Self::Custom),
+      _ => bail!("unsupported stone set `{s}`"),
+    }
+  }
+}
+
+
+/// The color of the pieces of a stone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PieceColor {
+  /// Red.
+  Red,
+  /// Green.
+  Green,
+  /// Yellow.
+  Yellow,
+  /// Violet.
+  Violet,
+  /// Blue.
+  Blue,
+  /// Cyan.
+  Cyan,
+  /// Gray (or orange, in dark mode).
+  Gray,
+}
+
+impl PieceColor {
+  /// Retrieve the index of the color in [`Piece::COLORS`].
+  #[inline]
+  fn index(&self) -> u8 {
+    *self as u8
+  }
+}
+
+
+/// The definition of a custom stone.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StoneConfig {
+  /// The shape of the stone, as a list of rows from top to bottom, with
+  /// `#` marking a piece and `.` marking empty space.
+  pub shape: Vec<String>,
+  /// The color of the stone's pieces. If not set, a color is picked
+  /// based on the stone's position in the list.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub color: Option<PieceColor>,
+}
+
+
+/// The template of a stone, i.e., the locations of its pieces.
+pub(super) type StoneTemplate = Box<[Point<i8>]>;
+
+
+/// Parse the textual representation of a stone's shape into a
+/// template, with its lower left corner at the origin.
+fn parse_shape<S>(shape: &[S]) -> Result<StoneTemplate>
+where
+  S: AsRef<str>,
+{
+  let rows = shape.len();
+  let mut template = Vec::new();
+
+  for (i, row) in shape.iter().enumerate() {
+    for (x, c) in row.as_ref().chars().enumerate() {
+      match c {
+        '#' => {
+          let x = i8::try_from(x).context("stone is too wide")?;
+          let y = i8::try_from(rows - 1 - i).context("stone is too tall")?;
+          let () = template.push(Point::new(x, y));
+        },
+        '.' | ' ' => (),
+        _ => bail!("encountered invalid character `{c}` in stone shape"),
+      }
+    }
+  }
+
+  ensure!(!template.is_empty(), "stone does not contain any pieces");
+
+  // Shift the stone so that its lower left corner is at the origin.
+  // SANITY: We checked that the template is not empty.
+  let x_min = template.iter().map(|p| p.x).min().unwrap();
+  let y_min = template.iter().map(|p| p.y).min().unwrap();
+  let () = template
+    .iter_mut()
+    .for_each(|p| *p -= Point::new(x_min, y_min));
+
+  // Make sure that all pieces are connected horizontally or vertically.
+  let mut reached = vec![template[0]];
+  let mut i = 0;
+  while let Some(p) = reached.get(i).copied() {
+    for neighbor in [
+      p + Point::new(1, 0),
+      p - Point::new(1, 0),
+      p + Point::new(0, 1),
+      p - Point::new(0, 1),
+    ] {
+      if template.contains(&neighbor) && !reached.contains(&neighbor) {
+        let () = reached.push(neighbor);
+      }
+    }
+    i += 1;
+  }
+  ensure!(
+    reached.len() == template.len(),
+    "stone pieces are not all connected"
+  );
+
+  Ok(template.into_boxed_slice())
+}
+
+
+/// Load the templates of the stones to play with, along with the index
+/// of their color, as described by the provided configuration.
+pub(super) fn load_templates(config: &Config) -> Result<Box<[(StoneTemplate, u8)]>> {
+  /// Pick the color of the stone at the given index, skipping the
+  /// one reserved for garbage.
+  fn default_color(index: usize) -> u8 {
+    (index % usize::from(Piece::GARBAGE_COLOR_IDX)) as u8
+  }
+
+  fn with_colors<I>(templates: I) -> Box<[(StoneTemplate, u8)]>
+  where
+    I: IntoIterator<Item = StoneTemplate>,
+  {
+    templates
+      .into_iter()
+      .enumerate()
+      .map(|(i, template)| (template, default_color(i)))
+      .collect()
+  }
+
+  fn parse_all(shapes: &[&[&str]]) -> Box<[(StoneTemplate, u8)]> {
+    // SANITY: Our built-in shapes are known to be valid.
+    with_colors(shapes.iter().map(|shape| parse_shape(shape).unwrap()))
+  }
+
+  let templates = match config.stone_set {
+    StoneSet::Tetrominoes => with_colors(default_templates(config.rotation_system)),
+    StoneSet::Trominoes => parse_all(TROMINOES),
+    StoneSet::Pentominoes => parse_all(PENTOMINOES),
+    StoneSet::Custom => {
+      ensure!(!config.stones.is_empty(), "no custom stones defined");
+
+      config
+        .stones
+        .iter()
+        .enumerate()
+        .map(|(i, stone)| {
+          let template = parse_shape(&stone.shape)
+            .with_context(|| format!("custom stone #{} is invalid", i + 1))?;
+          let color = stone
+            .color
+            .map(|color| color.index())
+            .unwrap_or_else(|| default_color(i));
+          Ok((template, color))
+        })
+        .collect::<Result<_>>()?
+    },
+  };
+
+  for (i, (template, _color)) in templates.iter().enumerate() {
+    // SANITY: Templates are never empty.
+    let w = i16::from(template.iter().map(|p| p.x).max().unwrap()) + 1;
+    let h = i16::from(template.iter().map(|p| p.y).max().unwrap()) + 1;
+    ensure!(
+      w <= config.field_width && h <= config.field_height,
+      "stone #{} ({w}x{h}) does not fit into field ({}x{})",
+      i + 1,
+      config.field_width,
+      config.field_height
+    );
+  }
+
+  Ok(templates)
+}
+
+
+/// Retrieve the templates of the seven classic stones.
+///
+/// With SRS, stones are in the orientation mandated by it, with all of
+/// them spawning flat and the J, L, and T stones pointing up.
+fn default_templates(rotation_system: RotationSystem) -> [StoneTemplate; 7] {
+  #[rustfmt::skip]
+  let mut templates = [
+    vec![Point::new(0, 0), Point::new(1, 0), Point::new(1, 1), Point::new(0, 1)].into_boxed_slice(), // O
+    vec![Point::new(0, 0), Point::new(1, 0), Point::new(1, 1), Point::new(2, 1)].into_boxed_slice(), // S
+    vec![Point::new(0, 1), Point::new(1, 1), Point::new(1, 0), Point::new(2, 0)].into_boxed_slice(), // Z
+    vec![Point::new(0, 0), Point::new(0, 1), Point::new(0, 2), Point::new(0, 3)].into_boxed_slice(), // I
+    vec![Point::new(0, 0), Point::new(1, 0), Point::new(1, 1), Point::new(2, 0)].into_boxed_slice(), // T
+    vec![Point::new(0, 0), Point::new(1, 0), Point::new(0, 1), Point::new(0, 2)].into_boxed_slice(), // J
+    vec![Point::new(0, 0), Point::new(1, 0), Point::new(1, 1), Point::new(1, 2)].into_boxed_slice(), // L
+  ];
+
+  if rotation_system == RotationSystem::Srs {
+    #[rustfmt::skip]
+    let [i, j, l] = [
+      vec![Point::new(0, 0), Point::new(1, 0), Point::new(2, 0), Point::new(3, 0)].into_boxed_slice(), // I
+      vec![Point::new(0, 0), Point::new(1, 0), Point::new(2, 0), Point::new(0, 1)].into_boxed_slice(), // J
+      vec![Point::new(0, 0), Point::new(1, 0), Point::new(2, 0), Point::new(2, 1)].into_boxed_slice(), // L
+    ];
+    templates[3] = i;
+    templates[5] = j;
+    templates[6] = l;
+  }
+  templates
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we can convert a [`StoneSet`] to a string and back.
+  #[test]
+  fn stone_set_str_conversion() {
+    for set in [
+      StoneSet::Tetrominoes,
+      StoneSet::Trominoes,
+      StoneSet::Pentominoes,
+      StoneSet::Custom,
+    ] {
+      assert_eq!(StoneSet::from_str(set.as_str()).unwrap(), set);
+    }
+
+    assert!(StoneSet::from_str("foobar").is_err());
+  }
+
+  /// Make sure that we can parse stone shapes.
+  #[test]
+  fn shape_parsing() {
+    let template = parse_shape(&["..", ".#.", "###"]).unwrap();
+    assert_eq!(
+      &*template,
+      &[
+        Point::new(1, 1),
+        Point::new(0, 0),
+        Point::new(1, 0),
+        Point::new(2, 0)
+      ]
+    );
+
+    let template = parse_shape(&["  #", "  #"]).unwrap();
+    assert_eq!(&*template, &[Point::new(0, 1), Point::new(0, 0)]);
+
+    assert!(parse_shape::<&str>(&[]).is_err());
+    assert!(parse_shape(&["..."]).is_err());
+    assert!(parse_shape(&["#x"]).is_err());
+    assert!(parse_shape(&["#.#"]).is_err());
+    assert!(parse_shape(&["#.", ".#"]).is_err());
+  }
+
+  /// Check that all built-in stone sets are valid.
+  #[test]
+  fn builtin_sets() {
+    for (set, count) in [
+      (StoneSet::Tetrominoes, 7),
+      (StoneSet::Trominoes, 2),
+      (StoneSet::Pentominoes, 18),
+    ] {
+      let config = Config {
+        stone_set: set,
+        ..Default::default()
+      };
+      let templates = load_templates(&config).unwrap();
+      assert_eq!(templates.len(), count);
+    }
+  }
+
+  /// Check that all classic stones spawn flat with SRS.
+  #[test]
+  fn srs_spawn_orientation() {
+    for template in default_templates(RotationSystem::Srs) {
+      // SANITY: Templates are never empty.
+      let w = template.iter().map(|p| p.x).max().unwrap() + 1;
+      let h = template.iter().map(|p| p.y).max().unwrap() + 1;
+      assert!(w >= h, "{template:?}");
+    }
+
+    // The T stone points up.
+    let t = &default_templates(RotationSystem::Srs)[4];
+    assert!(t.contains(&Point::new(1, 1)));
+  }
+
+  /// Make sure that custom stones are loaded and validated.
+  #[test]
+  fn custom_stones() {
+    let mut config = Config {
+      stone_set: StoneSet::Custom,
+      ..Default::default()
+    };
+    assert!(load_templates(&config).is_err());
+
+    config.stones = vec![
+      StoneConfig {
+        shape: vec!["#".to_string()],
+        color: Some(PieceColor::Blue),
+      },
+      StoneConfig {
+        shape: vec!["##".to_string()],
+        color: None,
+      },
+    ];
+    let templates = load_templates(&config).unwrap();
+    assert_eq!(templates.len(), 2);
+    assert_eq!(templates[0].1, PieceColor::Blue.index());
+    assert_eq!(templates[1].1, 1);
+
+    config.stones[1].shape = vec!["#".repeat(11)];
+    assert!(load_templates(&config).is_err());
+  }
+}
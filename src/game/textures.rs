@@ -0,0 +1,62 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::io::Cursor;
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use image::DynamicImage;
+use image::ImageFormat;
+use image::ImageReader;
+
+use xgl::sys;
+
+use crate::Texture;
+use crate::TextureBuilderExt as _;
+
+use super::data;
+
+
+/// Decode the PNG image contained in the provided buffer.
+fn decode_png(data: &[u8]) -> Result<DynamicImage> {
+  let reader = Cursor::new(data);
+  let image = ImageReader::with_format(reader, ImageFormat::Png).decode()?;
+  Ok(image)
+}
+
+
+/// The textures used for rendering a game.
+///
+/// These are the only GPU resources the game's components make use
+/// of. They are owned by the view and handed to the components when
+/// they are rendered.
+#[derive(Debug)]
+pub(super) struct Textures {
+  /// The texture used for each piece of a stone, for the walls of the
+  /// field, and for each "pixel" of rendered text.
+  pub piece: Rc<Texture>,
+  /// The texture used for the back of the field.
+  pub field_back: Rc<Texture>,
+}
+
+impl Textures {
+  /// Load all textures using the provided context.
+  pub fn new(context: &sys::Context) -> Result<Self> {
+    let piece = decode_png(data::TETRIS_FIELD_PIECE_TEXTURE)?;
+    let piece = Texture::builder()
+      .set_context(context)
+      .from_dynamic_image(&piece)?;
+
+    let field_back = decode_png(data::TETRIS_FIELD_BACK_TEXTURE)?;
+    let field_back = Texture::builder()
+      .set_context(context)
+      .from_dynamic_image(&field_back)?;
+
+    let slf = Self {
+      piece: Rc::new(piece),
+      field_back: Rc::new(field_back),
+    };
+    Ok(slf)
+  }
+}
@@ -99,8 +99,7 @@ impl Font {
     }
   }
 
-  // Determine the width of the given string when rendered.
-  #[cfg(test)]
+  /// Determine the width of the given string when rendered.
   pub fn str_width(&self, s: &[u8]) -> i16 {
     s.iter()
       .map(|c| {
@@ -0,0 +1,459 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+#![cfg_attr(target_arch = "wasm32", expect(dead_code, unused_imports))]
+
+use std::cmp::Ordering;
+use std::fs::create_dir_all;
+use std::fs::read_to_string;
+use std::fs::write;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use anyhow::Result;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use toml_edit::de::from_str as from_toml_str;
+use toml_edit::ser::to_string_pretty as to_toml_string;
+
+use crate::game::Mode;
+use crate::game::Outcome;
+use crate::game::Scoring;
+
+
+/// The maximum number of entries kept per ranking.
+pub(crate) const MAX_ENTRIES: usize = 10;
+/// The maximum length of a player's name, in characters.
+pub(crate) const MAX_NAME_LEN: usize = 8;
+/// The name recorded for games played by the AI.
+const AI_NAME: &str = "AI";
+
+
+/// Retrieve the default path to the program's high score file.
+#[cfg(not(target_arch = "wasm32"))]
+fn default_high_scores_path() -> Result<PathBuf> {
+  use dirs::data_dir;
+
+  let path = data_dir()
+    .context("unable to determine data directory")?
+    .join("tetromino")
+    .join("highscores.toml");
+
+  Ok(path)
+}
+
+
+/// The settings under which a score was achieved. Scores are only
+/// ever ranked against others achieved under the same settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct Category {
+  /// The mode played.
+  pub mode: Mode,
+  /// The width of the field.
+  pub field_width: i16,
+  /// The height of the field.
+  pub field_height: i16,
+  /// The level the game started at.
+  pub start_level: u16,
+  /// The rules used for awarding points. Scores recorded before the
+  /// rules were tracked were earned under the default ones.
+  #[serde(default)]
+  pub scoring: Scoring,
+  /// The number of lines to clear, for [`Mode::Sprint`] games. Zero
+  /// for all other modes.
+  #[serde(default)]
+  pub sprint_lines: u16,
+  /// The time to score points in, in seconds, for [`Mode::Ultra`]
+  /// games. Zero for all other modes.
+  #[serde(default)]
+  pub ultra_time_secs: u32,
+  /// The number of lines of garbage the field started out with, for
+  /// [`Mode::Dig`] games. Zero for all other modes.
+  #[serde(default)]
+  pub dig_rows: u16,
+  /// Whether the game was played or assisted by the AI. AI runs are
+  /// ranked separately, so that they don't push out human players.
+  pub ai: bool,
+}
+
+impl From<&Outcome> for Category {
+  fn from(outcome: &Outcome) -> Self {
+    Self {
+      mode: outcome.mode,
+      field_width: outcome.field_width,
+      field_height: outcome.field_height,
+      start_level: outcome.start_level,
+      scoring: outcome.scoring,
+      sprint_lines: if outcome.mode == Mode::Sprint {
+        outcome.sprint_lines
+      } else {
+        0
+      },
+      ultra_time_secs: if outcome.mode == Mode::Ultra {
+        u32::try_from(outcome.ultra_time.as_secs()).unwrap_or(u32::MAX)
+      } else {
+        0
+      },
+      dig_rows: if outcome.mode == Mode::Dig {
+        outcome.dig_rows
+      } else {
+        0
+      },
+      ai: outcome.ai,
+    }
+  }
+}
+
+
+/// A single entry in a ranking.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct Entry {
+  /// The name of the player.
+  pub name: String,
+  /// The number of points earned.
+  pub points: u64,
+  /// The total number of lines cleared.
+  pub lines: u32,
+  /// The level reached.
+  pub level: u16,
+  /// The time played, in milliseconds.
+  pub time_ms: u64,
+}
+
+impl Entry {
+  fn new(name: &str, outcome: &Outcome) -> Self {
+    Self {
+      name: name.chars().take(MAX_NAME_LEN).collect(),
+      points: outcome.points,
+      lines: outcome.lines,
+      level: outcome.level,
+      time_ms: u64::try_from(outcome.time.as_millis()).unwrap_or(u64::MAX),
+    }
+  }
+
+  /// Retrieve the time played.
+  #[inline]
+  pub fn time(&self) -> Duration {
+    Duration::from_millis(self.time_ms)
+  }
+}
+
+
+/// Compare two entries achieved in the given mode, with better ones
+/// ordering first.
+fn compare(mode: Mode, lhs: &Entry, rhs: &Entry) -> Ordering {
+  match mode {
+    Mode::Marathon | Mode::Ultra => rhs
+      .points
+      .cmp(&lhs.points)
+      .then_with(|| rhs.lines.cmp(&lhs.lines)),
+    Mode::Sprint | Mode::Dig => lhs
+      .time_ms
+      .cmp(&rhs.time_ms)
+      .then_with(|| rhs.points.cmp(&lhs.points)),
+  }
+}
+
+
+/// The ranking of a single category.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+struct Ranking {
+  /// The category the ranking is for.
+  category: Category,
+  /// The entries of the ranking, from best to worst.
+  entries: Vec<Entry>,
+}
+
+
+/// The on-disk representation of [`HighScores`].
+#[derive(Default, Deserialize, Serialize)]
+struct HighScoresFile {
+  #[serde(default)]
+  rankings: Vec<Ranking>,
+}
+
+
+/// A table of high scores, ranked by category.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct HighScores {
+  /// The rankings of all categories played so far.
+  rankings: Vec<Ranking>,
+}
+
+impl HighScores {
+  /// Load high scores from the file at the given path.
+  ///
+  /// A missing file is treated as an empty table.
+  fn load_from(path: &Path) -> Result<Self> {
+    let content = match read_to_string(path) {
+      Ok(content) => content,
+      Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+      e @ Err(..) => {
+        e.with_context(|| format!("failed to read high score file `{}`", path.display()))?
+      },
+    };
+
+    let HighScoresFile { rankings } = from_toml_str(&content)
+      .with_context(|| format!("failed to parse high score file `{}`", path.display()))?;
+    Ok(Self { rankings })
+  }
+
+  /// Save high scores to a file at the given path.
+  fn save_to(&self, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+      let () = create_dir_all(dir)
+        .with_context(|| format!("failed to create directory `{}`", dir.display()))?;
+    }
+
+    let file = HighScoresFile {
+      rankings: self.rankings.clone(),
+    };
+    let content = to_toml_string(&file).context("failed to serialize high scores")?;
+    let () = write(path, content)
+      .with_context(|| format!("failed to write high score file `{}`", path.display()))?;
+    Ok(())
+  }
+
+  /// Retrieve the ranked entries of the given category.
+  pub fn entries(&self, category: &Category) -> &[Entry] {
+    self
+      .rankings
+      .iter()
+      .find(|ranking| ranking.category == *category)
+      .map(|ranking| ranking.entries.as_slice())
+      .unwrap_or_default()
+  }
+
+  /// Determine the rank (zero based) the given outcome would achieve,
+  /// if any.
+  pub fn rank(&self, outcome: &Outcome) -> Option<usize> {
+    // Sprint and Dig games are ranked by time, which is meaningless
+    // unless the goal was actually reached.
+    if matches!(outcome.mode, Mode::Sprint | Mode::Dig) && !outcome.completed {
+      return None
+    }
+
+    let category = Category::from(outcome);
+    let entry = Entry::new("", outcome);
+    let rank = self
+      .entries(&category)
+      .iter()
+      .take_while(|other| compare(category.mode, other, &entry) != Ordering::Greater)
+      .count();
+    (rank < MAX_ENTRIES).then_some(rank)
+  }
+
+  /// Check whether the given outcome makes it into the ranking of its
+  /// category and, if so, whether the player should be asked for a
+  /// name.
+  #[inline]
+  pub fn needs_name(&self, outcome: &Outcome) -> bool {
+    !outcome.ai && self.rank(outcome).is_some()
+  }
+
+  /// Insert the given outcome into the ranking of its category,
+  /// returning its rank if it made it in.
+  ///
+  /// Games played by the AI are always recorded under the same name,
+  /// with `name` being ignored.
+  pub fn insert(&mut self, name: &str, outcome: &Outcome) -> Option<usize> {
+    let rank = self.rank(outcome)?;
+    let category = Category::from(outcome);
+    let name = if outcome.ai { AI_NAME } else { name };
+    let entry = Entry::new(name, outcome);
+
+    let ranking = if let Some(index) = self
+      .rankings
+      .iter()
+      .position(|ranking| ranking.category == category)
+    {
+      &mut self.rankings[index]
+    } else {
+      let ranking = Ranking {
+        category,
+        entries: Vec::new(),
+      };
+      let () = self.rankings.push(ranking);
+      // SANITY: We just pushed an element.
+      self.rankings.last_mut().unwrap()
+    };
+
+    let () = ranking.entries.insert(rank, entry);
+    let () = ranking.entries.truncate(MAX_ENTRIES);
+    Some(rank)
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HighScores {
+  /// Load high scores from their default path on the file system.
+  pub fn load() -> Result<Self> {
+    let path = default_high_scores_path().context("failed to retrieve program data path")?;
+    Self::load_from(&path)
+  }
+
+  /// Save high scores to their default path on the file system.
+  pub fn save(&self) -> Result<()> {
+    let path = default_high_scores_path().context("failed to retrieve program data path")?;
+    self.save_to(&path)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use tempfile::NamedTempFile;
+  use tempfile::TempDir;
+
+
+  fn outcome(mode: Mode, points: u64, secs: u64) -> Outcome {
+    Outcome {
+      mode,
+      field_width: 10,
+      field_height: 20,
+      start_level: 1,
+      scoring: Scoring::Legacy,
+      level: 1,
+      points,
+      lines: 0,
+      time: Duration::from_secs(secs),
+      completed: true,
+      sprint_lines: 40,
+      ultra_time: Duration::from_secs(120),
+      dig_rows: 10,
+      dig_left: 0,
+      ai: false,
+    }
+  }
+
+
+  /// Check that outcomes get ranked as expected.
+  #[test]
+  fn ranking() {
+    let mut scores = HighScores::default();
+    assert_eq!(
+      scores.insert("a", &outcome(Mode::Marathon, 100, 0)),
+      Some(0)
+    );
+    assert_eq!(
+      scores.insert("b", &outcome(Mode::Marathon, 300, 0)),
+      Some(0)
+    );
+    assert_eq!(
+      scores.insert("c", &outcome(Mode::Marathon, 200, 0)),
+      Some(1)
+    );
+    // Ties are ranked below existing entries.
+    assert_eq!(
+      scores.insert("d", &outcome(Mode::Marathon, 200, 0)),
+      Some(2)
+    );
+
+    let category = Category::from(&outcome(Mode::Marathon, 0, 0));
+    let names = scores
+      .entries(&category)
+      .iter()
+      .map(|entry| entry.name.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(names, ["b", "c", "d", "a"]);
+
+    // Sprint games are ranked by time and only if completed.
+    assert_eq!(scores.insert("a", &outcome(Mode::Sprint, 0, 60)), Some(0));
+    assert_eq!(scores.insert("b", &outcome(Mode::Sprint, 0, 50)), Some(0));
+    let mut failed = outcome(Mode::Sprint, 0, 10);
+    failed.completed = false;
+    assert_eq!(scores.rank(&failed), None);
+  }
+
+  /// Make sure that rankings are capped and kept separate by category.
+  #[test]
+  fn categories() {
+    let mut scores = HighScores::default();
+    for i in 0..MAX_ENTRIES as u64 {
+      assert!(
+        scores
+          .insert("human", &outcome(Mode::Marathon, 100 + i, 0))
+          .is_some()
+      );
+    }
+    assert_eq!(scores.rank(&outcome(Mode::Marathon, 100, 0)), None);
+    assert!(!scores.needs_name(&outcome(Mode::Marathon, 100, 0)));
+    assert!(scores.needs_name(&outcome(Mode::Marathon, 1000, 0)));
+
+    let mut ai = outcome(Mode::Marathon, 1, 0);
+    ai.ai = true;
+    assert!(!scores.needs_name(&ai));
+    assert_eq!(scores.insert("ignored", &ai), Some(0));
+    assert_eq!(scores.entries(&Category::from(&ai))[0].name, AI_NAME);
+
+    let mut wide = outcome(Mode::Marathon, 1, 0);
+    wide.field_width = 12;
+    assert_eq!(scores.rank(&wide), Some(0));
+
+    let mut guideline = outcome(Mode::Marathon, 1, 0);
+    guideline.scoring = Scoring::Guideline;
+    assert_eq!(scores.rank(&guideline), Some(0));
+
+    // Goals only separate categories of the modes they apply to.
+    let mut sprint = outcome(Mode::Marathon, 1, 0);
+    sprint.sprint_lines = 20;
+    assert_eq!(scores.rank(&sprint), None);
+
+    let _rank = scores.insert("human", &outcome(Mode::Sprint, 0, 60));
+    let mut sprint = outcome(Mode::Sprint, 0, 120);
+    assert_eq!(scores.rank(&sprint), Some(1));
+    sprint.sprint_lines = 20;
+    assert_eq!(scores.rank(&sprint), Some(0));
+
+    let _rank = scores.insert("human", &outcome(Mode::Ultra, 100, 120));
+    let mut ultra = outcome(Mode::Ultra, 50, 120);
+    assert_eq!(scores.rank(&ultra), Some(1));
+    ultra.ultra_time = Duration::from_secs(60);
+    assert_eq!(scores.rank(&ultra), Some(0));
+
+    let category = Category::from(&outcome(Mode::Marathon, 0, 0));
+    assert_eq!(scores.entries(&category).len(), MAX_ENTRIES);
+  }
+
+  /// Check that [`HighScores`] survive a round trip through the file
+  /// system.
+  #[test]
+  fn save_load() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("missing.toml");
+    assert_eq!(HighScores::load_from(&path).unwrap(), HighScores::default());
+
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path();
+
+    let mut scores = HighScores::default();
+    let _rank = scores.insert("someone", &outcome(Mode::Ultra, 42, 180));
+    let _rank = scores.insert("too long a name", &outcome(Mode::Dig, 1, 30));
+    let () = scores.save_to(path).unwrap();
+
+    let loaded = HighScores::load_from(path).unwrap();
+    assert_eq!(loaded, scores);
+    let category = Category::from(&outcome(Mode::Dig, 0, 0));
+    assert_eq!(loaded.entries(&category)[0].name, "too long");
+
+    // Files written before scoring rules were tracked can still be
+    // loaded.
+    let content = read_to_string(path).unwrap();
+    assert!(content.contains("scoring"));
+    let content = content
+      .lines()
+      .filter(|line| !line.starts_with("scoring"))
+      .collect::<Vec<_>>()
+      .join("\n");
+    let () = write(path, content).unwrap();
+    let loaded = HighScores::load_from(path).unwrap();
+    assert_eq!(loaded, scores);
+  }
+}
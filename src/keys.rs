@@ -21,13 +21,62 @@ mod imp {
   pub(crate) const KEY_MOVE_DOWN: Key = Key::KeyJ;
   pub(crate) const KEY_MOVE_RIGHT: Key = Key::KeyL;
   pub(crate) const KEY_DROP: Key = Key::Space;
+  pub(crate) const KEY_HOLD: Key = Key::KeyK;
   pub(crate) const KEY_RESTART: Key = Key::Backspace;
   pub(crate) const KEY_AUTO_PLAY: Key = Key::F2;
   pub(crate) const KEY_PAUSE: Key = Key::F3;
   pub(crate) const KEY_MODE: Key = Key::F4;
+  pub(crate) const KEY_HINT: Key = Key::F5;
   #[cfg(feature = "debug")]
   pub(crate) const KEY_DEBUG: Key = Key::F11;
   pub(crate) const KEY_QUIT: Key = Key::KeyQ;
+  pub(crate) const KEY_CONFIRM: Key = Key::Enter;
+  pub(crate) const KEY_ERASE: Key = Key::Backspace;
+
+  /// Map a key to the (upper case) character it represents when
+  /// entering a name, if any.
+  pub(crate) fn key_char(key: &Key) -> Option<u8> {
+    const CHARS: [(Key, u8); 36] = [
+      (Key::KeyA, b'A'),
+      (Key::KeyB, b'B'),
+      (Key::KeyC, b'C'),
+      (Key::KeyD, b'D'),
+      (Key::KeyE, b'E'),
+      (Key::KeyF, b'F'),
+      (Key::KeyG, b'G'),
+      (Key::KeyH, b'H'),
+      (Key::KeyI, b'I'),
+      (Key::KeyJ, b'J'),
+      (Key::KeyK, b'K'),
+      (Key::KeyL, b'L'),
+      (Key::KeyM, b'M'),
+      (Key::KeyN, b'N'),
+      (Key::KeyO, b'O'),
+      (Key::KeyP, b'P'),
+      (Key::KeyQ, b'Q'),
+      (Key::KeyR, b'R'),
+      (Key::KeyS, b'S'),
+      (Key::KeyT, b'T'),
+      (Key::KeyU, b'U'),
+      (Key::KeyV, b'V'),
+      (Key::KeyW, b'W'),
+      (Key::KeyX, b'X'),
+      (Key::KeyY, b'Y'),
+      (Key::KeyZ, b'Z'),
+      (Key::Digit0, b'0'),
+      (Key::Digit1, b'1'),
+      (Key::Digit2, b'2'),
+      (Key::Digit3, b'3'),
+      (Key::Digit4, b'4'),
+      (Key::Digit5, b'5'),
+      (Key::Digit6, b'6'),
+      (Key::Digit7, b'7'),
+      (Key::Digit8, b'8'),
+      (Key::Digit9, b'9'),
+    ];
+
+    CHARS.iter().find_map(|(k, c)| (k == key).then_some(*c))
+  }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -40,13 +89,26 @@ mod imp {
   pub(crate) const KEY_MOVE_DOWN: &str = "j";
   pub(crate) const KEY_MOVE_RIGHT: &str = "l";
   pub(crate) const KEY_DROP: &str = " ";
+  pub(crate) const KEY_HOLD: &str = "k";
   pub(crate) const KEY_RESTART: &str = "Backspace";
   pub(crate) const KEY_AUTO_PLAY: &str = "F2";
   pub(crate) const KEY_PAUSE: &str = "F3";
   pub(crate) const KEY_MODE: &str = "F4";
+  pub(crate) const KEY_HINT: &str = "F5";
   #[cfg(feature = "debug")]
   pub(crate) const KEY_DEBUG: &str = "F11";
   pub(crate) const KEY_QUIT: &str = "q";
+  pub(crate) const KEY_CONFIRM: &str = "Enter";
+  pub(crate) const KEY_ERASE: &str = "Backspace";
+
+  /// Map a key to the (upper case) character it represents when
+  /// entering a name, if any.
+  pub(crate) fn key_char(key: &Key) -> Option<u8> {
+    match key.as_bytes() {
+      [c] if c.is_ascii_alphanumeric() => Some(c.to_ascii_uppercase()),
+      _ => None,
+    }
+  }
 }
 
 pub(crate) use imp::*;
@@ -13,23 +13,35 @@ mod redefine;
 
 mod app;
 mod change;
+mod clock;
 mod config;
 mod game;
 mod gl;
 mod guard;
+mod highscore;
 mod instant;
 mod keys;
 mod mode;
 mod point;
 mod rand;
 mod rect;
+mod replay;
+#[cfg(not(target_arch = "wasm32"))]
+mod savegame;
+#[cfg(not(target_arch = "wasm32"))]
+mod simulate;
 mod tick;
+#[cfg(not(target_arch = "wasm32"))]
+mod tune;
 mod util;
 #[cfg(target_arch = "wasm32")]
 mod web;
 #[cfg(not(target_arch = "wasm32"))]
 mod winit;
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
 #[cfg(not(target_arch = "wasm32"))]
 use anyhow::Result;
 
@@ -45,21 +57,71 @@ use crate::rand::Rng;
 use crate::rect::Rect;
 
 pub use crate::change::Change;
+pub use crate::clock::Clock;
+pub use crate::clock::Frame;
 #[doc(hidden)]
 pub use crate::config::Config;
+pub use crate::game::AiWeights;
 pub use crate::game::Config as GameConfig;
+pub use crate::game::Difficulty;
+pub use crate::game::Engine;
 pub use crate::game::Game;
+pub use crate::game::Gravity;
+pub use crate::game::Mode;
+pub use crate::game::Outcome;
+pub use crate::game::PieceColor;
+pub use crate::game::Randomizer;
+pub use crate::game::RotationSystem;
+pub use crate::game::Scoring;
+pub use crate::game::SearchStats;
+pub use crate::game::Snapshot;
+pub use crate::game::StoneConfig;
+pub use crate::game::StoneSet;
 pub use crate::instant::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::simulate::Simulation;
 pub use crate::tick::Tick;
 #[cfg(not(target_arch = "wasm32"))]
+pub use crate::tune::Tuning;
+#[cfg(not(target_arch = "wasm32"))]
 pub use crate::winit::Context;
 #[cfg(not(target_arch = "wasm32"))]
 pub use crate::winit::Window;
 
 
+/// The way in which the inputs of a game are recorded or played back.
+#[doc(hidden)]
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub enum ReplayMode {
+  /// Inputs are neither recorded nor played back.
+  Off,
+  /// Inputs are recorded to the file at the given path.
+  Record(PathBuf),
+  /// A game is played back from the replay file at the given path.
+  Play(PathBuf),
+}
+
+
+// This function is really only meant to be used by the main program.
+#[doc(hidden)]
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run(replay: ReplayMode) -> Result<()> {
+  winit::run_app(replay)
+}
+
+
+// This function is really only meant to be used by the main program.
+#[doc(hidden)]
+#[cfg(not(target_arch = "wasm32"))]
+pub fn simulate(sim: &Simulation) -> Result<()> {
+  simulate::simulate(sim)
+}
+
+
 // This function is really only meant to be used by the main program.
 #[doc(hidden)]
 #[cfg(not(target_arch = "wasm32"))]
-pub fn run() -> Result<()> {
-  winit::run_app()
+pub fn tune(tuning: &Tuning) -> Result<()> {
+  tune::tune(tuning)
 }
@@ -7,14 +7,26 @@
 compile_error!("Wasm target does only support library target; build with --lib");
 
 use std::env::args_os;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::str::FromStr;
 
+use anyhow::Context as _;
+use anyhow::Error;
 use anyhow::Result;
 use anyhow::bail;
+use anyhow::ensure;
 
 use toml_edit::ser::to_string_pretty as to_toml_string;
 
 use tetromino::Config;
+use tetromino::Engine;
+use tetromino::ReplayMode;
+use tetromino::Simulation;
+use tetromino::Tuning;
 use tetromino::run;
+use tetromino::simulate;
+use tetromino::tune;
 
 
 fn default_config() -> String {
@@ -29,6 +41,100 @@ fn default_config() -> String {
 }
 
 
+/// Parse the value of the given command line option.
+fn parse_value<T>(option: &str, value: Option<OsString>) -> Result<T>
+where
+  T: FromStr,
+  T::Err: Into<Error>,
+{
+  let value = value.with_context(|| format!("option '{option}' requires a value"))?;
+  let value = value
+    .to_str()
+    .with_context(|| format!("value for option '{option}' is not valid UTF-8"))?;
+  T::from_str(value)
+    .map_err(Into::into)
+    .with_context(|| format!("invalid value '{value}' for option '{option}'"))
+}
+
+
+/// Parse the value of a field size option, of the form `WIDTHxHEIGHT`,
+/// for a field the AI is to play on.
+fn parse_field_size(option: &str, value: Option<OsString>) -> Result<(i16, i16)> {
+  let size = parse_value::<String>(option, value)?;
+  let (width, height) = size
+    .split_once('x')
+    .with_context(|| format!("field size '{size}' is not of the form WIDTHxHEIGHT"))?;
+  let width = parse_value(option, Some(width.into()))?;
+  let height = parse_value(option, Some(height.into()))?;
+  ensure!(
+    width <= Engine::MAX_AI_FIELD_WIDTH,
+    "field width {width} for option '{option}' exceeds maximum supported by the AI ({})",
+    Engine::MAX_AI_FIELD_WIDTH
+  );
+  Ok((width, height))
+}
+
+
+/// Parse the value of a seed option, which may list multiple seeds
+/// separated by commas.
+fn parse_seeds(option: &str, value: Option<OsString>) -> Result<Vec<u64>> {
+  let seeds = parse_value::<String>(option, value)?;
+  seeds
+    .split(',')
+    .map(|seed| parse_value(option, Some(seed.into())))
+    .collect()
+}
+
+
+/// Parse the arguments of the `--simulate` mode.
+fn parse_simulation<I>(mut args: I) -> Result<Simulation>
+where
+  I: Iterator<Item = OsString>,
+{
+  let mut sim = Simulation::default();
+
+  while let Some(arg) = args.next() {
+    match arg.to_str() {
+      Some(option @ "--simulate") => sim.games = parse_value(option, args.next())?,
+      Some(option @ "--seed") => sim.seeds.extend(parse_seeds(option, args.next())?),
+      Some(option @ "--max-stones") => sim.max_stones = parse_value(option, args.next())?,
+      Some(option @ "--field") => {
+        let () = sim.field_sizes.push(parse_field_size(option, args.next())?);
+      },
+      Some("--json") => sim.json = true,
+      _ => bail!("unexpected argument '{}' found", arg.to_string_lossy()),
+    }
+  }
+  Ok(sim)
+}
+
+
+/// Parse the arguments of the `--tune` mode.
+fn parse_tuning<I>(mut args: I) -> Result<Tuning>
+where
+  I: Iterator<Item = OsString>,
+{
+  let mut tuning = Tuning::default();
+
+  while let Some(arg) = args.next() {
+    match arg.to_str() {
+      Some(option @ "--tune") => tuning.generations = parse_value(option, args.next())?,
+      Some(option @ "--population") => tuning.population = parse_value(option, args.next())?,
+      Some(option @ "--games") => tuning.games = parse_value(option, args.next())?,
+      Some(option @ "--seed") => tuning.seed = parse_value(option, args.next())?,
+      Some(option @ "--field") => tuning.field_size = Some(parse_field_size(option, args.next())?),
+      Some(option @ "--max-stones") => tuning.max_stones = parse_value(option, args.next())?,
+      Some(option @ "--threads") => tuning.threads = Some(parse_value(option, args.next())?),
+      Some(option @ "--output") => {
+        tuning.output = Some(parse_value::<PathBuf>(option, args.next())?);
+      },
+      _ => bail!("unexpected argument '{}' found", arg.to_string_lossy()),
+    }
+  }
+  Ok(tuning)
+}
+
+
 fn main() -> Result<()> {
   // By convention the 0th argument contains the path to the program;
   // ignore it.
@@ -37,7 +143,7 @@ fn main() -> Result<()> {
   // Slightly ghetto argument parser helps us avoid unnecessary large
   // dependency on `clap`.
   match args().len() {
-    0 => run(),
+    0 => run(ReplayMode::Off),
     _ if args().any(|arg| arg == "--help" || arg == "-h") => {
       print!(
         "{name} {version} -- a graphical Tetris clone
@@ -46,8 +152,31 @@ USAGE:
   {name} [OPTIONS]
 
 OPTIONS:
-  -h, --help     Print help information
-  -V, --version  Print version information
+  --record <FILE>  Record the game played to FILE
+  --replay <FILE>  Play back the game recorded in FILE
+  --simulate <N>   Let the AI play N games without a window and report
+                   the results; accepts the following options:
+    --seed <SEED,..>    Seeds of the games; may be given multiple
+                        times; further games use seeds consecutive to
+                        the last one [default: 0]
+    --field <WxH>       Field size to play on; may be given multiple
+                        times [default: as configured]
+    --max-stones <N>    Stop games after N stones [default: 1000]
+    --json              Report the results as JSON
+  --tune <N>       Evolve the weights the AI uses over N generations of
+                   simulated games and save the best ones; accepts the
+                   following options:
+    --population <N>    Number of candidates per generation [default: 16]
+    --games <N>         Games per candidate and generation [default: 4]
+    --seed <SEED>       Seed from which all randomness is derived
+                        [default: 0]
+    --field <WxH>       Field size to play on [default: as configured]
+    --max-stones <N>    Stop games after N stones [default: 250]
+    --threads <N>       Number of threads to use [default: one per core]
+    --output <FILE>     File to save the weights to [default:
+                        tuned.toml next to the configuration file]
+  -h, --help       Print help information
+  -V, --version    Print version information
 
 CONFIG:
   The game reads the $XDG_CONFIG_DIR/tetromino/config.toml TOML
@@ -67,6 +196,24 @@ CONFIG:
       println!("{} {}", env!("CARGO_CRATE_NAME"), env!("VERSION"));
       Ok(())
     },
+    _ if args().any(|arg| arg == "--simulate") => {
+      let sim = parse_simulation(args())?;
+      simulate(&sim)
+    },
+    _ if args().any(|arg| arg == "--tune") => {
+      let tuning = parse_tuning(args())?;
+      tune(&tuning)
+    },
+    2 => {
+      // SANITY: We checked that there are two arguments.
+      let (option, path) = args().zip(args().skip(1)).next().unwrap();
+      let path = PathBuf::from(path);
+      match option.to_str() {
+        Some("--record") => run(ReplayMode::Record(path)),
+        Some("--replay") => run(ReplayMode::Play(path)),
+        _ => bail!("unexpected argument '{}' found", option.to_string_lossy()),
+      }
+    },
     _ => {
       let arg = args().next().unwrap();
       bail!("unexpected argument '{}' found", arg.to_string_lossy())
@@ -6,6 +6,11 @@ use std::ops::AddAssign;
 use std::ops::Sub;
 use std::ops::SubAssign;
 
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
 
 #[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
 pub(crate) struct Point<T> {
@@ -32,6 +37,32 @@ impl<T> Point<T> {
   }
 }
 
+// A point is serialized as a compact `[x, y]` pair.
+impl<T> Serialize for Point<T>
+where
+  T: Serialize,
+{
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    (&self.x, &self.y).serialize(serializer)
+  }
+}
+
+impl<'de, T> Deserialize<'de> for Point<T>
+where
+  T: Deserialize<'de>,
+{
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let (x, y) = <(T, T)>::deserialize(deserializer)?;
+    Ok(Self { x, y })
+  }
+}
+
 impl<T> From<(T, T)> for Point<T> {
   #[inline]
   fn from(other: (T, T)) -> Self {
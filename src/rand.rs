@@ -6,36 +6,53 @@
 
 use std::cell::Cell;
 
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use serde::de::Error as _;
+
 const MULTIPLIER: u64 = 6364136223846793005;
 const INCREMENT: u64 = 1442695040888963407;
 
 
-#[derive(Debug)]
+/// A pseudo random number generator.
+///
+/// The generator's state can be serialized, in which case it is
+/// represented in hexadecimal notation.
+#[derive(Clone, Debug)]
 pub(crate) struct Rng {
   state: Cell<u64>,
 }
 
-impl Rng {
-  #[cfg(not(target_arch = "wasm32"))]
-  pub fn new() -> Self {
-    use std::time::SystemTime;
-    use std::time::UNIX_EPOCH;
 
-    // SANITY: `UNIX_EPOCH` is earlier than *any* other `SystemTime`.
-    let seed = SystemTime::now()
-      .duration_since(UNIX_EPOCH)
-      .unwrap()
-      .as_nanos() as u64;
+/// Retrieve a seed suitable for seeding a [`Rng`] based on the current
+/// time.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn random_seed() -> u64 {
+  use std::time::SystemTime;
+  use std::time::UNIX_EPOCH;
 
-    Self::with_seed(seed)
-  }
+  // SANITY: `UNIX_EPOCH` is earlier than *any* other `SystemTime`.
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap()
+    .as_nanos() as u64
+}
 
-  #[cfg(target_arch = "wasm32")]
-  pub fn new() -> Self {
-    use crate::Instant;
+/// Retrieve a seed suitable for seeding a [`Rng`] based on the current
+/// time.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn random_seed() -> u64 {
+  use crate::Instant;
 
-    let seed = Instant::now().as_millis() as u64;
-    Self::with_seed(seed)
+  Instant::now().as_millis() as u64
+}
+
+
+impl Rng {
+  pub fn new() -> Self {
+    Self::with_seed(random_seed())
   }
 
   pub fn with_seed(seed: u64) -> Self {
@@ -46,6 +63,22 @@ impl Rng {
     }
   }
 
+  /// Create a new `Rng` seeded from this one.
+  ///
+  /// The new generator's sequence is fully determined by the state of
+  /// this one, making it suitable for deriving multiple independent
+  /// generators from a single seed.
+  pub fn fork(&self) -> Self {
+    let seed = (u64::from(self.rand_u32()) << 32) | u64::from(self.rand_u32());
+    Self::with_seed(seed)
+  }
+
+  /// Continue with the sequence of another generator, making this one
+  /// yield the same numbers that `other` would.
+  pub fn assume(&self, other: &Rng) {
+    let () = self.state.set(other.state.get());
+  }
+
   /// Generate a new pseudo random `u32` value.
   pub fn rand_u32(&self) -> u32 {
     fn rotr32(x: u32, r: usize) -> u32 {
@@ -64,11 +97,41 @@ impl Rng {
   }
 }
 
+impl Serialize for Rng {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    serializer.serialize_str(&format!("{:016x}", self.state.get()))
+  }
+}
+
+impl<'de> Deserialize<'de> for Rng {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let state = String::deserialize(deserializer)?;
+    let state = u64::from_str_radix(&state, 16).map_err(|_| {
+      D::Error::custom(format!(
+        "random number generator state `{state}` is malformed"
+      ))
+    })?;
+
+    Ok(Self {
+      state: Cell::new(state),
+    })
+  }
+}
+
 
 #[cfg(test)]
 mod tests {
   use std::collections::HashSet;
 
+  use toml_edit::de::from_str as from_toml_str;
+  use toml_edit::ser::to_string as to_toml_string;
+
   use super::*;
 
 
@@ -95,4 +158,44 @@ mod tests {
       assert_eq!(rng1.rand_u32(), rng2.rand_u32());
     })
   }
+
+  /// Make sure that forked `Rng` instances are deterministic but differ
+  /// from their parent.
+  #[test]
+  fn rng_fork() {
+    let rng1 = Rng::with_seed(42);
+    let rng2 = Rng::with_seed(42);
+    let fork1 = rng1.fork();
+    let fork2 = rng2.fork();
+
+    (0..10).for_each(|_| {
+      assert_eq!(fork1.rand_u32(), fork2.rand_u32());
+    });
+
+    let seq1 = (0..10).map(|_| rng1.rand_u32()).collect::<Vec<_>>();
+    let seq2 = (0..10).map(|_| fork1.rand_u32()).collect::<Vec<_>>();
+    assert_ne!(seq1, seq2);
+  }
+
+  /// Check that a deserialized `Rng` continues the sequence of the
+  /// serialized one.
+  #[test]
+  fn rng_serialization() {
+    #[derive(Deserialize, Serialize)]
+    struct Data {
+      rng: Rng,
+    }
+
+    let data = Data {
+      rng: Rng::with_seed(u64::MAX),
+    };
+    let _value = data.rng.rand_u32();
+    let toml = to_toml_string(&data).unwrap();
+    let copy = from_toml_str::<Data>(&toml).unwrap();
+
+    (0..10).for_each(|_| {
+      assert_eq!(data.rng.rand_u32(), copy.rng.rand_u32());
+    });
+    assert!(from_toml_str::<Data>("rng = \"xyz\"").is_err());
+  }
 }
@@ -0,0 +1,438 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+#![cfg_attr(target_arch = "wasm32", expect(dead_code))]
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::read_to_string;
+use std::fs::write;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Context as _;
+use anyhow::Error;
+use anyhow::Result;
+use anyhow::bail;
+use anyhow::ensure;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use toml_edit::de::from_str as from_toml_str;
+use toml_edit::ser::to_string_pretty as to_toml_string;
+
+use crate::Frame;
+use crate::game;
+
+
+/// An action performed on a game in response to user input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+  /// Rotate the active stone counter-clockwise.
+  RotateLeft,
+  /// Rotate the active stone clockwise.
+  RotateRight,
+  /// Move the active stone one unit to the left.
+  MoveLeft,
+  /// Move the active stone one unit down.
+  MoveDown,
+  /// Move the active stone one unit to the right.
+  MoveRight,
+  /// Drop the active stone.
+  Drop,
+  /// Put the active stone on hold.
+  Hold,
+  /// Restart the game.
+  Restart,
+  /// Enable or disable auto play, depending on its current state.
+  ToggleAutoPlay,
+  /// Pause or unpause the game, depending on its current state.
+  TogglePause,
+  /// Pause the game.
+  Pause,
+  /// Unpause the game.
+  Resume,
+  /// Switch between light and dark mode.
+  ToggleColorMode,
+  /// Show or hide the placement the AI suggests, depending on the
+  /// current state.
+  ToggleHint,
+}
+
+impl Action {
+  /// Retrieve the textual representation of the action, as used in
+  /// replay files.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::RotateLeft => "rotate_left",
+      Self::RotateRight => "rotate_right",
+      Self::MoveLeft => "move_left",
+      Self::MoveDown => "move_down",
+      Self::MoveRight => "move_right",
+      Self::Drop => "drop",
+      Self::Hold => "hold",
+      Self::Restart => "restart",
+      Self::ToggleAutoPlay => "toggle_auto_play",
+      Self::TogglePause => "toggle_pause",
+      Self::Pause => "pause",
+      Self::Resume => "resume",
+      Self::ToggleColorMode => "toggle_color_mode",
+      Self::ToggleHint => "toggle_hint",
+    }
+  }
+}
+
+impl FromStr for Action {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "rotate_left" => Ok(Self::RotateLeft),
+      "rotate_right" => Ok(Self::RotateRight),
+      "move_left" => Ok(Self::MoveLeft),
+      "move_down" => Ok(Self::MoveDown),
+      "move_right" => Ok(Self::MoveRight),
+      "drop" => Ok(Self::Drop),
+      "hold" => Ok(Self::Hold),
+      "restart" => Ok(Self::Restart),
+      "toggle_auto_play" => Ok(Self::ToggleAutoPlay),
+      "toggle_pause" => Ok(Self::TogglePause),
+      "pause" => Ok(Self::Pause),
+      "resume" => Ok(Self::Resume),
+      "toggle_color_mode" => Ok(Self::ToggleColorMode),
+      "toggle_hint" => Ok(Self::ToggleHint),
+      _ => bail!("unsupported action `{s}`"),
+    }
+  }
+}
+
+
+/// The actions performed at a single frame of the game.
+///
+/// An input is played back by advancing the game to `frame` and
+/// performing all actions afterwards.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Input {
+  /// The frame at which the actions were performed.
+  pub frame: Frame,
+  /// The actions performed.
+  pub actions: Vec<Action>,
+}
+
+impl Display for Input {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    let () = write!(f, "{}", self.frame)?;
+    for action in &self.actions {
+      let () = write!(f, " {}", action.as_str())?;
+    }
+    Ok(())
+  }
+}
+
+impl FromStr for Input {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut parts = s.split_ascii_whitespace();
+    let frame = parts.next().context("input is missing frame")?;
+    let frame = frame
+      .parse::<Frame>()
+      .with_context(|| format!("frame `{frame}` is malformed"))?;
+    let actions = parts.map(Action::from_str).collect::<Result<_>>()?;
+
+    let slf = Self { frame, actions };
+    Ok(slf)
+  }
+}
+
+
+/// The on-disk representation of a [`Replay`].
+#[derive(Deserialize, Serialize)]
+struct ReplayFile {
+  /// The seed of the game, in hexadecimal notation.
+  seed: String,
+  /// The number of frames the game ran for.
+  length: Frame,
+  /// The recorded inputs, in textual form.
+  inputs: Vec<String>,
+  /// The configuration of the game.
+  game: game::Config,
+  /// The weights the AI uses for evaluating its options.
+  #[serde(default)]
+  ai: game::AiWeights,
+}
+
+
+/// A recording of a game, from which it can be reproduced exactly.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Replay {
+  /// The seed the game's randomness was derived from.
+  seed: u64,
+  /// The configuration of the game.
+  config: game::Config,
+  /// The weights the AI uses for evaluating its options.
+  ai_weights: game::AiWeights,
+  /// The last frame the game was simulated up to.
+  length: Frame,
+  /// The recorded inputs, ordered by frame.
+  inputs: Vec<Input>,
+}
+
+impl Replay {
+  /// Create a replay for recording a game played with the given
+  /// configuration.
+  ///
+  /// The AI only plays deterministically when searching without a
+  /// time budget, so any budget is lifted for recorded games. Games
+  /// played by an external bot cannot be recorded at all, as nothing
+  /// guarantees that it decides the same way on playback.
+  pub fn new(seed: u64, config: game::Config, ai_weights: game::AiWeights) -> Result<Self> {
+    ensure!(
+      config.ai_bot.is_none(),
+      "games played by an external bot cannot be recorded"
+    );
+
+    let config = game::Config {
+      ai_time_budget_ms: 0,
+      ..config
+    };
+    let slf = Self {
+      seed,
+      config,
+      ai_weights,
+      length: 0,
+      inputs: Vec::new(),
+    };
+    Ok(slf)
+  }
+
+  /// Load a replay from the file at the given path.
+  pub fn load(path: &Path) -> Result<Self> {
+    let content = read_to_string(path)
+      .with_context(|| format!("failed to read replay file `{}`", path.display()))?;
+    let ReplayFile {
+      seed,
+      length,
+      inputs,
+      game,
+      ai,
+    } = from_toml_str(&content)
+      .with_context(|| format!("failed to parse replay file `{}`", path.display()))?;
+
+    let seed = u64::from_str_radix(&seed, 16)
+      .with_context(|| format!("replay seed `{seed}` is malformed"))?;
+    let inputs = inputs
+      .iter()
+      .map(|input| Input::from_str(input).with_context(|| format!("input `{input}` is invalid")))
+      .collect::<Result<Vec<_>>>()?;
+
+    if inputs.windows(2).any(|pair| pair[0].frame >= pair[1].frame) {
+      bail!("inputs of replay file `{}` are not ordered", path.display())
+    }
+    if inputs.last().is_some_and(|input| input.frame > length) {
+      bail!(
+        "inputs of replay file `{}` exceed its length",
+        path.display()
+      )
+    }
+
+    let slf = Self {
+      seed,
+      config: game,
+      ai_weights: ai,
+      length,
+      inputs,
+    };
+    Ok(slf)
+  }
+
+  /// Save the replay to a file at the given path.
+  pub fn save(&self, path: &Path) -> Result<()> {
+    let file = ReplayFile {
+      seed: format!("{:016x}", self.seed),
+      length: self.length,
+      inputs: self.inputs.iter().map(Input::to_string).collect(),
+      game: self.config.clone(),
+      ai: self.ai_weights,
+    };
+    let content = to_toml_string(&file).context("failed to serialize replay")?;
+    let () = write(path, content)
+      .with_context(|| format!("failed to write replay file `{}`", path.display()))?;
+    Ok(())
+  }
+
+  /// Record that the game has been simulated up to the given frame.
+  pub fn advance(&mut self, frame: Frame) {
+    self.length = self.length.max(frame);
+  }
+
+  /// Record an action performed at the given frame.
+  pub fn push_action(&mut self, frame: Frame, action: Action) {
+    let () = self.advance(frame);
+
+    match self.inputs.last_mut() {
+      Some(input) if input.frame == frame => input.actions.push(action),
+      _ => {
+        debug_assert!(self.inputs.last().is_none_or(|input| input.frame < frame));
+        let input = Input {
+          frame,
+          actions: vec![action],
+        };
+        let () = self.inputs.push(input);
+      },
+    }
+  }
+
+  /// Retrieve the seed the game's randomness was derived from.
+  #[inline]
+  pub fn seed(&self) -> u64 {
+    self.seed
+  }
+
+  /// Retrieve the configuration of the game.
+  #[inline]
+  pub fn config(&self) -> &game::Config {
+    &self.config
+  }
+
+  /// Retrieve the weights the AI uses for evaluating its options.
+  #[inline]
+  pub fn ai_weights(&self) -> &game::AiWeights {
+    &self.ai_weights
+  }
+
+  /// Retrieve the last frame the game was simulated up to.
+  #[inline]
+  pub fn length(&self) -> Frame {
+    self.length
+  }
+
+  /// Retrieve the recorded inputs.
+  #[inline]
+  pub fn inputs(&self) -> &[Input] {
+    &self.inputs
+  }
+}
+
+
+/// The state of recording or playing back the inputs of a game.
+#[derive(Debug)]
+pub(crate) enum Tape {
+  /// Inputs are neither recorded nor played back.
+  Off,
+  /// Inputs are recorded.
+  Record(Replay),
+  /// Inputs are played back.
+  Play {
+    /// The replay being played back.
+    replay: Replay,
+    /// The index of the next input to play back.
+    next: usize,
+  },
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use tempfile::NamedTempFile;
+
+
+  /// Check that we can convert an [`Action`] to a string and back.
+  #[test]
+  fn action_str_conversion() {
+    for action in [
+      Action::RotateLeft,
+      Action::RotateRight,
+      Action::MoveLeft,
+      Action::MoveDown,
+      Action::MoveRight,
+      Action::Drop,
+      Action::Hold,
+      Action::Restart,
+      Action::ToggleAutoPlay,
+      Action::TogglePause,
+      Action::Pause,
+      Action::Resume,
+      Action::ToggleColorMode,
+      Action::ToggleHint,
+    ] {
+      assert_eq!(Action::from_str(action.as_str()).unwrap(), action);
+    }
+
+    assert!(Action::from_str("foobar").is_err());
+  }
+
+  /// Make sure that we can convert an [`Input`] to a string and back.
+  #[test]
+  fn input_str_conversion() {
+    let input = Input {
+      frame: 1234,
+      actions: vec![Action::MoveLeft, Action::Drop],
+    };
+    let s = input.to_string();
+    assert_eq!(s, "1234 move_left drop");
+    assert_eq!(Input::from_str(&s).unwrap(), input);
+
+    let input = Input::from_str("0").unwrap();
+    assert_eq!(input.frame, 0);
+    assert!(input.actions.is_empty());
+
+    assert!(Input::from_str("").is_err());
+    assert!(Input::from_str("1.5").is_err());
+    assert!(Input::from_str("-1").is_err());
+    assert!(Input::from_str("12 jump").is_err());
+  }
+
+  /// Check that a [`Replay`] survives a round trip through the file
+  /// system.
+  #[test]
+  fn save_load() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path();
+
+    let ai_weights = game::AiWeights {
+      holes: 3.5,
+      ..Default::default()
+    };
+    let mut replay = Replay::new(u64::MAX - 1, game::Config::default(), ai_weights).unwrap();
+    let () = replay.push_action(0, Action::Pause);
+    let () = replay.advance(5);
+    let () = replay.push_action(10, Action::Hold);
+    let () = replay.push_action(10, Action::Drop);
+    let () = replay.advance(42);
+    let () = replay.save(path).unwrap();
+
+    let loaded = Replay::load(path).unwrap();
+    assert_eq!(loaded, replay);
+    assert_eq!(loaded.length(), 42);
+    assert_eq!(loaded.inputs().len(), 2);
+    assert_eq!(loaded.inputs()[0].actions, vec![Action::Pause]);
+    assert_eq!(loaded.inputs()[1].actions, vec![Action::Hold, Action::Drop]);
+  }
+
+  /// Make sure that recorded games are played in a way that can be
+  /// reproduced.
+  #[test]
+  fn deterministic_config() {
+    let config = game::Config {
+      ai_time_budget_ms: 100,
+      ..Default::default()
+    };
+    let replay = Replay::new(42, config, game::AiWeights::default()).unwrap();
+    assert_eq!(replay.config().ai_time_budget_ms, 0);
+
+    let config = game::Config {
+      ai_bot: Some("/bin/bot".into()),
+      ..Default::default()
+    };
+    let err = Replay::new(42, config, game::AiWeights::default()).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "games played by an external bot cannot be recorded"
+    );
+  }
+}
@@ -0,0 +1,138 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fs::create_dir_all;
+use std::fs::read_to_string;
+use std::fs::remove_file;
+use std::fs::rename;
+use std::fs::write;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use anyhow::Result;
+
+use dirs::data_dir;
+
+use toml_edit::de::from_str as from_toml_str;
+use toml_edit::ser::to_string as to_toml_string;
+
+use crate::game::Snapshot;
+
+
+/// A file holding the state of a game that has not been finished yet.
+#[derive(Debug)]
+pub(crate) struct SaveGame {
+  /// The path to the file.
+  path: PathBuf,
+}
+
+impl SaveGame {
+  /// Create a `SaveGame` object for the file at the given path.
+  fn new(path: PathBuf) -> Self {
+    Self { path }
+  }
+
+  /// Create a `SaveGame` object for the file at the program's default
+  /// location.
+  pub fn at_default_path() -> Result<Self> {
+    let path = data_dir()
+      .context("unable to determine data directory")?
+      .join("tetromino")
+      .join("savegame.toml");
+    Ok(Self::new(path))
+  }
+
+  /// Load the saved game, if any.
+  pub fn load(&self) -> Result<Option<Snapshot>> {
+    let path = &self.path;
+    let content = match read_to_string(path) {
+      Ok(content) => content,
+      Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+      e @ Err(..) => {
+        e.with_context(|| format!("failed to read saved game `{}`", path.display()))?
+      },
+    };
+
+    let snapshot = from_toml_str::<Snapshot>(&content)
+      .with_context(|| format!("failed to parse saved game `{}`", path.display()))?;
+    Ok(Some(snapshot))
+  }
+
+  /// Save a game.
+  ///
+  /// The file is replaced atomically, so that an interrupted save
+  /// never leaves a corrupted file behind.
+  pub fn save(&self, snapshot: &Snapshot) -> Result<()> {
+    let path = &self.path;
+    if let Some(dir) = path.parent() {
+      let () = create_dir_all(dir)
+        .with_context(|| format!("failed to create directory `{}`", dir.display()))?;
+    }
+
+    let content = to_toml_string(snapshot).context("failed to serialize game")?;
+    let tmp_path = path.with_extension("toml.tmp");
+    let () = write(&tmp_path, content)
+      .with_context(|| format!("failed to write saved game `{}`", tmp_path.display()))?;
+    let () = rename(&tmp_path, path)
+      .with_context(|| format!("failed to write saved game `{}`", path.display()))?;
+    Ok(())
+  }
+
+  /// Remove the saved game, if any.
+  pub fn remove(&self) -> Result<()> {
+    let path = &self.path;
+    match remove_file(path) {
+      Ok(()) => Ok(()),
+      Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+      e @ Err(..) => e.with_context(|| format!("failed to remove saved game `{}`", path.display())),
+    }
+  }
+
+  /// Retrieve the path to the file.
+  #[inline]
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use tempfile::TempDir;
+
+  use crate::game::Config;
+  use crate::game::Engine;
+
+
+  /// Check that a game can be saved, loaded, and removed again.
+  #[test]
+  fn save_load_remove() {
+    let dir = TempDir::new().unwrap();
+    let save = SaveGame::new(dir.path().join("subdir").join("savegame.toml"));
+    assert!(save.load().unwrap().is_none());
+
+    let config = Config {
+      enable_ai: true,
+      ..Default::default()
+    };
+    let mut engine = Engine::with_config(&config, Some(42)).unwrap();
+    let _result = engine.tick(30 * 60);
+    let snapshot = engine.snapshot();
+
+    let () = save.save(&snapshot).unwrap();
+    let loaded = save.load().unwrap().unwrap();
+    assert_eq!(
+      to_toml_string(&loaded).unwrap(),
+      to_toml_string(&snapshot).unwrap()
+    );
+
+    let () = save.remove().unwrap();
+    assert!(!save.path().exists());
+    // Removing a file that does not exist is not an error.
+    let () = save.remove().unwrap();
+  }
+}
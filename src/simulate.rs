@@ -0,0 +1,434 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cmp::max;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::Context as _;
+use anyhow::Result;
+use anyhow::ensure;
+
+use serde::Serialize;
+
+use crate::Config;
+use crate::Engine;
+use crate::SearchStats;
+use crate::Tick;
+use crate::game::AiWeights;
+use crate::game::Config as GameConfig;
+
+
+/// The default maximum number of stones placed per game.
+const DEFAULT_MAX_STONES: u64 = 1000;
+
+
+/// The parameters of a batch of games played by the AI without a
+/// window.
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+pub struct Simulation {
+  /// The number of games to play per field size.
+  pub games: u32,
+  /// The seeds of the games to play. Should there be fewer seeds than
+  /// games, the remaining games use seeds consecutive to the last one
+  /// (or to zero, if none is given). Each seed is played even if there
+  /// are more seeds than games.
+  pub seeds: Vec<u64>,
+  /// The sizes of the fields to play on, as width and height. If
+  /// empty, the size from the configuration is used.
+  pub field_sizes: Vec<(i16, i16)>,
+  /// The number of stones after which a game is stopped, if it is not
+  /// over by then.
+  pub max_stones: u64,
+  /// Whether to report the results as JSON instead of text.
+  pub json: bool,
+}
+
+impl Default for Simulation {
+  fn default() -> Self {
+    Self {
+      games: 1,
+      seeds: Vec::new(),
+      field_sizes: Vec::new(),
+      max_stones: DEFAULT_MAX_STONES,
+      json: false,
+    }
+  }
+}
+
+impl Simulation {
+  /// Retrieve the seeds of the games to play on each field size.
+  fn game_seeds(&self) -> Vec<u64> {
+    let mut seeds = self.seeds.clone();
+    let mut next = seeds.last().map(|seed| seed.wrapping_add(1)).unwrap_or(0);
+    while seeds.len() < self.games as usize {
+      let () = seeds.push(next);
+      next = next.wrapping_add(1);
+    }
+    seeds
+  }
+}
+
+
+/// The reason a simulated game ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum End {
+  /// The stack reached the top of the field.
+  ToppedOut,
+  /// The goal of the game mode was reached.
+  Completed,
+  /// The maximum number of stones was placed.
+  StoneLimit,
+}
+
+impl End {
+  fn as_str(&self) -> &'static str {
+    match self {
+      Self::ToppedOut => "topped out",
+      Self::Completed => "completed",
+      Self::StoneLimit => "stone limit",
+    }
+  }
+}
+
+
+/// The result of a single simulated game.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct GameResult {
+  /// The seed the game was played with.
+  seed: u64,
+  /// The width of the field.
+  field_width: i16,
+  /// The height of the field.
+  field_height: i16,
+  /// The total number of lines cleared.
+  pub lines: u32,
+  /// The number of points earned.
+  points: u64,
+  /// The number of stones placed.
+  pub stones: u64,
+  /// The height of the stack at the end of the game.
+  pub stack_height: i16,
+  /// The number of holes in the stack at the end of the game.
+  pub holes: u16,
+  /// The average time the AI spent searching per stone, in
+  /// microseconds.
+  search_us: f64,
+  /// The reason the game ended.
+  end: End,
+}
+
+
+/// Aggregated results of all games played on a field of a given size.
+#[derive(Clone, Debug, Serialize)]
+struct Summary {
+  /// The width of the field.
+  field_width: i16,
+  /// The height of the field.
+  field_height: i16,
+  /// The number of games played.
+  games: usize,
+  /// The average number of lines cleared per game.
+  lines: f64,
+  /// The average number of points earned per game.
+  points: f64,
+  /// The average number of stones placed per game.
+  stones: f64,
+  /// The average time the AI spent searching per stone, in
+  /// microseconds.
+  search_us: f64,
+  /// The number of games in which the stack reached the top.
+  topped_out: usize,
+  /// The number of games in which the goal of the mode was reached.
+  completed: usize,
+  /// The number of games stopped at the stone limit.
+  stone_limit: usize,
+  /// The minimum, median, and maximum number of stones placed in
+  /// games that topped out, if any did.
+  topped_out_stones: Option<[u64; 3]>,
+}
+
+impl Summary {
+  fn new(field_width: i16, field_height: i16, results: &[GameResult], stats: SearchStats) -> Self {
+    let count = results.len();
+    let average =
+      |f: fn(&GameResult) -> f64| results.iter().map(f).sum::<f64>() / max(count, 1) as f64;
+    let ended = |end| results.iter().filter(|result| result.end == end).count();
+
+    let mut topped_out = results
+      .iter()
+      .filter(|result| result.end == End::ToppedOut)
+      .map(|result| result.stones)
+      .collect::<Vec<_>>();
+    let () = topped_out.sort_unstable();
+    let topped_out_stones = topped_out
+      .first()
+      .zip(topped_out.last())
+      .map(|(min, max)| [*min, topped_out[topped_out.len() / 2], *max]);
+
+    Self {
+      field_width,
+      field_height,
+      games: count,
+      lines: average(|result| f64::from(result.lines)),
+      points: average(|result| result.points as f64),
+      stones: average(|result| result.stones as f64),
+      search_us: stats.average().as_secs_f64() * 1_000_000.0,
+      topped_out: ended(End::ToppedOut),
+      completed: ended(End::Completed),
+      stone_limit: ended(End::StoneLimit),
+      topped_out_stones,
+    }
+  }
+}
+
+
+/// The report of a simulation.
+#[derive(Clone, Debug, Serialize)]
+struct Report {
+  /// The results of the individual games.
+  games: Vec<GameResult>,
+  /// The results aggregated per field size.
+  summaries: Vec<Summary>,
+}
+
+impl Display for Report {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    writeln!(
+      f,
+      "{:>20}  {:>7}  {:>8}  {:>10}  {:>7}  {:>10}  end",
+      "seed", "field", "lines", "points", "stones", "search/µs"
+    )?;
+    for result in &self.games {
+      writeln!(
+        f,
+        "{:>20}  {:>7}  {:>8}  {:>10}  {:>7}  {:>10.1}  {}",
+        result.seed,
+        format!("{}x{}", result.field_width, result.field_height),
+        result.lines,
+        result.points,
+        result.stones,
+        result.search_us,
+        result.end.as_str(),
+      )?;
+    }
+
+    for summary in &self.summaries {
+      writeln!(f)?;
+      writeln!(
+        f,
+        "field {}x{}: {} games",
+        summary.field_width, summary.field_height, summary.games
+      )?;
+      writeln!(f, "  lines:     {:.1} on average", summary.lines)?;
+      writeln!(f, "  points:    {:.1} on average", summary.points)?;
+      writeln!(f, "  stones:    {:.1} on average", summary.stones)?;
+      writeln!(f, "  search:    {:.1} µs per stone", summary.search_us)?;
+      write!(
+        f,
+        "  game over: {} topped out, {} completed, {} at stone limit",
+        summary.topped_out, summary.completed, summary.stone_limit
+      )?;
+      if let Some([min, median, max]) = summary.topped_out_stones {
+        write!(
+          f,
+          " (topped out after {min}/{median}/{max} min/median/max stones)"
+        )?;
+      }
+      writeln!(f)?;
+    }
+    Ok(())
+  }
+}
+
+
+/// Let the AI play a single game until it is over or `max_stones`
+/// stones have been placed.
+pub(crate) fn play(
+  config: &GameConfig,
+  weights: AiWeights,
+  seed: u64,
+  max_stones: u64,
+) -> Result<(GameResult, SearchStats)> {
+  let config = GameConfig {
+    enable_ai: false,
+    // A time budget would make the results depend on the speed of
+    // the machine, and games no longer reproducible.
+    ai_time_budget_ms: 0,
+    ..config.clone()
+  };
+  // The AI is only enabled below, so the engine does not check the
+  // field for us.
+  ensure!(
+    config.field_width <= Engine::MAX_AI_FIELD_WIDTH,
+    "field width ({}) exceeds maximum supported by the AI ({})",
+    config.field_width,
+    Engine::MAX_AI_FIELD_WIDTH
+  );
+  let mut engine = Engine::with_config(&config, Some(seed))?;
+  // The AI is only enabled once the weights are set, so that it
+  // considers them starting with the very first stone.
+  let () = engine.set_ai_weights(weights);
+  let () = engine.auto_play(true)?;
+
+  let mut frame = engine.frame();
+  while engine.stones() < max_stones {
+    let (_change, tick) = engine.tick(frame);
+    frame = match tick {
+      Tick::At(next) => max(next, frame + 1),
+      Tick::Now => frame + 1,
+      Tick::None => break,
+    };
+  }
+
+  let end = match engine.outcome() {
+    Some(outcome) if outcome.completed => End::Completed,
+    Some(..) => End::ToppedOut,
+    None => End::StoneLimit,
+  };
+  let stats = engine.search_stats();
+  let (stack_height, holes) = engine.stack();
+  let result = GameResult {
+    seed,
+    field_width: config.field_width,
+    field_height: config.field_height,
+    lines: engine.lines(),
+    points: engine.points(),
+    stones: engine.stones(),
+    stack_height,
+    holes,
+    search_us: stats.average().as_secs_f64() * 1_000_000.0,
+    end,
+  };
+  Ok((result, stats))
+}
+
+
+fn simulate_with(sim: &Simulation, config: &GameConfig, weights: AiWeights) -> Result<Report> {
+  let mut field_sizes = sim.field_sizes.clone();
+  if field_sizes.is_empty() {
+    let () = field_sizes.push((config.field_width, config.field_height));
+  }
+
+  let mut report = Report {
+    games: Vec::new(),
+    summaries: Vec::new(),
+  };
+
+  for (width, height) in field_sizes {
+    let config = GameConfig {
+      field_width: width,
+      field_height: height,
+      ..config.clone()
+    };
+
+    let start = report.games.len();
+    let mut total = SearchStats::default();
+    for seed in sim.game_seeds() {
+      let (result, stats) = play(&config, weights, seed, sim.max_stones).with_context(|| {
+        format!("failed to simulate game with seed {seed} on {width}x{height} field")
+      })?;
+      let () = report.games.push(result);
+      total.searches += stats.searches;
+      total.time += stats.time;
+    }
+
+    let summary = Summary::new(width, height, &report.games[start..], total);
+    let () = report.summaries.push(summary);
+  }
+  Ok(report)
+}
+
+
+/// Let the AI play a batch of games without a window, as configured
+/// by the program's configuration, and print a report about them.
+pub(crate) fn simulate(sim: &Simulation) -> Result<()> {
+  let Config { game, ai, .. } = Config::load().context("failed to load program configuration")?;
+  let report = simulate_with(sim, &game, ai)?;
+
+  if sim.json {
+    let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+    println!("{json}");
+  } else {
+    print!("{report}");
+  }
+  Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that simulated games are reproducible and honor the
+  /// stone limit.
+  #[test]
+  fn simulate_games() {
+    let sim = Simulation {
+      games: 2,
+      seeds: vec![42],
+      field_sizes: vec![(10, 20), (6, 8)],
+      max_stones: 25,
+      json: false,
+    };
+    let config = GameConfig::default();
+    let report = simulate_with(&sim, &config, AiWeights::default()).unwrap();
+    assert_eq!(report.games.len(), 4);
+    assert_eq!(report.summaries.len(), 2);
+
+    let summary = &report.summaries[0];
+    assert_eq!((summary.field_width, summary.field_height), (10, 20));
+    assert_eq!(summary.games, 2);
+    assert_eq!(
+      summary.topped_out + summary.completed + summary.stone_limit,
+      2
+    );
+
+    for result in &report.games {
+      assert!(result.stones <= sim.max_stones);
+      if result.end == End::StoneLimit {
+        assert_eq!(result.stones, sim.max_stones);
+      }
+    }
+
+    let other = simulate_with(&sim, &config, AiWeights::default()).unwrap();
+    for (result, other) in report.games.iter().zip(&other.games) {
+      assert_eq!(result.seed, other.seed);
+      assert_eq!(result.lines, other.lines);
+      assert_eq!(result.points, other.points);
+      assert_eq!(result.stones, other.stones);
+      assert_eq!(result.end, other.end);
+    }
+
+    let text = report.to_string();
+    assert!(text.contains("field 6x8: 2 games"), "{text}");
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["games"][0]["seed"], 42);
+    assert_eq!(json["games"][1]["seed"], 43);
+    assert_eq!(json["summaries"][1]["field_width"], 6);
+  }
+
+  /// Check that games are seeded as expected.
+  #[test]
+  fn game_seeds() {
+    let mut sim = Simulation {
+      games: 3,
+      ..Default::default()
+    };
+    assert_eq!(sim.game_seeds(), [0, 1, 2]);
+
+    sim.seeds = vec![7];
+    assert_eq!(sim.game_seeds(), [7, 8, 9]);
+
+    sim.seeds = vec![7, 3];
+    assert_eq!(sim.game_seeds(), [7, 3, 4]);
+
+    sim.seeds = vec![5, 1, 9, 2];
+    assert_eq!(sim.game_seeds(), [5, 1, 9, 2]);
+  }
+}
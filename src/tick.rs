@@ -7,42 +7,67 @@ use crate::Instant;
 
 
 /// An enumeration describing when the next program "tick" should occur.
+///
+/// By default, points in time are expressed as [`Instant`]s, but other
+/// representations, such as frame counters, are supported as well.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub enum Tick {
+pub enum Tick<T = Instant> {
   /// The next tick should happen immediately.
   Now,
-  /// The next tick should happen at the given instant.
-  At(Instant),
+  /// The next tick should happen at the given point in time.
+  At(T),
   /// No additional tick is necessary at this point.
   None,
 }
 
-impl From<Option<Instant>> for Tick {
-  fn from(other: Option<Instant>) -> Self {
+impl<T> Tick<T> {
+  /// Convert the point in time contained, if any, using the provided
+  /// function.
+  #[inline]
+  pub fn map<U, F>(self, f: F) -> Tick<U>
+  where
+    F: FnOnce(T) -> U,
+  {
+    match self {
+      Self::Now => Tick::Now,
+      Self::At(t) => Tick::At(f(t)),
+      Self::None => Tick::None,
+    }
+  }
+}
+
+impl<T> From<Option<T>> for Tick<T> {
+  fn from(other: Option<T>) -> Self {
     match other {
-      Some(instant) => Tick::At(instant),
+      Some(t) => Tick::At(t),
       None => Tick::None,
     }
   }
 }
 
-impl PartialOrd<Tick> for Tick {
+impl<T> PartialOrd<Tick<T>> for Tick<T>
+where
+  T: Ord,
+{
   fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
     Some(self.cmp(other))
   }
 }
 
-impl Ord for Tick {
+impl<T> Ord for Tick<T>
+where
+  T: Ord,
+{
   fn cmp(&self, other: &Self) -> Ordering {
     match (self, other) {
       (Self::None, Self::None) => Ordering::Equal,
-      (Self::None, Self::At(_instant)) => Ordering::Greater,
+      (Self::None, Self::At(_t)) => Ordering::Greater,
       (Self::None, Self::Now) => Ordering::Greater,
-      (Self::At(_instant), Self::None) => Ordering::Less,
-      (Self::At(instant1), Self::At(instant2)) => instant1.cmp(instant2),
-      (Self::At(_instant), Self::Now) => Ordering::Greater,
+      (Self::At(_t), Self::None) => Ordering::Less,
+      (Self::At(t1), Self::At(t2)) => t1.cmp(t2),
+      (Self::At(_t), Self::Now) => Ordering::Greater,
       (Self::Now, Self::None) => Ordering::Less,
-      (Self::Now, Self::At(_instant)) => Ordering::Less,
+      (Self::Now, Self::At(_t)) => Ordering::Less,
       (Self::Now, Self::Now) => Ordering::Equal,
     }
   }
@@ -61,12 +86,12 @@ mod tests {
   fn ordering() {
     let now = Instant::now();
 
-    assert_eq!(Tick::None.cmp(&Tick::None), Ordering::Equal);
-    assert_eq!(Tick::None.cmp(&Tick::Now), Ordering::Greater);
+    assert_eq!(Tick::<Instant>::None.cmp(&Tick::None), Ordering::Equal);
+    assert_eq!(Tick::<Instant>::None.cmp(&Tick::Now), Ordering::Greater);
     assert_eq!(Tick::None.cmp(&Tick::At(now)), Ordering::Greater);
 
-    assert_eq!(Tick::Now.cmp(&Tick::Now), Ordering::Equal);
-    assert_eq!(Tick::Now.cmp(&Tick::None), Ordering::Less);
+    assert_eq!(Tick::<Instant>::Now.cmp(&Tick::Now), Ordering::Equal);
+    assert_eq!(Tick::<Instant>::Now.cmp(&Tick::None), Ordering::Less);
     assert_eq!(Tick::Now.cmp(&Tick::At(now)), Ordering::Less);
 
     assert_eq!(Tick::At(now).cmp(&Tick::None), Ordering::Less);
@@ -76,5 +101,16 @@ mod tests {
     assert_eq!(Tick::At(now).cmp(&Tick::At(now)), Ordering::Equal);
     assert_eq!(Tick::At(now).cmp(&Tick::At(later)), Ordering::Less);
     assert_eq!(Tick::At(later).cmp(&Tick::At(now)), Ordering::Greater);
+
+    assert_eq!(Tick::At(1u64).cmp(&Tick::At(2u64)), Ordering::Less);
+    assert_eq!(Tick::At(1u64).cmp(&Tick::None), Ordering::Less);
+  }
+
+  /// Check that we can map the point in time of a [`Tick`].
+  #[test]
+  fn mapping() {
+    assert_eq!(Tick::<u64>::Now.map(|t| t + 1), Tick::Now);
+    assert_eq!(Tick::At(41u64).map(|t| t + 1), Tick::At(42));
+    assert_eq!(Tick::<u64>::None.map(|t| t + 1), Tick::None);
   }
 }
@@ -0,0 +1,446 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cmp::max;
+use std::cmp::min;
+use std::f64::consts::PI;
+use std::num::NonZeroUsize;
+use std::panic::resume_unwind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread::available_parallelism;
+use std::thread::scope;
+
+use anyhow::Context as _;
+use anyhow::Result;
+
+use toml_edit::ser::to_string_pretty as to_toml_string;
+
+use crate::Config;
+use crate::config::default_config_path;
+use crate::config::load_config;
+use crate::config::save_config;
+use crate::game::AiWeights;
+use crate::game::Config as GameConfig;
+use crate::rand::Rng;
+use crate::simulate::GameResult;
+use crate::simulate::play;
+
+
+/// The standard deviation of the (logarithmic) change applied to
+/// weights when mutating them.
+const SIGMA: f64 = 0.25;
+/// The maximum exponent the height of the top most piece may be
+/// raised to.
+const MAX_EXPONENT: u8 = 32;
+/// The fitness lost for each row the stack is high at the end of a
+/// game.
+const HEIGHT_PENALTY: f64 = 1.0;
+/// The fitness lost for each hole in the stack at the end of a game.
+const HOLE_PENALTY: f64 = 2.0;
+
+
+/// The parameters of a run evolving the weights used by the AI.
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+pub struct Tuning {
+  /// The number of generations to evolve.
+  pub generations: u32,
+  /// The number of candidate weight sets per generation.
+  pub population: usize,
+  /// The number of games each candidate plays per generation.
+  pub games: u32,
+  /// The seed from which all randomness is derived.
+  pub seed: u64,
+  /// The size of the field to play on, as width and height. If not
+  /// present, the size from the configuration is used.
+  pub field_size: Option<(i16, i16)>,
+  /// The number of stones after which a game is stopped, if it is not
+  /// over by then.
+  pub max_stones: u64,
+  /// The number of threads to simulate games on. If not present, one
+  /// per available core is used.
+  pub threads: Option<NonZeroUsize>,
+  /// The file to write the best weights to. If not present, they are
+  /// written to `tuned.toml` next to the program's configuration
+  /// file.
+  pub output: Option<PathBuf>,
+}
+
+impl Default for Tuning {
+  fn default() -> Self {
+    Self {
+      generations: 1,
+      population: 16,
+      games: 4,
+      seed: 0,
+      field_size: None,
+      max_stones: 250,
+      threads: None,
+      output: None,
+    }
+  }
+}
+
+
+/// Draw a sample from the standard normal distribution.
+fn normal(rng: &Rng) -> f64 {
+  // Box-Muller transform; `u1` is kept away from zero for the
+  // logarithm to stay finite.
+  let u1 = (f64::from(rng.rand_u32()) + 1.0) / (f64::from(u32::MAX) + 1.0);
+  let u2 = f64::from(rng.rand_u32()) / f64::from(u32::MAX);
+  (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// Pick one of two values at random.
+fn pick<T>(rng: &Rng, a: T, b: T) -> T {
+  if rng.rand_u32() & 1 == 0 { a } else { b }
+}
+
+/// Combine two sets of weights, picking each weight from either
+/// parent at random.
+fn crossover(a: &AiWeights, b: &AiWeights, rng: &Rng) -> AiWeights {
+  AiWeights {
+    holes: pick(rng, a.holes, b.holes),
+    coverage: pick(rng, a.coverage, b.coverage),
+    stack: pick(rng, a.stack, b.stack),
+    roughness: pick(rng, a.roughness, b.roughness),
+    high_point: pick(rng, a.high_point, b.high_point),
+    high_point_exponent: pick(rng, a.high_point_exponent, b.high_point_exponent),
+    move_down: pick(rng, a.move_down, b.move_down),
+    move_sideways: pick(rng, a.move_sideways, b.move_sideways),
+    rotate: pick(rng, a.rotate, b.rotate),
+  }
+}
+
+/// Randomly change a set of weights.
+///
+/// Weights are scaled by a log-normally distributed factor, meaning
+/// that they keep their sign (and a weight of zero stays zero).
+fn mutate(weights: &AiWeights, rng: &Rng) -> AiWeights {
+  let scale = |weight: f64| weight * (SIGMA * normal(rng)).exp();
+  let exponent = weights.high_point_exponent;
+  let exponent = match rng.rand_u32() % 4 {
+    0 => max(exponent.saturating_sub(1), 1),
+    1 => min(exponent.saturating_add(1), MAX_EXPONENT),
+    _ => exponent,
+  };
+
+  AiWeights {
+    holes: scale(weights.holes),
+    coverage: scale(weights.coverage),
+    stack: scale(weights.stack),
+    roughness: scale(weights.roughness),
+    high_point: scale(weights.high_point),
+    high_point_exponent: exponent,
+    move_down: scale(weights.move_down),
+    move_sideways: scale(weights.move_sideways),
+    rotate: scale(weights.rotate),
+  }
+}
+
+/// Breed the next generation from the candidates of the current one,
+/// which are expected to be ordered from best to worst.
+///
+/// The best quarter of candidates is carried over unchanged, while
+/// the remainder is made up of mutated crossovers of them.
+fn breed(ranked: &[AiWeights], population: usize, rng: &Rng) -> Vec<AiWeights> {
+  let elite = &ranked[..max(ranked.len() / 4, 1)];
+  let mut next = elite.to_vec();
+  while next.len() < population {
+    let a = &elite[rng.rand_u32() as usize % elite.len()];
+    let b = &elite[rng.rand_u32() as usize % elite.len()];
+    let () = next.push(mutate(&crossover(a, b, rng), rng));
+  }
+  let () = next.truncate(population);
+  next
+}
+
+
+/// Score the result of a game.
+///
+/// Games are scored by the number of stones placed, i.e., by how long
+/// the AI survived, less a penalty for the height of and the holes in
+/// the stack left behind. The latter sets apart candidates that all
+/// make it to the stone limit.
+fn fitness(result: &GameResult) -> f64 {
+  result.stones as f64
+    - HEIGHT_PENALTY * f64::from(result.stack_height)
+    - HOLE_PENALTY * f64::from(result.holes)
+}
+
+
+/// Evaluate the given candidates, returning the average fitness each
+/// achieved in the games played with the given seeds.
+///
+/// Games are distributed across `threads` threads.
+fn evaluate(
+  config: &GameConfig,
+  candidates: &[AiWeights],
+  seeds: &[u64],
+  max_stones: u64,
+  threads: NonZeroUsize,
+) -> Result<Vec<f64>> {
+  let jobs = candidates.len() * seeds.len();
+  let next = AtomicUsize::new(0);
+
+  let results = scope(|scope| {
+    let handles = (0..min(threads.get(), jobs))
+      .map(|_| {
+        scope.spawn(|| {
+          let mut results = Vec::new();
+          loop {
+            let job = next.fetch_add(1, Ordering::Relaxed);
+            if job >= jobs {
+              break Ok(results)
+            }
+
+            let (candidate, game) = (job / seeds.len(), job % seeds.len());
+            let (result, _stats) = play(config, candidates[candidate], seeds[game], max_stones)?;
+            let () = results.push((candidate, fitness(&result)));
+          }
+        })
+      })
+      .collect::<Vec<_>>();
+
+    handles
+      .into_iter()
+      .map(|handle| handle.join().unwrap_or_else(|panic| resume_unwind(panic)))
+      .collect::<Result<Vec<Vec<_>>>>()
+  })?;
+
+  let mut fitness = vec![0.0; candidates.len()];
+  for (candidate, score) in results.into_iter().flatten() {
+    fitness[candidate] += score;
+  }
+  let () = fitness
+    .iter_mut()
+    .for_each(|fitness| *fitness /= max(seeds.len(), 1) as f64);
+  Ok(fitness)
+}
+
+
+/// Save the given weights to the configuration file at `path`,
+/// leaving all other settings untouched.
+fn save_weights(weights: &AiWeights, path: &Path) -> Result<()> {
+  let mut config = load_config(path)?;
+  config.ai = *weights;
+  save_config(&config, path)
+}
+
+
+fn tune_with(
+  tuning: &Tuning,
+  config: &GameConfig,
+  weights: AiWeights,
+  output: &Path,
+) -> Result<AiWeights> {
+  let config = if let Some((width, height)) = tuning.field_size {
+    GameConfig {
+      field_width: width,
+      field_height: height,
+      ..config.clone()
+    }
+  } else {
+    config.clone()
+  };
+  let threads = tuning
+    .threads
+    .or_else(|| available_parallelism().ok())
+    .unwrap_or(NonZeroUsize::MIN);
+  let population = max(tuning.population, 1);
+
+  let rng = Rng::with_seed(tuning.seed);
+  // The initial population consists of the weights we start out with
+  // as well as variations of them.
+  let mut candidates = vec![weights];
+  while candidates.len() < population {
+    let () = candidates.push(mutate(&weights, &rng));
+  }
+
+  // The best weights found so far have to prove themselves against
+  // the ones we started out with on a common set of games, distinct
+  // from the ones candidates are ranked by.
+  let validation = (0..u64::from(tuning.games))
+    .map(|game| tuning.seed.wrapping_sub(game + 1))
+    .collect::<Vec<_>>();
+  let mut best = weights;
+  // SANITY: We evaluate exactly one candidate.
+  let mut best_fitness = evaluate(&config, &[best], &validation, tuning.max_stones, threads)?[0];
+  println!("initial weights: fitness {best_fitness:.1}");
+
+  for generation in 0..tuning.generations {
+    // All candidates of a generation play the same games, for them to
+    // be comparable, but each generation plays different ones, to not
+    // favor weights that happen to do well on a particular set.
+    let seeds = (0..u64::from(tuning.games))
+      .map(|game| {
+        tuning
+          .seed
+          .wrapping_add(u64::from(generation) * u64::from(tuning.games) + game)
+      })
+      .collect::<Vec<_>>();
+    let fitness = evaluate(&config, &candidates, &seeds, tuning.max_stones, threads)?;
+
+    let mut ranked = candidates.iter().zip(&fitness).collect::<Vec<_>>();
+    let () = ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    let average = fitness.iter().sum::<f64>() / fitness.len() as f64;
+    // SANITY: The population always contains at least one candidate.
+    let (weights, most) = ranked[0];
+    let validated = evaluate(
+      &config,
+      &[*weights],
+      &validation,
+      tuning.max_stones,
+      threads,
+    )?[0];
+
+    let status = if validated > best_fitness {
+      best = *weights;
+      best_fitness = validated;
+      let () = save_weights(&best, output)
+        .with_context(|| format!("failed to save AI weights to `{}`", output.display()))?;
+      format!("new best, saved to `{}`", output.display())
+    } else {
+      format!("best so far {best_fitness:.1}")
+    };
+    println!(
+      "generation {}/{}: best {most:.1}, average {average:.1}; validated {validated:.1}, {status}",
+      generation + 1,
+      tuning.generations,
+    );
+
+    let ranked = ranked
+      .into_iter()
+      .map(|(weights, _fitness)| *weights)
+      .collect::<Vec<_>>();
+    candidates = breed(&ranked, population, &rng);
+  }
+  Ok(best)
+}
+
+
+/// Evolve the weights the AI uses by letting it play games with
+/// different candidate weight sets, and save the best performing one.
+pub(crate) fn tune(tuning: &Tuning) -> Result<()> {
+  let Config { game, ai, .. } = Config::load().context("failed to load program configuration")?;
+  let output = match &tuning.output {
+    Some(output) => output.clone(),
+    None => default_config_path()
+      .context("failed to retrieve program config directory path")?
+      .with_file_name("tuned.toml"),
+  };
+
+  let best = tune_with(tuning, &game, ai, &output)?;
+  // SANITY: Converting weights to a string is expected to always
+  //         succeed and any failure would be a bug.
+  let best = to_toml_string(&best).expect("failed to serialize AI weights");
+  println!("best weights:\n{best}");
+  Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use tempfile::TempDir;
+
+
+  /// Check that crossovers only contain weights of their parents.
+  #[test]
+  fn crossover_parents() {
+    let rng = Rng::with_seed(1337);
+    let a = AiWeights::default();
+    let b = mutate(&a, &rng);
+
+    for _ in 0..16 {
+      let child = crossover(&a, &b, &rng);
+      assert!(child.holes == a.holes || child.holes == b.holes);
+      assert!(child.rotate == a.rotate || child.rotate == b.rotate);
+      assert!(
+        child.high_point_exponent == a.high_point_exponent
+          || child.high_point_exponent == b.high_point_exponent
+      );
+    }
+  }
+
+  /// Check that mutations keep weights within their bounds.
+  #[test]
+  fn mutate_bounds() {
+    let rng = Rng::with_seed(42);
+    let mut weights = AiWeights {
+      coverage: 0.0,
+      high_point_exponent: 1,
+      ..Default::default()
+    };
+
+    for _ in 0..256 {
+      weights = mutate(&weights, &rng);
+      assert!(weights.holes > 0.0);
+      assert_eq!(weights.coverage, 0.0);
+      assert!((1..=MAX_EXPONENT).contains(&weights.high_point_exponent));
+    }
+  }
+
+  /// Check that the best candidates survive into the next generation.
+  #[test]
+  fn breed_elite() {
+    let rng = Rng::with_seed(7);
+    let ranked = (0..8)
+      .map(|i| AiWeights {
+        holes: f64::from(i),
+        ..Default::default()
+      })
+      .collect::<Vec<_>>();
+
+    let next = breed(&ranked, 8, &rng);
+    assert_eq!(next.len(), 8);
+    assert_eq!(next[..2], ranked[..2]);
+  }
+
+  /// Check that tuning saves weights that the game can load.
+  #[test]
+  fn tune_save() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    let tuning = Tuning {
+      generations: 2,
+      population: 3,
+      games: 1,
+      seed: 3,
+      field_size: Some((6, 8)),
+      max_stones: 10,
+      threads: NonZeroUsize::new(2),
+      output: None,
+    };
+
+    let best = tune_with(&tuning, &GameConfig::default(), AiWeights::default(), &path).unwrap();
+    // Weights are only saved once they beat the initial ones.
+    if path.exists() {
+      let config = load_config(&path).unwrap();
+      assert_eq!(config.ai, best);
+      assert_ne!(best, AiWeights::default());
+    } else {
+      assert_eq!(best, AiWeights::default());
+    }
+  }
+
+  /// Check that game results are scored as expected.
+  #[test]
+  fn fitness_scores() {
+    let config = GameConfig {
+      field_width: 6,
+      field_height: 8,
+      ..Default::default()
+    };
+    let (result, _stats) = play(&config, AiWeights::default(), 5, 12).unwrap();
+    let expected = result.stones as f64
+      - HEIGHT_PENALTY * f64::from(result.stack_height)
+      - HOLE_PENALTY * f64::from(result.holes);
+    assert_eq!(fitness(&result), expected);
+    assert!(fitness(&result) < result.stones as f64);
+  }
+}
@@ -38,6 +38,7 @@ use crate::game;
 use crate::game::Game;
 use crate::keys;
 use crate::keys::Keys as KeysT;
+use crate::replay::Tape;
 
 type Keys<K> = KeysT<K, Instant>;
 type OpsState = (Document, HtmlCanvasElement, sys::Context);
@@ -316,12 +317,56 @@ fn game_config(params: &UrlSearchParams, window: &Window) -> Result<game::Config
   let () = parse_and_set_int_param!(params => config.field_width);
   let () = parse_and_set_int_param!(params => config.field_height);
   let () = parse_and_set_int_param!(params => config.preview_stone_count);
+  let () = parse_and_set_int_param!(params => config.lock_delay_ms);
+  let () = parse_and_set_int_param!(params => config.lock_reset_limit);
+  let () = parse_and_set_int_param!(params => config.scoring);
+  let () = parse_and_set_int_param!(params => config.randomizer);
+  let () = parse_and_set_int_param!(params => config.stone_set);
+  let () = parse_and_set_int_param!(params => config.gravity);
+  if let Some(table) = params.get("gravity_table") {
+    // The table is provided as a comma separated list of speeds.
+    config.gravity_table = table
+      .split(',')
+      .map(|speed| speed.trim().parse::<f64>())
+      .collect::<Result<_, _>>()
+      .context("failed to parse `gravity_table`")?;
+  }
+  let () = parse_and_set_int_param!(params => config.gravity_base);
+  let () = parse_and_set_int_param!(params => config.gravity_growth);
+  let () = parse_and_set_int_param!(params => config.gravity_exponent);
+  let () = parse_and_set_int_param!(params => config.rotation_system);
+  let () = parse_and_set_int_param!(params => config.mode);
+  let () = parse_and_set_int_param!(params => config.sprint_lines);
+  let () = parse_and_set_int_param!(params => config.ultra_time_secs);
+  let () = parse_and_set_int_param!(params => config.dig_rows);
+  let () = parse_and_set_int_param!(params => config.dig_interval_ms);
   let () = parse_and_set_int_param!(params => config.enable_ai);
+  let () = parse_and_set_int_param!(params => config.ai_lookahead);
+  let () = parse_and_set_int_param!(params => config.ai_time_budget_ms);
+  let () = parse_and_set_int_param!(params => config.ai_difficulty);
+  let () = parse_and_set_int_param!(params => config.enable_hold);
+  let () = parse_and_set_int_param!(params => config.enable_ghost);
   let () = parse_and_set_int_param!(params => config.enable_dark_mode);
 
   Ok(config)
 }
 
+fn ai_weights(params: &UrlSearchParams) -> Result<game::AiWeights> {
+  let mut weights = game::AiWeights::default();
+
+  let () = parse_and_set_int_param!(params => weights.holes);
+  let () = parse_and_set_int_param!(params => weights.coverage);
+  let () = parse_and_set_int_param!(params => weights.stack);
+  let () = parse_and_set_int_param!(params => weights.roughness);
+  let () = parse_and_set_int_param!(params => weights.high_point);
+  let () = parse_and_set_int_param!(params => weights.high_point_exponent);
+  let () = parse_and_set_int_param!(params => weights.move_down);
+  let () = parse_and_set_int_param!(params => weights.move_sideways);
+  let () = parse_and_set_int_param!(params => weights.rotate);
+
+  Ok(weights)
+}
+
 fn keys_config(params: &UrlSearchParams) -> Result<keys::Config> {
   let mut config = keys::Config::default();
 
@@ -409,8 +454,10 @@ pub fn run(canvas: JsValue) -> Result<(), JsValue> {
 
     let (width, height) = window_size(&window);
     let config = game_config(&params, &window).context("failed to create game configuration")?;
-    let game = Game::with_config(width, height, &config, &context)
+    let mut game = Game::with_config(width, height, &config, None, &context)
       .context("failed to instantiate game object")?;
+    let weights = ai_weights(&params).context("failed to create AI weights")?;
+    let () = game.set_ai_weights(weights);
 
     let config = keys_config(&params).context("failed to create key configuration")?;
     let keys::Config {
@@ -422,7 +469,7 @@ pub fn run(canvas: JsValue) -> Result<(), JsValue> {
     let keys = Keys::new(timeout, interval);
 
     let ops_state = (document.clone(), canvas, context);
-    let app = App::new(ops_state, game, keys);
+    let app = App::new(ops_state, game, keys, Tape::Off);
     let state = State::new(app, window).context("failed to instantiate application state")?;
     let () = forget(state);
 
@@ -56,16 +56,28 @@ use xgl::sys;
 use crate::Change;
 use crate::Config;
 use crate::Instant;
+use crate::ReplayMode;
 use crate::Tick;
 use crate::app::App as AppT;
 use crate::game::Game;
+use crate::highscore::HighScores;
+use crate::keys;
 use crate::keys::Config as KeysConfig;
 use crate::keys::Keys;
+use crate::rand::random_seed;
+use crate::replay::Replay;
+use crate::replay::Tape;
+use crate::savegame::SaveGame;
 
 
 type App = AppT<Window>;
 
 
+/// The interval at which the state of a running game is saved, so
+/// that it can be resumed even after a crash.
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+
 fn window_size(window: &WinitWindow) -> (NonZeroU32, NonZeroU32) {
   let size = window.inner_size();
   let phys_w =
@@ -284,12 +296,35 @@ impl Window {
 }
 
 
-#[derive(Default)]
 struct Handler {
   app: OnceCell<Result<App>>,
+  replay: ReplayMode,
+  /// The file to save the game in, if it is to be saved at all.
+  savegame: Option<SaveGame>,
+  /// The time at which to save the game next.
+  next_autosave: Instant,
+  /// Whether the saved game has been removed after the current game
+  /// ended.
+  save_removed: bool,
+  /// The level most recently reported to the user.
+  reported_level: Option<u16>,
+  /// Whether the outcome of the current game has been reported.
+  reported_outcome: bool,
 }
 
 impl Handler {
+  fn new(replay: ReplayMode, savegame: Option<SaveGame>) -> Self {
+    Self {
+      app: OnceCell::new(),
+      replay,
+      savegame,
+      next_autosave: Instant::now() + AUTOSAVE_INTERVAL,
+      save_removed: false,
+      reported_level: None,
+      reported_outcome: false,
+    }
+  }
+
   fn app<'slf>(&'slf mut self, event_loop: &ActiveEventLoop) -> Option<&'slf mut App> {
     match self.app.get_mut()? {
       Ok(state) => Some(state),
@@ -310,12 +345,92 @@ impl Handler {
     let () = app.on_window_resize(phys_w, phys_h);
     let () = app.ops().request_redraw();
   }
+
+  /// Report progress, i.e., level ups and the final results, on
+  /// stdout.
+  ///
+  /// While we actually render the score in real-time, we also print
+  /// to stdout, just to have a history in a slightly more persistent
+  /// location (still visible after the main window got closed).
+  fn report(&mut self) {
+    if let Some(Ok(app)) = self.app.get() {
+      let game = app.game();
+      let level = game.level();
+      if let Some(reported) = self.reported_level
+        && level > reported
+      {
+        println!("{} points @ level {level}", game.points());
+      }
+      self.reported_level = Some(level);
+
+      match game.outcome() {
+        Some(outcome) if !self.reported_outcome => {
+          println!("{outcome}");
+          self.reported_outcome = true;
+        },
+        Some(..) => (),
+        None => self.reported_outcome = false,
+      }
+    }
+  }
+
+  /// Save the game if it is time to do so, or remove the saved game
+  /// once the game is over, as there is nothing left to resume then.
+  fn autosave(&mut self) {
+    let (Some(savegame), Some(Ok(app))) = (&self.savegame, self.app.get()) else {
+      return
+    };
+
+    let game = app.game();
+    if game.is_over() {
+      if !self.save_removed {
+        if let Err(err) = savegame.remove() {
+          eprintln!("warning: failed to remove saved game: {err:#}");
+        }
+        self.save_removed = true;
+      }
+      return
+    }
+    self.save_removed = false;
+
+    let now = Instant::now();
+    if now < self.next_autosave {
+      return
+    }
+    self.next_autosave = now + AUTOSAVE_INTERVAL;
+
+    if let Err(err) = savegame.save(&game.snapshot()) {
+      eprintln!("warning: failed to save game: {err:#}");
+    }
+  }
 }
 
+
 impl ApplicationHandler for Handler {
   fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-    fn create_app(event_loop: &ActiveEventLoop) -> Result<App> {
+    fn create_app(
+      event_loop: &ActiveEventLoop,
+      replay: &ReplayMode,
+      savegame: Option<&SaveGame>,
+    ) -> Result<App> {
       let config = Config::load().context("failed to load program configuration")?;
+      let tape = match replay {
+        ReplayMode::Off => Tape::Off,
+        ReplayMode::Record(..) => {
+          Tape::Record(Replay::new(random_seed(), config.game.clone(), config.ai)?)
+        },
+        ReplayMode::Play(path) => Tape::Play {
+          replay: Replay::load(path)?,
+          next: 0,
+        },
+      };
+      let (game_config, ai_weights, seed) = match &tape {
+        Tape::Off => (&config.game, &config.ai, None),
+        Tape::Record(replay) | Tape::Play { replay, .. } => {
+          (replay.config(), replay.ai_weights(), Some(replay.seed()))
+        },
+      };
+
       let display_handle = event_loop
         .display_handle()
         .context("failed to retrieve display handle")?;
@@ -324,17 +439,60 @@ impl ApplicationHandler for Handler {
         Window::new(display_handle, create_window_fn).context("failed to create OpenGL window")?;
       let (phys_w, phys_h) = window.size();
       let gl_context = window.render_context().gl_context();
-      let game = Game::with_config(phys_w, phys_h, &config.game, gl_context)
-        .context("failed to instantiate game object")?;
+      let snapshot = savegame.and_then(|savegame| match savegame.load() {
+        Ok(snapshot) => snapshot
+          .filter(|snapshot| !snapshot.is_over())
+          .map(|snapshot| (savegame, snapshot)),
+        Err(err) => {
+          eprintln!("warning: ignoring saved game: {err:#}");
+          None
+        },
+      });
+      let game = snapshot.and_then(|(savegame, snapshot)| {
+        match Game::from_snapshot(phys_w, phys_h, game_config, &snapshot, gl_context) {
+          Ok(game) => Some(game),
+          Err(err) => {
+            eprintln!(
+              "warning: ignoring saved game `{}`: {err:#}",
+              savegame.path().display()
+            );
+            None
+          },
+        }
+      });
+      let mut game = if let Some(mut game) = game {
+        // Give the player a chance to decide whether to continue the
+        // game or start a new one.
+        let () = game.prompt_resume(
+          format!("{:?}", keys::KEY_PAUSE),
+          format!("{:?}", keys::KEY_RESTART),
+        );
+        game
+      } else {
+        Game::with_config(phys_w, phys_h, game_config, seed, gl_context)
+          .context("failed to instantiate game object")?
+      };
+      let () = game.set_ai_weights(*ai_weights);
+      // Games recorded or played back are not eligible for high
+      // scores.
+      if let Tape::Off = tape {
+        let scores = HighScores::load().unwrap_or_else(|err| {
+          eprintln!("warning: ignoring high scores: {err:#}");
+          HighScores::default()
+        });
+        let () = game.set_high_scores(scores);
+      }
       let timeout = Duration::from_millis(config.keyboard.auto_repeat_timeout_ms.into());
       let interval = Duration::from_millis(config.keyboard.auto_repeat_interval_ms.into());
       let keys = Keys::new(timeout, interval);
 
-      let app = App::new(window, game, keys);
+      let app = App::new(window, game, keys, tape);
       Ok(app)
     }
 
-    let _app = self.app.get_or_init(|| create_app(event_loop));
+    let _app = self
+      .app
+      .get_or_init(|| create_app(event_loop, &self.replay, self.savegame.as_ref()));
     // "Check" the app and potentially trigger an event loop exit if
     // we failed part of the initialization.
     let _app = self.app(event_loop);
@@ -412,18 +570,58 @@ impl ApplicationHandler for Handler {
         Change::Unchanged => (),
       }
     }
+
+    let () = self.report();
+    let () = self.autosave();
   }
 }
 
 
-pub(crate) fn run_app() -> Result<()> {
+pub(crate) fn run_app(replay: ReplayMode) -> Result<()> {
   let event_loop = EventLoop::new().context("failed to create event loop")?;
   let () = event_loop.set_control_flow(ControlFlow::Wait);
-  let mut handler = Handler::default();
+  // Only regular games are saved; recorded and played back ones
+  // are not.
+  let savegame = match replay {
+    ReplayMode::Off => Some(SaveGame::at_default_path()?),
+    ReplayMode::Record(..) | ReplayMode::Play(..) => None,
+  };
+  let mut handler = Handler::new(replay, savegame);
   let () = event_loop.run_app(&mut handler)?;
   if let Some(result) = handler.app.into_inner() {
     let app = result?;
-    let (game, keys) = app.into_parts();
+    let (game, keys, tape) = app.into_parts();
+    match (tape, &handler.replay) {
+      (Tape::Record(replay), ReplayMode::Record(path)) => {
+        let () = replay.save(path).context("failed to save replay")?;
+      },
+      // The game played back is not based on the user's
+      // configuration, so don't persist it.
+      (Tape::Play { .. }, _) => return Ok(()),
+      _ => (),
+    }
+
+    if let Some(savegame) = &handler.savegame {
+      let () = if game.is_over() {
+        savegame.remove()
+      } else {
+        savegame.save(&game.snapshot())
+      }
+      .context("failed to save game")?;
+    }
+
+    if let Some(scores) = game.high_scores() {
+      let () = scores.save().context("failed to save high scores")?;
+    }
+
+    let ai = *game.ai_weights();
+    let mut game_config = game.into_config();
+    if let ReplayMode::Record(..) = &handler.replay {
+      // Recorded games are played without a time budget for the AI
+      // (see `Replay::new`), which is not meant to stick.
+      let config = Config::load().context("failed to load program configuration")?;
+      game_config.ai_time_budget_ms = config.game.ai_time_budget_ms;
+    }
 
     let config = Config {
       keyboard: KeysConfig {
@@ -432,7 +630,8 @@ pub(crate) fn run_app() -> Result<()> {
         // SANITY: We originally set the interval duration from a `u32`.
         auto_repeat_interval_ms: u32::try_from(keys.interval().as_millis()).unwrap(),
       },
-      game: game.into_config(),
+      game: game_config,
+      ai,
     };
     let () = config
       .save()
@@ -442,34 +641,3 @@ pub(crate) fn run_app() -> Result<()> {
     Ok(())
   }
 }
-
-
-/// A test helper to run a function with an OpenGL window (and context)
-/// present.
-///
-/// # Notes
-/// You most likely want to use this function in a separate process,
-/// because of the effectively global OpenGL state.
-#[cfg(test)]
-#[expect(deprecated)]
-pub(crate) fn with_opengl_context<F>(f: F)
-where
-  F: FnOnce(&sys::Context),
-{
-  use winit::event_loop::EventLoop;
-  use winit::platform::x11::EventLoopBuilderExtX11 as _;
-  use winit::raw_window_handle::HasDisplayHandle as _;
-
-  let event_loop = EventLoop::builder().with_any_thread(true).build().unwrap();
-  let display_handle = event_loop.display_handle().unwrap();
-  // Create an invisible window, which will contain a valid OpenGL
-  // context.
-  let create_window_fn = |mut attrs: WindowAttributes| {
-    attrs.visible = false;
-    event_loop.create_window(attrs)
-  };
-  let _window = Window::new(display_handle, create_window_fn).unwrap();
-  let context = sys::Context::default();
-
-  f(&context)
-}
@@ -14,6 +14,7 @@ use raw_window_handle::XlibDisplayHandle;
 use raw_window_handle::XlibWindowHandle;
 
 use tetromino::Change;
+use tetromino::Clock;
 use tetromino::Context;
 use tetromino::Game;
 use tetromino::GameConfig;
@@ -56,7 +57,7 @@ static tetromino_description: xlock::ModStruct = xlock::ModStruct {
 /// Our "mode's" main state object.
 struct State {
   /// Relevant Tetromino related data.
-  data: Option<(Context, Game)>,
+  data: Option<(Context, Game, Clock)>,
 }
 
 
@@ -80,15 +81,18 @@ extern "C" fn init_tetromino(mode_info: *const xlock::ModeInfo) {
     let state = unsafe { lock_struct.userdata.cast::<State>().as_mut().unwrap() };
     // At this point the Tetromino logic doesn't support creation of
     // multiple instances at the same time. Make sure to drop any
-    // previous data before we start over.
-    state.data = None;
-
-    // TODO: We certainly should not re-create the game when one is
-    //       already present. It's unclear how to handle the window,
-    //       because textures may logically "belong" to it (or at least
-    //       the associated OpenGL context). We may not *have to*
-    //       recreate it, conceptually. If we do, we may need to
-    //       serialize the game and restore it to keep the state.
+    // previous data before we start over, but remember the state of
+    // the game so that we can continue it.
+    let previous = state
+      .data
+      .take()
+      .map(|(_context, game, clock)| (game.snapshot(), clock));
+
+    // TODO: We may not *have to* re-create the window's context when
+    //       one is already present. It's unclear how to handle it,
+    //       though, because textures may logically "belong" to it (or
+    //       at least the associated OpenGL context). Hence, we
+    //       recreate everything and restore the game from a snapshot.
     let display = NonNull::new(mode_info.windowinfo.display.cast());
     let screen = mode_info.windowinfo.screen;
     let display_handle = XlibDisplayHandle::new(display, screen);
@@ -110,9 +114,17 @@ extern "C" fn init_tetromino(mode_info: *const xlock::ModeInfo) {
     config.enable_dark_mode = true;
 
     let gl_context = context.gl_context();
-    let game = Game::with_config(phys_w, phys_h, &config, gl_context).unwrap();
-
-    state.data = Some((context, game));
+    let (game, clock) = match previous {
+      Some((snapshot, clock)) => {
+        let game = Game::from_snapshot(phys_w, phys_h, &config, &snapshot, gl_context).unwrap();
+        (game, clock)
+      },
+      None => {
+        let game = Game::with_config(phys_w, phys_h, &config, None, gl_context).unwrap();
+        (game, Clock::new(Instant::now()))
+      },
+    };
+    state.data = Some((context, game, clock));
   } else {
     // TODO: We probably still want to be sure to clear the window on
     //       all other screens.
@@ -121,9 +133,9 @@ extern "C" fn init_tetromino(mode_info: *const xlock::ModeInfo) {
 
 /// "Tick" the game.
 fn tick(state: &mut State, force_render: bool) {
-  if let Some((context, game)) = &mut state.data {
-    let now = Instant::now();
-    let (change, _wait) = game.tick(now);
+  if let Some((context, game, clock)) = &mut state.data {
+    let frame = clock.frame(Instant::now());
+    let (change, _wait) = game.tick(frame);
 
     if change == Change::Resize {
       let (phys_w, phys_h) = (None, None);
@@ -180,7 +192,7 @@ extern "C" fn change_tetromino(mode_info: *const xlock::ModeInfo) {
   //         we are sure we have a `State` object set.
   let state = unsafe { lock_struct.userdata.cast::<State>().as_mut().unwrap() };
 
-  if let Some((_context, game)) = &mut state.data {
+  if let Some((_context, game, _clock)) = &mut state.data {
     let _change = game.restart();
   }
 }